@@ -0,0 +1,770 @@
+//! Batch split (`split --manifest <file>`): many named secrets split in one run.
+//!
+//! The manifest lists the secrets, each with its own holders and threshold, as yaml or csv.
+//! Every secret becomes a set of its own with one share per holder. The shares are written per holder,
+//! `<out dir>/<holder>/<secret name>/`, so every holder gets one directory with all of their shares.
+//! The summary report names the secrets, the sets, the holders and the files, never the secrets themselves.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, ConfigError};
+use crate::secret_input::{self, Secret};
+use crate::share_files::{self, ImportedEntry, ShareFileError};
+
+/// Written into --out-dir, next to the directories of the holders
+pub const SUMMARY_FILE: &str = "split-summary.json";
+
+const CSV_COLUMNS: [&str; 6] = ["name", "threshold", "holders", "value", "env", "file"];
+
+/// The line the row starts on, and its cells
+pub type CsvRow = (usize, Vec<String>);
+
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    #[error("Error reading the batch manifest: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+    #[error("Unsupported batch manifest format: {path}. Use .yaml, .yml or .csv")]
+    UnsupportedFormat { path: PathBuf },
+    #[error("Invalid batch manifest: {path}")]
+    InvalidYaml {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+    #[error("Invalid batch manifest: {path}, line {line}: {reason}")]
+    InvalidCsv {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+    #[error("No secrets in the batch manifest: {path}")]
+    NoSecrets { path: PathBuf },
+    #[error("Invalid name: '{name}'. Secrets and holders name directories: letters, digits, spaces, '.', '-' and '_', starting with a letter or a digit")]
+    InvalidName { name: String },
+    #[error("The secret {name} is listed twice")]
+    DuplicateSecret { name: String },
+    #[error("The secret {name} has no holders. Give them in the manifest or with --holder")]
+    NoHolders { name: String },
+    #[error("The holder {holder} is listed twice for the secret {name}")]
+    DuplicateHolder { name: String, holder: String },
+    #[error("The secret {name} needs exactly one of value, env or file")]
+    SecretSource { name: String },
+    #[error("Invalid threshold of the secret {name}")]
+    InvalidThreshold { name: String, source: ConfigError },
+    #[error(
+        "{} of {total} secrets failed, {}:{}",
+        failures.len(),
+        display_split(split),
+        display_failures(failures)
+    )]
+    SecretsFailed {
+        total: usize,
+        /// Names of the secrets split before the others failed
+        split: Vec<String>,
+        failures: Vec<SecretFailure>,
+    },
+}
+
+/// A secret of the batch that couldn't be read or split, and why
+#[derive(Debug)]
+pub struct SecretFailure {
+    pub name: String,
+    pub error: anyhow::Error,
+}
+
+/// Where the secret of an entry is taken from
+#[derive(Debug)]
+pub enum SecretSource {
+    /// The secret itself, written into the manifest
+    Value(String),
+    /// Name of an environment variable
+    Env(String),
+    /// A file, relative to the manifest. Binary files are supported
+    File(PathBuf),
+}
+
+/// A secret of the manifest, with its holders and threshold resolved
+#[derive(Debug)]
+pub struct BatchEntry {
+    pub name: String,
+    pub threshold: usize,
+    pub number_of_shares: usize,
+    /// One per share, or none: the shares of an imported entry without `--holder` are written together
+    pub holders: Vec<String>,
+    pub source: SecretSource,
+    /// `import-passwords`: the entry of the password manager, kept in the shares
+    pub entry: Option<ImportedEntry>,
+}
+
+/// Holders and threshold for the entries that don't give their own:
+/// the top level of a yaml manifest, then `--holder` and the shared secret config
+pub struct Defaults<'a> {
+    pub holders: &'a [String],
+    pub threshold: usize,
+}
+
+/// What a batch split made, without the secrets. Printed and written into [SUMMARY_FILE]
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub secrets: Vec<SecretReport>,
+    pub holders: Vec<HolderBundle>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecretReport {
+    pub name: String,
+    pub set_id: String,
+    pub threshold: usize,
+    pub number_of_shares: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub holders: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<ImportedEntry>,
+    /// Verifiable shares only, see the `check` command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitments_fingerprint: Option<String>,
+    /// Manifest of the holders, see the `holders` command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_file: Option<PathBuf>,
+}
+
+/// Everything one holder gets: a directory with a share of every secret they hold
+#[derive(Debug, Serialize)]
+pub struct HolderBundle {
+    pub holder: String,
+    pub dir: PathBuf,
+    pub secrets: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct YamlManifest {
+    #[serde(default)]
+    holders: Vec<String>,
+    threshold: Option<usize>,
+    secrets: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestEntry {
+    name: String,
+    threshold: Option<usize>,
+    #[serde(default)]
+    holders: Vec<String>,
+    value: Option<String>,
+    env: Option<String>,
+    file: Option<PathBuf>,
+}
+
+impl BatchEntry {
+    pub fn read_secret(&self) -> Result<Secret> {
+        let secret = match &self.source {
+            SecretSource::Value(value) => value.clone(),
+            SecretSource::Env(var_name) => env::var(var_name)
+                .with_context(|| format!("Environment variable {} is not set", var_name))?,
+            SecretSource::File(path) => return secret_input::read_secret_file(path),
+        };
+
+        if secret.is_empty() {
+            bail!("The secret is empty");
+        }
+
+        Ok(Secret::Text(secret))
+    }
+
+    /// Directories of the shares: `<out dir>/<holder>/<secret name>`,
+    /// or all the shares in `<out dir>/<secret name>` without holders
+    pub fn share_dirs(&self, out_dir: &Path) -> Vec<PathBuf> {
+        if self.holders.is_empty() {
+            return vec![out_dir.join(&self.name); self.number_of_shares];
+        }
+
+        self.holders
+            .iter()
+            .map(|holder| out_dir.join(holder).join(&self.name))
+            .collect()
+    }
+}
+
+/// Reads the manifest and checks every entry, nothing is written before the whole manifest is valid
+pub fn read_manifest(path: &Path, defaults: &Defaults) -> Result<Vec<BatchEntry>, BatchError> {
+    let content = fs::read_to_string(path).map_err(|source| BatchError::ReadError {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let (entries, holders, threshold) = match extension.as_deref() {
+        Some("yaml") | Some("yml") => {
+            let manifest: YamlManifest =
+                serde_yaml::from_str(&content).map_err(|source| BatchError::InvalidYaml {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+            let holders = if manifest.holders.is_empty() {
+                defaults.holders.to_vec()
+            } else {
+                manifest.holders
+            };
+            let threshold = manifest.threshold.unwrap_or(defaults.threshold);
+            (manifest.secrets, holders, threshold)
+        }
+        Some("csv") => (
+            csv_entries(path, &content)?,
+            defaults.holders.to_vec(),
+            defaults.threshold,
+        ),
+        _ => {
+            return Err(BatchError::UnsupportedFormat {
+                path: path.to_path_buf(),
+            })
+        }
+    };
+
+    if entries.is_empty() {
+        return Err(BatchError::NoSecrets {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut names = HashSet::new();
+    entries
+        .into_iter()
+        .map(|entry| {
+            if !names.insert(entry.name.clone()) {
+                return Err(BatchError::DuplicateSecret { name: entry.name });
+            }
+            resolve(entry, &holders, threshold, base_dir)
+        })
+        .collect()
+}
+
+fn resolve(
+    entry: ManifestEntry,
+    holders: &[String],
+    threshold: usize,
+    base_dir: &Path,
+) -> Result<BatchEntry, BatchError> {
+    let name = entry.name;
+    check_name(&name)?;
+
+    let holders = if entry.holders.is_empty() {
+        holders.to_vec()
+    } else {
+        entry.holders
+    };
+    if holders.is_empty() {
+        return Err(BatchError::NoHolders { name });
+    }
+    check_holders(&name, &holders)?;
+
+    let threshold = entry.threshold.unwrap_or(threshold);
+    config::validate(&SharedSecretConfig {
+        number_of_shares: holders.len(),
+        threshold,
+    })
+    .map_err(|source| BatchError::InvalidThreshold {
+        name: name.clone(),
+        source,
+    })?;
+
+    let source = match (entry.value, entry.env, entry.file) {
+        (Some(value), None, None) => SecretSource::Value(value),
+        (None, Some(var_name), None) => SecretSource::Env(var_name),
+        (None, None, Some(file)) => SecretSource::File(base_dir.join(file)),
+        _ => return Err(BatchError::SecretSource { name }),
+    };
+
+    Ok(BatchEntry {
+        name,
+        threshold,
+        number_of_shares: holders.len(),
+        holders,
+        source,
+        entry: None,
+    })
+}
+
+/// Holders name the directories of their bundles, each may appear once per secret
+pub fn check_holders(name: &str, holders: &[String]) -> Result<(), BatchError> {
+    let mut seen = HashSet::new();
+    for holder in holders {
+        check_name(holder)?;
+        if !seen.insert(holder) {
+            return Err(BatchError::DuplicateHolder {
+                name: name.to_string(),
+                holder: holder.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<(), BatchError> {
+    let valid = matches!(name.chars().next(), Some(c) if c.is_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '.' | '-' | '_'));
+
+    if !valid {
+        return Err(BatchError::InvalidName {
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// One entry per row after the header. The header names the columns, in any order:
+/// `name` is required, `holders` are separated by ';', empty cells are left out
+fn csv_entries(path: &Path, content: &str) -> Result<Vec<ManifestEntry>, BatchError> {
+    let invalid = |line, reason: String| BatchError::InvalidCsv {
+        path: path.to_path_buf(),
+        line,
+        reason,
+    };
+
+    let mut rows = parse_csv(content).map_err(|(line, reason)| invalid(line, reason))?;
+    if rows.is_empty() {
+        return Ok(vec![]);
+    }
+    let (header_line, header) = rows.remove(0);
+
+    let mut columns = vec![];
+    for column in &header {
+        let column = column.trim().to_lowercase();
+        if !CSV_COLUMNS.contains(&column.as_str()) {
+            return Err(invalid(
+                header_line,
+                format!(
+                    "unknown column '{}', the columns are {}",
+                    column,
+                    CSV_COLUMNS.join(", ")
+                ),
+            ));
+        }
+        if columns.contains(&column) {
+            return Err(invalid(
+                header_line,
+                format!("column '{}' is given twice", column),
+            ));
+        }
+        columns.push(column);
+    }
+    if !columns.iter().any(|column| column == "name") {
+        return Err(invalid(header_line, String::from("no 'name' column")));
+    }
+
+    rows.into_iter()
+        .map(|(line, cells)| {
+            if cells.len() != columns.len() {
+                return Err(invalid(
+                    line,
+                    format!("{} cells, expected {}", cells.len(), columns.len()),
+                ));
+            }
+
+            let mut entry = ManifestEntry::default();
+            for (column, cell) in columns.iter().zip(cells) {
+                let value = cell.trim();
+                if value.is_empty() {
+                    continue;
+                }
+                match column.as_str() {
+                    "name" => entry.name = value.to_string(),
+                    "threshold" => {
+                        let threshold = value.parse().map_err(|_| {
+                            invalid(line, format!("threshold '{}' is not a number", value))
+                        })?;
+                        entry.threshold = Some(threshold);
+                    }
+                    "holders" => {
+                        entry.holders = value
+                            .split(';')
+                            .map(str::trim)
+                            .filter(|holder| !holder.is_empty())
+                            .map(String::from)
+                            .collect()
+                    }
+                    // the secret is taken as is, spaces included
+                    "value" => entry.value = Some(cell),
+                    "env" => entry.env = Some(value.to_string()),
+                    "file" => entry.file = Some(PathBuf::from(value)),
+                    _ => unreachable!("Unknown columns are rejected with the header"),
+                }
+            }
+
+            if entry.name.is_empty() {
+                return Err(invalid(line, String::from("the name is empty")));
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Rows of cells with the line each row starts on. Cells are separated by commas,
+/// quoted cells can contain commas, line breaks and doubled quotes. Empty lines are skipped
+pub fn parse_csv(content: &str) -> Result<Vec<CsvRow>, (usize, String)> {
+    let mut rows = vec![];
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut row_line = 1;
+
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => quoted = false,
+                _ => cell.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if cell.is_empty() => quoted = true,
+            ',' => cells.push(std::mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                cells.push(std::mem::take(&mut cell));
+                let row = std::mem::take(&mut cells);
+                if !(row.len() == 1 && row[0].is_empty()) {
+                    rows.push((row_line, row));
+                }
+                row_line = line;
+            }
+            _ => cell.push(c),
+        }
+    }
+
+    if quoted {
+        return Err((row_line, String::from("a quoted cell is never closed")));
+    }
+    if !cell.is_empty() || !cells.is_empty() {
+        cells.push(cell);
+        rows.push((row_line, cells));
+    }
+
+    Ok(rows)
+}
+
+/// The holders in the order they first appear in the manifest, each with the secrets they hold
+pub fn holder_bundles(entries: &[BatchEntry], out_dir: &Path) -> Vec<HolderBundle> {
+    let mut bundles: Vec<HolderBundle> = vec![];
+    for entry in entries {
+        for holder in &entry.holders {
+            let index = bundles
+                .iter()
+                .position(|bundle| &bundle.holder == holder)
+                .unwrap_or_else(|| {
+                    bundles.push(HolderBundle {
+                        holder: holder.clone(),
+                        dir: out_dir.join(holder),
+                        secrets: vec![],
+                    });
+                    bundles.len() - 1
+                });
+            bundles[index].secrets.push(entry.name.clone());
+        }
+    }
+    bundles
+}
+
+fn display_split(split: &[String]) -> String {
+    if split.is_empty() {
+        return String::from("nothing was split");
+    }
+    format!("split anyway: {}", split.join(", "))
+}
+
+fn display_failures(failures: &[SecretFailure]) -> String {
+    failures
+        .iter()
+        .map(|failure| format!("\n  {}: {:#}", failure.name, failure.error))
+        .collect()
+}
+
+/// A batch never replaces the summary of an earlier one, checked before any share is written
+pub fn check_summary(out_dir: &Path) -> Result<(), ShareFileError> {
+    let path = out_dir.join(SUMMARY_FILE);
+    if path.exists() {
+        return Err(ShareFileError::AlreadyExists { path });
+    }
+    Ok(())
+}
+
+/// Writes the report next to the bundles of the holders
+pub fn write_summary(out_dir: &Path, report: &BatchReport) -> Result<PathBuf, ShareFileError> {
+    let path = out_dir.join(SUMMARY_FILE);
+    // the report is a plain struct of numbers and strings, serialization can't fail
+    let report_json = serde_json::to_string_pretty(report).expect("Error serializing report");
+    share_files::write_new_file(&path, report_json.as_bytes())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::share_files::SplitOutputArgs;
+
+    #[derive(Parser)]
+    struct TestArgs {
+        #[clap(flatten)]
+        output: SplitOutputArgs,
+    }
+
+    const DEFAULTS: Defaults = Defaults {
+        holders: &[],
+        threshold: 2,
+    };
+
+    fn write_manifest(dir: &Path, file_name: &str, content: &str) -> PathBuf {
+        let path = dir.join(file_name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn quoted_csv_cells() {
+        let rows = parse_csv(
+            "name,value\r\n\"a, b\",\"say \"\"hi\"\"\"\r\n\r\nmultiline,\"one\ntwo\"\nlast,x",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rows,
+            vec![
+                (1, vec![String::from("name"), String::from("value")]),
+                (2, vec![String::from("a, b"), String::from("say \"hi\"")]),
+                (4, vec![String::from("multiline"), String::from("one\ntwo")]),
+                (6, vec![String::from("last"), String::from("x")]),
+            ]
+        );
+
+        assert_eq!(
+            parse_csv("name,value\nbroken,\"never closed\n")
+                .unwrap_err()
+                .0,
+            2
+        );
+    }
+
+    #[test]
+    fn csv_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_manifest(
+            dir.path(),
+            "secrets.csv",
+            "name,holders,threshold,value\n\
+             wifi,\"alice; bob jr.;carol\",2,\" spaced, \"\"quoted\"\" \"\n\
+             vault,alice;bob,,secret\n",
+        );
+
+        let entries = read_manifest(&path, &DEFAULTS).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].name, "wifi");
+        assert_eq!(entries[0].holders, ["alice", "bob jr.", "carol"]);
+        assert_eq!(entries[0].threshold, 2);
+        assert_eq!(entries[0].number_of_shares, 3);
+        assert!(
+            matches!(&entries[0].source, SecretSource::Value(value) if value == " spaced, \"quoted\" ")
+        );
+
+        assert_eq!(entries[1].holders, ["alice", "bob"]);
+        assert_eq!(entries[1].threshold, DEFAULTS.threshold);
+    }
+
+    #[test]
+    fn duplicate_secret_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_manifest(
+            dir.path(),
+            "secrets.yaml",
+            "holders: [alice, bob]\n\
+             secrets:\n  \
+               - {name: wifi, value: one}\n  \
+               - {name: wifi, value: two}\n",
+        );
+
+        let err = read_manifest(&path, &DEFAULTS).unwrap_err();
+        assert!(matches!(err, BatchError::DuplicateSecret { name } if name == "wifi"));
+    }
+
+    #[test]
+    fn names_outside_the_out_dir_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = write_manifest(
+            dir.path(),
+            "holder.yaml",
+            "secrets:\n  - {name: wifi, holders: [alice, ../x], value: one}\n",
+        );
+        let err = read_manifest(&path, &DEFAULTS).unwrap_err();
+        assert!(matches!(err, BatchError::InvalidName { name } if name == "../x"));
+
+        let path = write_manifest(
+            dir.path(),
+            "secret.csv",
+            "name,holders,value\n../x,alice;bob,one\n",
+        );
+        let err = read_manifest(&path, &DEFAULTS).unwrap_err();
+        assert!(matches!(err, BatchError::InvalidName { name } if name == "../x"));
+    }
+
+    #[test]
+    fn holders_given_on_the_command_line_are_checked() {
+        let holders = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        check_holders("wifi", &holders(&["alice", "bob jr."])).unwrap();
+
+        let err = check_holders("wifi", &holders(&["alice", "../../x"])).unwrap_err();
+        assert!(matches!(err, BatchError::InvalidName { name } if name == "../../x"));
+
+        let err = check_holders("wifi", &holders(&["alice", "bob", "alice"])).unwrap_err();
+        assert!(matches!(
+            err,
+            BatchError::DuplicateHolder { name, holder } if name == "wifi" && holder == "alice"
+        ));
+    }
+
+    #[test]
+    fn every_failed_secret_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = dir.path().join("out");
+        let path = write_manifest(
+            dir.path(),
+            "secrets.yaml",
+            "holders: [alice, bob, carol]\n\
+             secrets:\n  \
+               - {name: wifi, value: one}\n  \
+               - {name: vault, value: two}\n  \
+               - {name: bank, value: three}\n",
+        );
+        let entries = read_manifest(&path, &DEFAULTS).unwrap();
+        let output = TestArgs::parse_from(["split", "--out-dir", out_dir.to_str().unwrap()]).output;
+
+        // a share left over from an earlier run is never overwritten
+        let existing_dir = out_dir.join("bob").join("vault");
+        fs::create_dir_all(&existing_dir).unwrap();
+        fs::write(existing_dir.join("share.json"), "{}").unwrap();
+
+        let err = crate::split_batch(&entries, &output, None, None).unwrap_err();
+        let message = format!("{:#}", err);
+        match err.downcast::<BatchError>().unwrap() {
+            BatchError::SecretsFailed {
+                total,
+                split,
+                failures,
+            } => {
+                assert_eq!(total, 3);
+                assert_eq!(split, ["wifi", "bank"]);
+                assert_eq!(failures.len(), 1);
+                assert_eq!(failures[0].name, "vault");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+        assert!(message.starts_with("1 of 3 secrets failed, split anyway: wifi, bank:\n  vault: "));
+        assert!(out_dir.join("carol").join("bank").is_dir());
+    }
+
+    #[test]
+    fn unreadable_secrets_stop_the_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = dir.path().join("out");
+        let path = write_manifest(
+            dir.path(),
+            "secrets.yaml",
+            "holders: [alice, bob]\n\
+             secrets:\n  \
+               - {name: wifi, value: one}\n  \
+               - {name: vault, env: META_SECRET_BATCH_TEST_UNSET}\n  \
+               - {name: bank, file: missing.txt}\n",
+        );
+        let entries = read_manifest(&path, &DEFAULTS).unwrap();
+        let output = TestArgs::parse_from(["split", "--out-dir", out_dir.to_str().unwrap()]).output;
+
+        let err = crate::split_batch(&entries, &output, None, None).unwrap_err();
+        match err.downcast::<BatchError>().unwrap() {
+            BatchError::SecretsFailed {
+                total,
+                split,
+                failures,
+            } => {
+                assert_eq!(total, 3);
+                assert!(split.is_empty());
+                let names: Vec<&str> = failures
+                    .iter()
+                    .map(|failure| failure.name.as_str())
+                    .collect();
+                assert_eq!(names, ["vault", "bank"]);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+        assert!(!out_dir.exists());
+    }
+
+    #[test]
+    fn summary_is_never_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = |name: &str| BatchReport {
+            secrets: vec![SecretReport {
+                name: name.to_string(),
+                set_id: String::from("set"),
+                threshold: 2,
+                number_of_shares: 2,
+                holders: vec![String::from("alice"), String::from("bob")],
+                entry: None,
+                commitments_fingerprint: None,
+                manifest_file: None,
+            }],
+            holders: vec![],
+        };
+
+        check_summary(dir.path()).unwrap();
+        let path = write_summary(dir.path(), &report("wifi")).unwrap();
+        assert_eq!(path, dir.path().join(SUMMARY_FILE));
+
+        // a second batch into the same directory stops before splitting anything
+        let err = check_summary(dir.path()).unwrap_err();
+        assert!(
+            matches!(&err, ShareFileError::AlreadyExists { path: existing } if *existing == path)
+        );
+        let exit_code = crate::output::report(
+            crate::output::OutputFormat::Json,
+            Err(anyhow::Error::new(err)),
+        );
+        assert_eq!(exit_code, crate::output::ExitCode::Io as i32);
+
+        let err = write_summary(dir.path(), &report("vault")).unwrap_err();
+        assert!(matches!(err, ShareFileError::AlreadyExists { .. }));
+        assert!(fs::read_to_string(&path).unwrap().contains("wifi"));
+    }
+}
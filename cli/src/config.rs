@@ -0,0 +1,878 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use clap::{Args, Subcommand};
+use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::holders::HolderConfig;
+use crate::policy::{PolicyError, PolicyGroup, SetPolicy};
+use crate::recipients::{self, Recipient, RecipientError};
+
+/// Environment variable with a path to the config file
+pub const CONFIG_ENV_VAR: &str = "META_SECRET_CONFIG";
+/// Config file names looked up in the config directories, in order of preference
+const CONFIG_FILE_NAMES: [&str; 4] = ["config.yaml", "config.yml", "config.toml", "config.json"];
+
+/// Shares are points of a polynomial over GF(256), so there are only 255 distinct non-zero x coordinates
+pub const MAX_NUMBER_OF_SHARES: usize = 255;
+/// A threshold of one would mean that every single share is the secret itself
+pub const MIN_THRESHOLD: usize = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetaSecretConfig {
+    pub shared_secret: SharedSecretConfig,
+
+    /// Named alternatives to the default `shared_secret` config, selected by `split --profile <name>`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, SharedSecretConfig>,
+
+    /// Public keys (age or SSH) by holder name, `split --recipient <name>` encrypts the share to the key
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub recipients: BTreeMap<String, String>,
+
+    /// Share holders by name, `split --holder <name>` assigns the shares to them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub holders: BTreeMap<String, HolderConfig>,
+
+    /// Weighted and nested threshold policies by name, `split --policy <name>` gives a share to every holder of the policy
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub policies: BTreeMap<String, PolicyGroup>,
+}
+
+impl Default for MetaSecretConfig {
+    fn default() -> Self {
+        Self {
+            shared_secret: SharedSecretConfig {
+                number_of_shares: 3,
+                threshold: 2,
+            },
+            profiles: BTreeMap::new(),
+            recipients: BTreeMap::new(),
+            holders: BTreeMap::new(),
+            policies: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Write the default config into the user config directory (or into the --config path)
+    Init {
+        /// Overwrite the config file if it already exists
+        #[clap(long)]
+        force: bool,
+    },
+    /// Print the effective config and the place it was loaded from
+    Show,
+}
+
+/// Per-invocation overrides of the shared secret config
+#[derive(Debug, Args)]
+pub struct SharedSecretArgs {
+    /// Use a named profile from the `profiles` section of the config instead of `shared_secret`
+    #[clap(short, long)]
+    profile: Option<String>,
+
+    /// Total number of shares to generate
+    #[clap(long)]
+    shares: Option<usize>,
+
+    /// Number of shares required to restore the secret
+    #[clap(short, long)]
+    threshold: Option<usize>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Error reading config file: {path}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Error parsing config file: {path}. {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("Unsupported config format: {path}. Use .yaml, .yml, .toml or .json")]
+    UnsupportedFormat { path: PathBuf },
+    #[error("Config file already exists: {path}. Use --force to overwrite it")]
+    AlreadyExists { path: PathBuf },
+    #[error("Can't find user config directory. Please set $XDG_CONFIG_HOME or use --config")]
+    NoConfigDir,
+    #[error("Error writing config file: {path}")]
+    Write { path: PathBuf, source: io::Error },
+    #[error("Unknown profile: {name}. Available profiles: {available:?}")]
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error("Invalid profile: {name}")]
+    InvalidProfile {
+        name: String,
+        source: Box<ConfigError>,
+    },
+    #[error("Unknown recipient: {name}. Add it to the `recipients` section of the config (or a public key to the holder) or use --recipient <NAME>=<PUBLIC KEY>. Available recipients: {available:?}")]
+    UnknownRecipient {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error(transparent)]
+    InvalidRecipient(#[from] RecipientError),
+    #[error("Unknown policy: {name}. Available policies: {available:?}")]
+    UnknownPolicy {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error("Invalid policy: {name}")]
+    InvalidPolicy { name: String, source: PolicyError },
+    #[error("Threshold ({threshold}) can't be less than {MIN_THRESHOLD}")]
+    ThresholdTooLow { threshold: usize },
+    #[error(
+        "Threshold ({threshold}) can't be greater than the number of shares ({number_of_shares})"
+    )]
+    ThresholdExceedsShares {
+        threshold: usize,
+        number_of_shares: usize,
+    },
+    #[error("Number of shares ({number_of_shares}) can't be greater than {MAX_NUMBER_OF_SHARES}")]
+    TooManyShares { number_of_shares: usize },
+}
+
+/// Where the effective config came from
+#[derive(Debug)]
+pub enum ConfigSource {
+    CommandLine(PathBuf),
+    EnvVar(PathBuf),
+    UserConfigDir(PathBuf),
+    WorkingDir(PathBuf),
+    BuiltIn,
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::CommandLine(path) => write!(f, "{} (--config)", path.display()),
+            ConfigSource::EnvVar(path) => write!(f, "{} (${})", path.display(), CONFIG_ENV_VAR),
+            ConfigSource::UserConfigDir(path) => write!(f, "{}", path.display()),
+            ConfigSource::WorkingDir(path) => write!(f, "{} (working directory)", path.display()),
+            ConfigSource::BuiltIn => write!(f, "built-in defaults"),
+        }
+    }
+}
+
+pub struct LoadedConfig {
+    pub config: MetaSecretConfig,
+    pub source: ConfigSource,
+}
+
+/// Finds and loads the config. Lookup order:
+///  - `--config <path>`
+///  - `$META_SECRET_CONFIG`
+///  - `$XDG_CONFIG_HOME/meta-secret/config.{yaml,yml,toml,json}` (`~/.config` if XDG_CONFIG_HOME is not set)
+///  - `config.yaml` in the working directory (the way the cli used to work)
+///  - built-in defaults
+pub fn load(config_path: Option<&Path>) -> Result<LoadedConfig, ConfigError> {
+    let source = find_config_file(config_path);
+
+    let config = match &source {
+        ConfigSource::CommandLine(path)
+        | ConfigSource::EnvVar(path)
+        | ConfigSource::UserConfigDir(path)
+        | ConfigSource::WorkingDir(path) => read_config_file(path)?,
+        ConfigSource::BuiltIn => MetaSecretConfig::default(),
+    };
+
+    config.validate()?;
+    Ok(LoadedConfig { config, source })
+}
+
+fn find_config_file(config_path: Option<&Path>) -> ConfigSource {
+    if let Some(path) = config_path {
+        return ConfigSource::CommandLine(path.to_path_buf());
+    }
+
+    if let Some(path) = env::var_os(CONFIG_ENV_VAR).filter(|path| !path.is_empty()) {
+        return ConfigSource::EnvVar(PathBuf::from(path));
+    }
+
+    if let Some(config_dir) = user_config_dir() {
+        let config_file = CONFIG_FILE_NAMES
+            .iter()
+            .map(|file_name| config_dir.join(file_name))
+            .find(|path| path.is_file());
+
+        if let Some(path) = config_file {
+            return ConfigSource::UserConfigDir(path);
+        }
+    }
+
+    let working_dir_config = PathBuf::from("config.yaml");
+    if working_dir_config.is_file() {
+        return ConfigSource::WorkingDir(working_dir_config);
+    }
+
+    ConfigSource::BuiltIn
+}
+
+/// `$XDG_CONFIG_HOME/meta-secret`, or `~/.config/meta-secret`
+fn user_config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+
+    Some(config_home.join("meta-secret"))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("yaml");
+
+        match extension.to_lowercase().as_str() {
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            "toml" => Ok(ConfigFormat::Toml),
+            "json" => Ok(ConfigFormat::Json),
+            _ => Err(ConfigError::UnsupportedFormat {
+                path: path.to_path_buf(),
+            }),
+        }
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<MetaSecretConfig, ConfigError> {
+    let format = ConfigFormat::from_path(path)?;
+
+    let content = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let parse_result = match format {
+        ConfigFormat::Yaml => serde_yaml::from_str(&content).map_err(|err| err.to_string()),
+        ConfigFormat::Toml => toml::from_str(&content).map_err(|err| err.to_string()),
+        ConfigFormat::Json => serde_json::from_str(&content).map_err(|err| err.to_string()),
+    };
+
+    parse_result.map_err(|message| ConfigError::Parse {
+        path: path.to_path_buf(),
+        message,
+    })
+}
+
+/// Writes the default config for `config init`, returns the path of the new file
+pub fn init(config_path: Option<&Path>, force: bool) -> Result<PathBuf, ConfigError> {
+    let path = match config_path {
+        Some(path) => path.to_path_buf(),
+        None => user_config_dir()
+            .ok_or(ConfigError::NoConfigDir)?
+            .join(CONFIG_FILE_NAMES[0]),
+    };
+
+    if path.exists() && !force {
+        return Err(ConfigError::AlreadyExists { path });
+    }
+
+    let content = MetaSecretConfig::default().to_string_in(ConfigFormat::from_path(&path)?);
+
+    let write_result = match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => fs::create_dir_all(dir).and_then(|_| fs::write(&path, content)),
+        None => fs::write(&path, content),
+    };
+
+    write_result.map_err(|source| ConfigError::Write {
+        path: path.clone(),
+        source,
+    })?;
+
+    Ok(path)
+}
+
+impl MetaSecretConfig {
+    pub fn to_yaml(&self) -> String {
+        self.to_string_in(ConfigFormat::Yaml)
+    }
+
+    fn to_string_in(&self, format: ConfigFormat) -> String {
+        // serialization of plain structs and maps with string keys can't fail
+        match format {
+            ConfigFormat::Yaml => serde_yaml::to_string(self).expect("Error serializing config"),
+            ConfigFormat::Toml => toml::to_string(self).expect("Error serializing config"),
+            ConfigFormat::Json => {
+                serde_json::to_string_pretty(self).expect("Error serializing config")
+            }
+        }
+    }
+
+    /// Checks the default config, all the profiles and policies and the public keys of the recipients and the holders
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        validate(&self.shared_secret)?;
+        for (name, profile) in &self.profiles {
+            validate(profile).map_err(|err| ConfigError::InvalidProfile {
+                name: name.clone(),
+                source: Box::new(err),
+            })?;
+        }
+        for (name, policy) in &self.policies {
+            policy
+                .validate()
+                .map_err(|source| ConfigError::InvalidPolicy {
+                    name: name.clone(),
+                    source,
+                })?;
+        }
+        for recipient in self.known_recipients() {
+            recipients::validate(&recipient)?;
+        }
+        Ok(())
+    }
+
+    /// Recipients of the `recipients` section and the holders with a public key
+    fn known_recipients(&self) -> Vec<Recipient> {
+        let holders = self.holders.iter().filter_map(|(name, holder)| {
+            Some(Recipient {
+                name: name.clone(),
+                public_key: holder.public_key.clone()?,
+            })
+        });
+        let recipients = self.recipients.iter().map(|(name, public_key)| Recipient {
+            name: name.clone(),
+            public_key: public_key.clone(),
+        });
+
+        holders.chain(recipients).collect()
+    }
+
+    /// Resolves `split --recipient` arguments: names of the holders with a public key,
+    /// names of the `recipients` section or `<name>=<public key>`
+    pub fn recipients(&self, args: &[String]) -> Result<Vec<Recipient>, ConfigError> {
+        let known_recipients = self.known_recipients();
+        args.iter()
+            .map(|arg| {
+                let recipient = match arg.split_once('=') {
+                    Some((name, public_key)) => Recipient {
+                        name: name.trim().to_string(),
+                        public_key: public_key.trim().to_string(),
+                    },
+                    None => known_recipients
+                        .iter()
+                        .find(|recipient| recipient.name == *arg)
+                        .cloned()
+                        .ok_or_else(|| ConfigError::UnknownRecipient {
+                            name: arg.clone(),
+                            available: known_recipients
+                                .iter()
+                                .map(|recipient| recipient.name.clone())
+                                .collect(),
+                        })?,
+                };
+
+                recipients::validate(&recipient)?;
+                Ok(recipient)
+            })
+            .collect()
+    }
+
+    /// Resolves the config to split with: the default one or the selected profile,
+    /// with the command line overrides applied on top
+    pub fn shared_secret_config(
+        &self,
+        args: &SharedSecretArgs,
+    ) -> Result<SharedSecretConfig, ConfigError> {
+        let base = self.base_config(args)?;
+        let config = SharedSecretConfig {
+            number_of_shares: args.shares.unwrap_or(base.number_of_shares),
+            threshold: args.threshold.unwrap_or(base.threshold),
+        };
+
+        validate(&config)?;
+        Ok(config)
+    }
+
+    /// Only the threshold of the config to split with, for `split --manifest`:
+    /// the number of shares of every secret of the batch is the number of its holders
+    pub fn threshold(&self, args: &SharedSecretArgs) -> Result<usize, ConfigError> {
+        Ok(args.threshold.unwrap_or(self.base_config(args)?.threshold))
+    }
+
+    fn base_config(&self, args: &SharedSecretArgs) -> Result<SharedSecretConfig, ConfigError> {
+        match &args.profile {
+            None => Ok(self.shared_secret),
+            Some(name) => {
+                self.profiles
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| ConfigError::UnknownProfile {
+                        name: name.clone(),
+                        available: self.profiles.keys().cloned().collect(),
+                    })
+            }
+        }
+    }
+
+    /// Policy of the `policies` section to split with (`split --policy <name>`)
+    pub fn policy(&self, name: &str) -> Result<SetPolicy, ConfigError> {
+        let root = self
+            .policies
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownPolicy {
+                name: name.to_string(),
+                available: self.policies.keys().cloned().collect(),
+            })?;
+
+        Ok(SetPolicy {
+            name: name.to_string(),
+            root: root.clone(),
+        })
+    }
+}
+
+pub fn validate(config: &SharedSecretConfig) -> Result<(), ConfigError> {
+    if config.threshold < MIN_THRESHOLD {
+        return Err(ConfigError::ThresholdTooLow {
+            threshold: config.threshold,
+        });
+    }
+
+    if config.number_of_shares > MAX_NUMBER_OF_SHARES {
+        return Err(ConfigError::TooManyShares {
+            number_of_shares: config.number_of_shares,
+        });
+    }
+
+    if config.threshold > config.number_of_shares {
+        return Err(ConfigError::ThresholdExceedsShares {
+            threshold: config.threshold,
+            number_of_shares: config.number_of_shares,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, MutexGuard};
+
+    use super::*;
+
+    /// The config lookup reads the environment and the working directory of the whole process
+    static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    /// The lookup environment of a test: `$XDG_CONFIG_HOME` and the working directory in a temp dir,
+    /// `$META_SECRET_CONFIG` unset. Everything is restored on drop
+    struct LookupEnv {
+        dir: tempfile::TempDir,
+        working_dir: PathBuf,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl LookupEnv {
+        fn new() -> Self {
+            let lock = ENVIRONMENT
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let dir = tempfile::tempdir().unwrap();
+            fs::create_dir_all(dir.path().join("xdg").join("meta-secret")).unwrap();
+            fs::create_dir_all(dir.path().join("work")).unwrap();
+
+            let working_dir = env::current_dir().unwrap();
+            env::set_current_dir(dir.path().join("work")).unwrap();
+            env::set_var("XDG_CONFIG_HOME", dir.path().join("xdg"));
+            env::remove_var(CONFIG_ENV_VAR);
+
+            LookupEnv {
+                dir,
+                working_dir,
+                _lock: lock,
+            }
+        }
+
+        fn user_config(&self, file_name: &str) -> PathBuf {
+            self.dir
+                .path()
+                .join("xdg")
+                .join("meta-secret")
+                .join(file_name)
+        }
+
+        fn working_dir_config(&self) -> PathBuf {
+            self.dir.path().join("work").join("config.yaml")
+        }
+
+        fn file(&self, file_name: &str) -> PathBuf {
+            self.dir.path().join(file_name)
+        }
+    }
+
+    impl Drop for LookupEnv {
+        fn drop(&mut self) {
+            env::set_current_dir(&self.working_dir).unwrap();
+            env::remove_var("XDG_CONFIG_HOME");
+            env::remove_var(CONFIG_ENV_VAR);
+        }
+    }
+
+    /// A yaml config with `number_of_shares` shares and a threshold of 2
+    fn write_config(path: &Path, number_of_shares: usize) {
+        let content = format!(
+            "shared_secret:\n  number_of_shares: {}\n  threshold: 2\n",
+            number_of_shares
+        );
+        fs::write(path, content).unwrap();
+    }
+
+    fn loaded_shares(config_path: Option<&Path>) -> (usize, String) {
+        let loaded = load(config_path).unwrap();
+        let source = match loaded.source {
+            ConfigSource::CommandLine(_) => "command line",
+            ConfigSource::EnvVar(_) => "env var",
+            ConfigSource::UserConfigDir(_) => "user config dir",
+            ConfigSource::WorkingDir(_) => "working dir",
+            ConfigSource::BuiltIn => "built-in",
+        };
+        (
+            loaded.config.shared_secret.number_of_shares,
+            source.to_string(),
+        )
+    }
+
+    fn shared_secret(number_of_shares: usize, threshold: usize) -> SharedSecretConfig {
+        SharedSecretConfig {
+            number_of_shares,
+            threshold,
+        }
+    }
+
+    fn args(
+        profile: Option<&str>,
+        shares: Option<usize>,
+        threshold: Option<usize>,
+    ) -> SharedSecretArgs {
+        SharedSecretArgs {
+            profile: profile.map(String::from),
+            shares,
+            threshold,
+        }
+    }
+
+    fn config_with_profiles() -> MetaSecretConfig {
+        let mut config = MetaSecretConfig::default();
+        config
+            .profiles
+            .insert(String::from("family"), shared_secret(5, 3));
+        config
+            .profiles
+            .insert(String::from("team"), shared_secret(7, 4));
+        config
+    }
+
+    #[test]
+    fn valid_configs() {
+        validate(&shared_secret(2, 2)).unwrap();
+        validate(&shared_secret(3, 2)).unwrap();
+        validate(&shared_secret(MAX_NUMBER_OF_SHARES, MAX_NUMBER_OF_SHARES)).unwrap();
+        MetaSecretConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn threshold_below_the_minimum() {
+        for threshold in [0, 1] {
+            let err = validate(&shared_secret(3, threshold)).unwrap_err();
+            assert!(matches!(err, ConfigError::ThresholdTooLow { threshold: t } if t == threshold));
+        }
+    }
+
+    #[test]
+    fn threshold_above_the_number_of_shares() {
+        let err = validate(&shared_secret(3, 4)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::ThresholdExceedsShares {
+                threshold: 4,
+                number_of_shares: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn too_many_shares() {
+        let err = validate(&shared_secret(MAX_NUMBER_OF_SHARES + 1, 2)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::TooManyShares {
+                number_of_shares: 256
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_profile() {
+        let mut config = config_with_profiles();
+        config
+            .profiles
+            .insert(String::from("broken"), shared_secret(2, 3));
+
+        let err = config.validate().unwrap_err();
+        match err {
+            ConfigError::InvalidProfile { name, source } => {
+                assert_eq!(name, "broken");
+                assert!(matches!(
+                    *source,
+                    ConfigError::ThresholdExceedsShares { .. }
+                ));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn unknown_profile() {
+        let config = config_with_profiles();
+
+        let err = config
+            .shared_secret_config(&args(Some("friends"), None, None))
+            .unwrap_err();
+        match err {
+            ConfigError::UnknownProfile { name, available } => {
+                assert_eq!(name, "friends");
+                assert_eq!(available, ["family", "team"]);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+
+        assert!(matches!(
+            config.threshold(&args(Some("friends"), None, Some(2))),
+            Err(ConfigError::UnknownProfile { .. })
+        ));
+    }
+
+    #[test]
+    fn profiles_and_overrides() {
+        let config = config_with_profiles();
+
+        let resolved = config
+            .shared_secret_config(&args(None, None, None))
+            .unwrap();
+        assert_eq!((resolved.number_of_shares, resolved.threshold), (3, 2));
+
+        let resolved = config
+            .shared_secret_config(&args(Some("family"), None, None))
+            .unwrap();
+        assert_eq!((resolved.number_of_shares, resolved.threshold), (5, 3));
+
+        // the command line beats the profile, value by value
+        let resolved = config
+            .shared_secret_config(&args(Some("family"), None, Some(4)))
+            .unwrap();
+        assert_eq!((resolved.number_of_shares, resolved.threshold), (5, 4));
+
+        let resolved = config
+            .shared_secret_config(&args(Some("team"), Some(9), None))
+            .unwrap();
+        assert_eq!((resolved.number_of_shares, resolved.threshold), (9, 4));
+
+        let resolved = config
+            .shared_secret_config(&args(Some("team"), Some(10), Some(6)))
+            .unwrap();
+        assert_eq!((resolved.number_of_shares, resolved.threshold), (10, 6));
+
+        assert_eq!(
+            config.threshold(&args(Some("team"), None, None)).unwrap(),
+            4
+        );
+        assert_eq!(
+            config
+                .threshold(&args(Some("team"), None, Some(3)))
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn overrides_are_validated() {
+        let config = config_with_profiles();
+
+        assert!(matches!(
+            config.shared_secret_config(&args(Some("family"), Some(2), None)),
+            Err(ConfigError::ThresholdExceedsShares {
+                threshold: 3,
+                number_of_shares: 2
+            })
+        ));
+        assert!(matches!(
+            config.shared_secret_config(&args(None, None, Some(1))),
+            Err(ConfigError::ThresholdTooLow { threshold: 1 })
+        ));
+        assert!(matches!(
+            config.shared_secret_config(&args(None, Some(300), None)),
+            Err(ConfigError::TooManyShares {
+                number_of_shares: 300
+            })
+        ));
+    }
+
+    #[test]
+    fn lookup_order() {
+        let lookup = LookupEnv::new();
+        let command_line = lookup.file("command-line.yaml");
+        let env_var = lookup.file("env-var.yaml");
+        write_config(&command_line, 4);
+        write_config(&env_var, 5);
+        write_config(&lookup.user_config("config.yaml"), 6);
+        write_config(&lookup.working_dir_config(), 7);
+        env::set_var(CONFIG_ENV_VAR, &env_var);
+
+        let lookup_steps = [
+            (Some(command_line.as_path()), 4, "command line"),
+            (None, 5, "env var"),
+            (None, 6, "user config dir"),
+            (None, 7, "working dir"),
+            (None, 3, "built-in"),
+        ];
+        for (config_path, number_of_shares, source) in lookup_steps {
+            assert_eq!(
+                loaded_shares(config_path),
+                (number_of_shares, source.to_string())
+            );
+
+            // every step is found once the steps before it are gone
+            match source {
+                "env var" => env::remove_var(CONFIG_ENV_VAR),
+                "user config dir" => fs::remove_file(lookup.user_config("config.yaml")).unwrap(),
+                "working dir" => fs::remove_file(lookup.working_dir_config()).unwrap(),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn empty_env_var_is_ignored() {
+        let lookup = LookupEnv::new();
+        write_config(&lookup.working_dir_config(), 7);
+        env::set_var(CONFIG_ENV_VAR, "");
+
+        assert_eq!(loaded_shares(None), (7, String::from("working dir")));
+    }
+
+    #[test]
+    fn user_config_file_names() {
+        let lookup = LookupEnv::new();
+        fs::write(
+            lookup.user_config("config.json"),
+            r#"{"shared_secret": {"number_of_shares": 8, "threshold": 2}}"#,
+        )
+        .unwrap();
+        assert_eq!(loaded_shares(None), (8, String::from("user config dir")));
+
+        fs::write(
+            lookup.user_config("config.toml"),
+            "[shared_secret]\nnumber_of_shares = 9\nthreshold = 2\n",
+        )
+        .unwrap();
+        assert_eq!(loaded_shares(None), (9, String::from("user config dir")));
+
+        write_config(&lookup.user_config("config.yml"), 10);
+        assert_eq!(loaded_shares(None), (10, String::from("user config dir")));
+    }
+
+    #[test]
+    fn toml_and_json_configs() {
+        let lookup = LookupEnv::new();
+
+        let toml_path = lookup.file("config.toml");
+        fs::write(
+            &toml_path,
+            "[shared_secret]\n\
+             number_of_shares = 5\n\
+             threshold = 3\n\
+             \n\
+             [profiles.team]\n\
+             number_of_shares = 7\n\
+             threshold = 4\n",
+        )
+        .unwrap();
+        let config = read_config_file(&toml_path).unwrap();
+        assert_eq!(config.shared_secret.number_of_shares, 5);
+        assert_eq!(config.shared_secret.threshold, 3);
+        assert_eq!(config.profiles["team"].number_of_shares, 7);
+
+        let json_path = lookup.file("config.JSON");
+        fs::write(
+            &json_path,
+            r#"{
+                "shared_secret": {"number_of_shares": 4, "threshold": 2},
+                "profiles": {"family": {"number_of_shares": 6, "threshold": 3}}
+            }"#,
+        )
+        .unwrap();
+        let config = read_config_file(&json_path).unwrap();
+        assert_eq!(config.shared_secret.number_of_shares, 4);
+        assert_eq!(config.profiles["family"].threshold, 3);
+
+        // what `config init` writes is read back the same
+        for file_name in ["init.yaml", "init.toml", "init.json"] {
+            let path = init(Some(&lookup.file(file_name)), false).unwrap();
+            let config = read_config_file(&path).unwrap();
+            assert_eq!(config.shared_secret.number_of_shares, 3);
+            assert_eq!(config.shared_secret.threshold, 2);
+        }
+    }
+
+    #[test]
+    fn invalid_config_files() {
+        let lookup = LookupEnv::new();
+
+        let missing = lookup.file("missing.yaml");
+        match load(Some(&missing)) {
+            Err(ConfigError::Read { path, source }) => {
+                assert_eq!(path, missing);
+                assert_eq!(source.kind(), io::ErrorKind::NotFound);
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("a missing --config file is an error"),
+        }
+
+        let ini = lookup.file("config.ini");
+        fs::write(&ini, "shares = 3").unwrap();
+        assert!(matches!(
+            load(Some(&ini)),
+            Err(ConfigError::UnsupportedFormat { .. })
+        ));
+
+        let unknown_field = lookup.file("unknown.toml");
+        fs::write(
+            &unknown_field,
+            "[shared_secret]\nnumber_of_shares = 3\nthreshold = 2\n[unknown]\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            load(Some(&unknown_field)),
+            Err(ConfigError::Parse { .. })
+        ));
+
+        let invalid = lookup.file("invalid.json");
+        fs::write(
+            &invalid,
+            r#"{"shared_secret": {"number_of_shares": 3, "threshold": 5}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            load(Some(&invalid)),
+            Err(ConfigError::ThresholdExceedsShares { .. })
+        ));
+
+        // a broken config in a later step of the lookup is never read
+        fs::write(lookup.working_dir_config(), "shared_secret: [").unwrap();
+        write_config(&lookup.user_config("config.yaml"), 4);
+        assert_eq!(loaded_shares(None), (4, String::from("user config dir")));
+    }
+}
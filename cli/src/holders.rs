@@ -0,0 +1,438 @@
+//! Holder registry: who got which share.
+//!
+//! Holders are described in the `holders` section of the config (contact, notes, public key).
+//! `split --holder <name>` assigns the shares to the holders and records the assignment in a manifest,
+//! one json file per set in the local data directory. Manifests contain no secrets:
+//! only the set information, the holder names and the paths of the share files.
+
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use crate::policy::{PolicyError, SetPolicy};
+use crate::share_files::{self, ShareFileError, ShareSetInfo, WrittenShare};
+use crate::sheet;
+
+/// Version of the manifest format, see [Manifest]
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// A holder of the `holders` section of the config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HolderConfig {
+    /// How to reach the holder: email, phone, address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// age or SSH public key, `split --recipient <name>` encrypts the share of the holder to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// Assignment of the shares of one set to their holders
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub set_id: String,
+    /// Date of the split
+    pub created: String,
+    pub threshold: usize,
+    pub number_of_shares: usize,
+    /// Set replaced by this one (`reshare`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<String>,
+    /// The policy of `split --policy`, the holders restore the secret when they meet it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<SetPolicy>,
+    pub shares: Vec<AssignedShare>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssignedShare {
+    pub index: usize,
+    pub holder: String,
+    /// Where the share was written to
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestArgs {
+    /// Directory of the holder manifests.
+    /// Defaults to $XDG_DATA_HOME/meta-secret/manifests (~/.local/share/meta-secret/manifests)
+    #[clap(long, value_name = "DIR")]
+    manifest_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HoldersError {
+    #[error("Can't find user data directory. Please set $XDG_DATA_HOME or use --manifest-dir")]
+    NoDataDir,
+    #[error("Error writing manifest: {path}")]
+    WriteError { path: PathBuf, source: io::Error },
+    #[error("Error reading manifests: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+    #[error("Invalid manifest: {path}")]
+    InvalidManifest {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("No manifest of the set {set}. Holders are recorded by `split --holder <name>`")]
+    UnknownSet { set: String },
+    #[error("Several sets start with {set}: {matches:?}")]
+    AmbiguousSet { set: String, matches: Vec<String> },
+    #[error("The share has no set information (made by a previous version of the cli): {path}")]
+    NoSet { path: PathBuf },
+    #[error(transparent)]
+    ShareFileError(#[from] ShareFileError),
+}
+
+/// Holders of the sets and what a recovery needs
+#[derive(Debug, Serialize)]
+pub struct HoldersReport {
+    pub manifest_dir: PathBuf,
+    pub sets: Vec<SetHolders>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetHolders {
+    pub set_id: String,
+    pub created: String,
+    pub threshold: usize,
+    pub number_of_shares: usize,
+    pub holders: Vec<ShareHolder>,
+    /// Holders not marked as unavailable
+    pub available_holders: usize,
+    pub recoverable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    /// Groups of the policy the available holders don't meet
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unmet_policy: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<String>,
+    /// Newer sets made from this one by `reshare`, the holders should destroy their old shares
+    pub superseded_by: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareHolder {
+    pub index: usize,
+    pub name: String,
+    /// The holder is described in the config
+    pub registered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub available: bool,
+    pub files: Vec<PathBuf>,
+}
+
+impl ManifestArgs {
+    /// `--manifest-dir`, or `$XDG_DATA_HOME/meta-secret/manifests`, or `~/.local/share/meta-secret/manifests`
+    pub fn manifest_dir(&self) -> Result<PathBuf, HoldersError> {
+        if let Some(dir) = &self.manifest_dir {
+            return Ok(dir.clone());
+        }
+
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("share")))
+            .ok_or(HoldersError::NoDataDir)?;
+
+        Ok(data_home.join("meta-secret").join("manifests"))
+    }
+}
+
+/// Writes the manifest of the set into `<manifest dir>/<set id>.json`
+pub fn write_manifest(manifest_dir: &Path, manifest: &Manifest) -> Result<PathBuf, HoldersError> {
+    let path = manifest_dir.join(format!("{}.json", manifest.set_id));
+    let write_error = |source| HoldersError::WriteError {
+        path: path.clone(),
+        source,
+    };
+
+    fs::create_dir_all(manifest_dir).map_err(write_error)?;
+    // a manifest is a plain struct of numbers and strings, serialization can't fail
+    let manifest_json = serde_json::to_string_pretty(manifest).expect("Error serializing manifest");
+
+    // the set id is random, an existing manifest of the same set is never overwritten
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(write_error)?;
+    file.write_all(manifest_json.as_bytes())
+        .map_err(write_error)?;
+
+    Ok(path)
+}
+
+/// Manifest of the shares just written, assigned to the holders in order
+pub fn manifest(
+    set: &ShareSetInfo,
+    holders: &[String],
+    written_shares: &[WrittenShare],
+) -> Manifest {
+    let shares = holders
+        .iter()
+        .zip(written_shares)
+        .enumerate()
+        .map(|(index, (holder, written_share))| AssignedShare {
+            index,
+            holder: holder.clone(),
+            files: [
+                &written_share.json_file,
+                &written_share.qr_file,
+                &written_share.sheet_file,
+                &written_share.age_file,
+            ]
+            .into_iter()
+            .flatten()
+            .chain(&written_share.qr_parts)
+            .cloned()
+            .collect(),
+        })
+        .collect();
+
+    Manifest {
+        version: MANIFEST_VERSION,
+        set_id: set.set_id.clone(),
+        created: sheet::today(),
+        threshold: set.threshold,
+        number_of_shares: set.number_of_shares,
+        supersedes: set.supersedes.clone(),
+        policy: set.policy.clone(),
+        shares,
+    }
+}
+
+/// Reports the holders of the set (all the known sets if `set` is not given).
+/// The set is given by its id, the beginning of the id or by one of its share files
+pub fn report(
+    manifest_dir: &Path,
+    set: Option<&str>,
+    registry: &BTreeMap<String, HolderConfig>,
+    unavailable: &[String],
+) -> Result<HoldersReport, HoldersError> {
+    let mut manifests = read_manifests(manifest_dir)?;
+    let superseded: Vec<(String, String)> = manifests
+        .iter()
+        .filter_map(|manifest| Some((manifest.supersedes.clone()?, manifest.set_id.clone())))
+        .collect();
+
+    if let Some(set) = set {
+        let set_id = match Path::new(set) {
+            path if path.is_file() => share_files::read_stored_share(path)?
+                .set()
+                .map(|set| set.set_id.clone())
+                .ok_or_else(|| HoldersError::NoSet {
+                    path: path.to_path_buf(),
+                })?,
+            _ => set.to_string(),
+        };
+
+        manifests.retain(|manifest| manifest.set_id.starts_with(&set_id));
+        match manifests.len() {
+            0 => return Err(HoldersError::UnknownSet { set: set_id }),
+            1 => {}
+            _ => {
+                return Err(HoldersError::AmbiguousSet {
+                    set: set_id,
+                    matches: manifests
+                        .iter()
+                        .map(|manifest| manifest.set_id.clone())
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    let unavailable: HashSet<&str> = unavailable.iter().map(String::as_str).collect();
+    let sets = manifests
+        .into_iter()
+        .map(|manifest| {
+            let superseded_by = superseded
+                .iter()
+                .filter(|(old_set_id, _)| *old_set_id == manifest.set_id)
+                .map(|(_, new_set_id)| new_set_id.clone())
+                .collect();
+            set_holders(manifest, superseded_by, registry, &unavailable)
+        })
+        .collect();
+
+    Ok(HoldersReport {
+        manifest_dir: manifest_dir.to_path_buf(),
+        sets,
+    })
+}
+
+fn set_holders(
+    manifest: Manifest,
+    superseded_by: Vec<String>,
+    registry: &BTreeMap<String, HolderConfig>,
+    unavailable: &HashSet<&str>,
+) -> SetHolders {
+    let holders: Vec<ShareHolder> = manifest
+        .shares
+        .into_iter()
+        .map(|share| {
+            let holder_config = registry.get(&share.holder);
+            ShareHolder {
+                index: share.index,
+                registered: holder_config.is_some(),
+                contact: holder_config.and_then(|holder| holder.contact.clone()),
+                notes: holder_config.and_then(|holder| holder.notes.clone()),
+                available: !unavailable.contains(share.holder.as_str()),
+                name: share.holder,
+                files: share.files,
+            }
+        })
+        .collect();
+
+    let available_holders = holders.iter().filter(|holder| holder.available).count();
+    let unmet_policy = match &manifest.policy {
+        Some(policy) => {
+            let available_indices = holders
+                .iter()
+                .filter(|holder| holder.available)
+                .map(|holder| holder.index);
+            match policy.check(available_indices) {
+                Err(PolicyError::Unmet { unmet, .. }) => unmet,
+                _ => vec![],
+            }
+        }
+        None => vec![],
+    };
+
+    SetHolders {
+        set_id: manifest.set_id,
+        created: manifest.created,
+        threshold: manifest.threshold,
+        number_of_shares: manifest.number_of_shares,
+        recoverable: match &manifest.policy {
+            Some(_) => unmet_policy.is_empty(),
+            None => available_holders >= manifest.threshold,
+        },
+        policy: manifest.policy.map(|policy| policy.name),
+        unmet_policy,
+        available_holders,
+        holders,
+        supersedes: manifest.supersedes,
+        superseded_by,
+    }
+}
+
+/// All the manifests of the directory, sorted by date. A missing directory means no manifests yet
+fn read_manifests(manifest_dir: &Path) -> Result<Vec<Manifest>, HoldersError> {
+    if !manifest_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let files = share_files::list_files(manifest_dir, share_files::is_json_file)?;
+    let mut manifests = vec![];
+    for path in files {
+        let content = fs::read_to_string(&path).map_err(|source| HoldersError::ReadError {
+            path: path.clone(),
+            source,
+        })?;
+        let manifest: Manifest = serde_json::from_str(&content)
+            .map_err(|source| HoldersError::InvalidManifest { path, source })?;
+        manifests.push(manifest);
+    }
+
+    manifests.sort_by(|left, right| left.created.cmp(&right.created));
+    Ok(manifests)
+}
+
+impl HoldersReport {
+    pub fn print_text(&self) {
+        if self.sets.is_empty() {
+            println!(
+                "No holders recorded in {}. Holders are recorded by `split --holder <name>`",
+                self.manifest_dir.display()
+            );
+            return;
+        }
+
+        for (set_number, set) in self.sets.iter().enumerate() {
+            if set_number > 0 {
+                println!();
+            }
+
+            println!("Set {} (split {})", set.set_id, set.created);
+            match &set.policy {
+                Some(policy) => println!(
+                    "  the holders meeting the policy {} restore the secret:",
+                    policy
+                ),
+                None => println!(
+                    "  any {} of the {} holders restore the secret:",
+                    set.threshold, set.number_of_shares
+                ),
+            }
+            if let Some(supersedes) = &set.supersedes {
+                println!("  supersedes set {}", supersedes);
+            }
+            for new_set_id in &set.superseded_by {
+                println!(
+                    "  superseded by set {}: the holders should destroy these shares",
+                    new_set_id
+                );
+            }
+
+            for holder in &set.holders {
+                let mut details = vec![];
+                if let Some(contact) = &holder.contact {
+                    details.push(contact.clone());
+                }
+                if let Some(notes) = &holder.notes {
+                    details.push(notes.clone());
+                }
+                if !holder.registered {
+                    details.push(String::from("not in the config"));
+                }
+                if !holder.available {
+                    details.push(String::from("UNAVAILABLE"));
+                }
+
+                if details.is_empty() {
+                    println!("    share {}: {}", holder.index, holder.name);
+                } else {
+                    println!(
+                        "    share {}: {} ({})",
+                        holder.index,
+                        holder.name,
+                        details.join("; ")
+                    );
+                }
+            }
+
+            if set.recoverable && set.policy.is_some() {
+                println!("  status: recoverable, the available holders meet the policy");
+            } else if set.recoverable {
+                println!(
+                    "  status: recoverable, {} of the {} available holders are needed",
+                    set.threshold, set.available_holders
+                );
+            } else if set.policy.is_some() {
+                println!("  status: NOT recoverable, the available holders don't meet the policy");
+                for unmet in &set.unmet_policy {
+                    println!("    - {}", unmet);
+                }
+            } else {
+                println!(
+                    "  status: NOT recoverable, only {} holder(s) available, {} needed",
+                    set.available_holders, set.threshold
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,524 @@
+//! Hybrid mode for big secrets.
+//! The file is encrypted with a random data encryption key (DEK) and only the key gets split,
+//! so shares stay small no matter how big the file is (see docs/ideas/remote-split.md).
+//!
+//! Encrypted file format: `MSDEK` magic, format version (1 byte), stream nonce (19 bytes),
+//! then the file content encrypted by XChaCha20Poly1305 in the STREAM construction, chunk by chunk,
+//! so neither encryption nor decryption needs to keep the whole file in memory.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::XChaCha20Poly1305;
+
+use crate::secret_input::Secret;
+use crate::share_files;
+
+const MAGIC: &[u8; 5] = b"MSDEK";
+const FORMAT_VERSION: u8 = 1;
+/// XChaCha20 nonce is 24 bytes, STREAM BE32 takes 5 of them for the chunk counter and the last chunk flag
+const STREAM_NONCE_SIZE: usize = 19;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+pub const ENCRYPTED_FILE_EXTENSION: &str = "enc";
+
+#[derive(Debug, thiserror::Error)]
+pub enum HybridError {
+    #[error("Error reading: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+    #[error("Error writing: {path}")]
+    WriteError { path: PathBuf, source: io::Error },
+    #[error("File already exists: {path}")]
+    AlreadyExists { path: PathBuf },
+    #[error("Not a meta secret encrypted file: {path}")]
+    InvalidFormat { path: PathBuf },
+    #[error("Invalid data key. Expected {expected} bytes, got {actual}")]
+    InvalidKey { expected: usize, actual: usize },
+    #[error("Error decrypting: {path}. The file is corrupted or doesn't belong to these shares")]
+    DecryptionError { path: PathBuf },
+}
+
+/// Encrypts the file next to the shares and returns the data key as the secret to split.
+/// `restore` looks for the encrypted file next to the share files, so every one of `out_dirs` gets a copy
+pub fn encrypt_file(path: &Path, out_dirs: &[&Path]) -> Result<Secret, HybridError> {
+    let (out_dir, copy_dirs) = out_dirs
+        .split_first()
+        .expect("Encrypted file needs an output directory");
+
+    let secret = encrypt_into(path, out_dir)?;
+    let encrypted_file = match &secret {
+        Secret::DataKey { encrypted_file, .. } => encrypted_file,
+        _ => unreachable!("The file is encrypted with a data key"),
+    };
+
+    let mut written_dirs = vec![*out_dir];
+    for copy_dir in copy_dirs {
+        if let Err(err) = copy_encrypted_file(&out_dir.join(encrypted_file), copy_dir) {
+            remove_encrypted_files(encrypted_file, &written_dirs);
+            return Err(err);
+        }
+        written_dirs.push(copy_dir);
+    }
+
+    Ok(secret)
+}
+
+/// Removes the encrypted files of a split that failed, so that it can be run again
+pub fn remove_encrypted_files(encrypted_file: &str, out_dirs: &[&Path]) {
+    for out_dir in out_dirs {
+        let _ = fs::remove_file(out_dir.join(encrypted_file));
+    }
+}
+
+fn copy_encrypted_file(encrypted_path: &Path, out_dir: &Path) -> Result<(), HybridError> {
+    let copy_path = out_dir.join(encrypted_path.file_name().unwrap_or_default());
+    let write_error = |source| HybridError::WriteError {
+        path: copy_path.clone(),
+        source,
+    };
+
+    let mut reader = File::open(encrypted_path).map_err(|source| HybridError::ReadError {
+        path: encrypted_path.to_path_buf(),
+        source,
+    })?;
+    fs::create_dir_all(out_dir).map_err(|source| HybridError::WriteError {
+        path: out_dir.to_path_buf(),
+        source,
+    })?;
+    let mut writer = create_new_file(&copy_path)?;
+
+    let copy = io::copy(&mut reader, &mut writer)
+        .and_then(|_| writer.sync_all())
+        .map_err(write_error);
+    if copy.is_err() {
+        let _ = fs::remove_file(&copy_path);
+    }
+    copy
+}
+
+fn encrypt_into(path: &Path, out_dir: &Path) -> Result<Secret, HybridError> {
+    let read_error = |source| HybridError::ReadError {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string());
+    let encrypted_file_name = format!(
+        "{}.{}",
+        file_name.as_deref().unwrap_or("secret"),
+        ENCRYPTED_FILE_EXTENSION
+    );
+    let encrypted_path = out_dir.join(&encrypted_file_name);
+
+    let mut reader = BufReader::new(File::open(path).map_err(read_error)?);
+
+    fs::create_dir_all(out_dir).map_err(|source| HybridError::WriteError {
+        path: out_dir.to_path_buf(),
+        source,
+    })?;
+    let mut writer = BufWriter::new(create_new_file(&encrypted_path)?);
+
+    let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+    let mut nonce = [0u8; STREAM_NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    let encryption = (|| {
+        let write_error = |source| HybridError::WriteError {
+            path: encrypted_path.clone(),
+            source,
+        };
+
+        writer.write_all(MAGIC).map_err(write_error)?;
+        writer.write_all(&[FORMAT_VERSION]).map_err(write_error)?;
+        writer.write_all(&nonce).map_err(write_error)?;
+
+        let cipher = XChaCha20Poly1305::new(&key);
+        let mut encryptor = EncryptorBE32::from_aead(cipher, nonce.as_ref().into());
+
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        loop {
+            let read_size = read_chunk(&mut reader, &mut buffer).map_err(read_error)?;
+
+            if read_size == CHUNK_SIZE {
+                let encrypted_chunk = encryptor
+                    .encrypt_next(buffer.as_slice())
+                    .expect("Chunk encryption can't fail");
+                writer.write_all(&encrypted_chunk).map_err(write_error)?;
+            } else {
+                let encrypted_chunk = encryptor
+                    .encrypt_last(&buffer[..read_size])
+                    .expect("Chunk encryption can't fail");
+                writer.write_all(&encrypted_chunk).map_err(write_error)?;
+                break;
+            }
+        }
+
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .and_then(|file| file.sync_all())
+            .map_err(write_error)
+    })();
+
+    if encryption.is_err() {
+        // don't leave a partially encrypted file behind
+        let _ = fs::remove_file(&encrypted_path);
+    }
+    encryption?;
+
+    let content_type = mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string();
+
+    Ok(Secret::DataKey {
+        key: key.to_vec(),
+        encrypted_file: encrypted_file_name,
+        file_name,
+        content_type,
+    })
+}
+
+/// Decrypts the file with the restored data key into a new file readable only by the owner
+pub fn decrypt_file(key: &[u8], encrypted_path: &Path, output: &Path) -> Result<(), HybridError> {
+    if key.len() != 32 {
+        return Err(HybridError::InvalidKey {
+            expected: 32,
+            actual: key.len(),
+        });
+    }
+
+    let read_error = |source| HybridError::ReadError {
+        path: encrypted_path.to_path_buf(),
+        source,
+    };
+
+    let mut reader = BufReader::new(File::open(encrypted_path).map_err(read_error)?);
+
+    let mut header = [0u8; MAGIC.len() + 1 + STREAM_NONCE_SIZE];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| HybridError::InvalidFormat {
+            path: encrypted_path.to_path_buf(),
+        })?;
+
+    let (magic, rest) = header.split_at(MAGIC.len());
+    let (version, nonce) = rest.split_at(1);
+    if magic != MAGIC || version[0] != FORMAT_VERSION {
+        return Err(HybridError::InvalidFormat {
+            path: encrypted_path.to_path_buf(),
+        });
+    }
+
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let mut decryptor = DecryptorBE32::from_aead(cipher, nonce.into());
+
+    let mut writer = BufWriter::new(create_new_file(output)?);
+    let decryption = (|| {
+        let write_error = |source| HybridError::WriteError {
+            path: output.to_path_buf(),
+            source,
+        };
+        let decryption_error = |_| HybridError::DecryptionError {
+            path: encrypted_path.to_path_buf(),
+        };
+
+        let mut buffer = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+        loop {
+            let read_size = read_chunk(&mut reader, &mut buffer).map_err(read_error)?;
+
+            if read_size == buffer.len() {
+                let chunk = decryptor
+                    .decrypt_next(buffer.as_slice())
+                    .map_err(decryption_error)?;
+                writer.write_all(&chunk).map_err(write_error)?;
+            } else {
+                let chunk = decryptor
+                    .decrypt_last(&buffer[..read_size])
+                    .map_err(decryption_error)?;
+                writer.write_all(&chunk).map_err(write_error)?;
+                break;
+            }
+        }
+
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .and_then(|file| file.sync_all())
+            .map_err(write_error)
+    })();
+
+    if decryption.is_err() {
+        // don't leave a partially decrypted file behind
+        let _ = fs::remove_file(output);
+    }
+
+    decryption
+}
+
+/// Fills the buffer up, returns less than the buffer size only at the end of the stream
+fn read_chunk(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read_size = 0;
+    while read_size < buffer.len() {
+        match reader.read(&mut buffer[read_size..]) {
+            Ok(0) => break,
+            Ok(size) => read_size += size,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read_size)
+}
+
+fn create_new_file(path: &Path) -> Result<File, HybridError> {
+    share_files::create_private_file(path).map_err(|source| match source.kind() {
+        io::ErrorKind::AlreadyExists => HybridError::AlreadyExists {
+            path: path.to_path_buf(),
+        },
+        _ => HybridError::WriteError {
+            path: path.to_path_buf(),
+            source,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+
+    use super::*;
+    use crate::recipients::Recipient;
+    use crate::share_files::{Sharing, SplitOutputArgs};
+
+    #[derive(Parser)]
+    struct TestArgs {
+        #[clap(flatten)]
+        output: SplitOutputArgs,
+    }
+
+    const HEADER_SIZE: usize = MAGIC.len() + 1 + STREAM_NONCE_SIZE;
+    const ENCRYPTED_CHUNK_SIZE: usize = CHUNK_SIZE + TAG_SIZE;
+
+    fn encrypt(dir: &Path, content: &[u8]) -> (Vec<u8>, PathBuf) {
+        let path = dir.join("secret.bin");
+        fs::write(&path, content).unwrap();
+        match encrypt_file(&path, &[&dir.join("out")]).unwrap() {
+            Secret::DataKey {
+                key,
+                encrypted_file,
+                ..
+            } => (key, dir.join("out").join(encrypted_file)),
+            _ => panic!("Expected a data key"),
+        }
+    }
+
+    fn round_trip(content: &[u8]) {
+        let dir = tempfile::tempdir().unwrap();
+        let (key, encrypted_path) = encrypt(dir.path(), content);
+
+        let encrypted_size = fs::metadata(&encrypted_path).unwrap().len() as usize;
+        let chunks = content.len() / CHUNK_SIZE + 1;
+        assert_eq!(
+            encrypted_size,
+            HEADER_SIZE + content.len() + chunks * TAG_SIZE
+        );
+
+        let output = dir.path().join("restored.bin");
+        decrypt_file(&key, &encrypted_path, &output).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), content);
+    }
+
+    fn assert_rejected(key: &[u8], encrypted_path: &Path) {
+        let output = encrypted_path.with_file_name("restored.bin");
+        let result = decrypt_file(key, encrypted_path, &output);
+        assert!(matches!(result, Err(HybridError::DecryptionError { .. })));
+        assert!(!output.exists(), "Partially decrypted file left behind");
+    }
+
+    #[test]
+    fn empty_payload() {
+        round_trip(b"");
+    }
+
+    #[test]
+    fn exactly_one_chunk() {
+        round_trip(&vec![7u8; CHUNK_SIZE]);
+    }
+
+    #[test]
+    fn one_chunk_and_one_byte() {
+        let mut content = vec![7u8; CHUNK_SIZE];
+        content.push(8);
+        round_trip(&content);
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (key, encrypted_path) = encrypt(dir.path(), &vec![1u8; 2 * CHUNK_SIZE]);
+
+        // drop the last chunk, what's left ends on a complete chunk
+        let encrypted = fs::read(&encrypted_path).unwrap();
+        fs::write(
+            &encrypted_path,
+            &encrypted[..HEADER_SIZE + 2 * ENCRYPTED_CHUNK_SIZE],
+        )
+        .unwrap();
+
+        assert_rejected(&key, &encrypted_path);
+    }
+
+    #[test]
+    fn reordered_chunks_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut content = vec![1u8; CHUNK_SIZE];
+        content.extend(vec![2u8; CHUNK_SIZE]);
+        content.push(3);
+        let (key, encrypted_path) = encrypt(dir.path(), &content);
+
+        let mut encrypted = fs::read(&encrypted_path).unwrap();
+        let (first, second) = encrypted[HEADER_SIZE..].split_at_mut(ENCRYPTED_CHUNK_SIZE);
+        first.swap_with_slice(&mut second[..ENCRYPTED_CHUNK_SIZE]);
+        fs::write(&encrypted_path, &encrypted).unwrap();
+
+        assert_rejected(&key, &encrypted_path);
+    }
+
+    #[test]
+    fn wrong_key_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut key, encrypted_path) = encrypt(dir.path(), b"top secret");
+        key[0] ^= 1;
+
+        assert_rejected(&key, &encrypted_path);
+    }
+
+    #[test]
+    fn existing_output_is_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let (key, encrypted_path) = encrypt(dir.path(), b"top secret");
+        let output = dir.path().join("secret.bin");
+
+        let result = decrypt_file(&key, &encrypted_path, &output);
+        assert!(matches!(result, Err(HybridError::AlreadyExists { .. })));
+        assert_eq!(fs::read(&output).unwrap(), b"top secret");
+    }
+
+    fn share_dir_args(dir: &Path) -> SplitOutputArgs {
+        let mut args = vec![String::from("split")];
+        for holder in ["alice", "bob", "carol"] {
+            args.push(String::from("--share-dir"));
+            args.push(dir.join(holder).to_str().unwrap().to_string());
+        }
+        TestArgs::parse_from(args).output
+    }
+
+    fn sharing() -> Sharing {
+        Sharing::Core(SharedSecretConfig {
+            number_of_shares: 3,
+            threshold: 2,
+        })
+    }
+
+    fn secret_file(dir: &Path) -> PathBuf {
+        let path = dir.join("backup.tar");
+        fs::write(&path, vec![7u8; CHUNK_SIZE + 1]).unwrap();
+        path
+    }
+
+    fn encrypted_files(dir: &Path) -> Vec<PathBuf> {
+        ["alice", "bob", "carol"]
+            .iter()
+            .map(|holder| dir.join(holder).join("backup.tar.enc"))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    #[test]
+    fn encrypted_file_next_to_every_share() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = secret_file(dir.path());
+
+        let (secret, written_set) =
+            crate::split_hybrid(&path, &sharing(), &share_dir_args(dir.path()), &[]).unwrap();
+        assert_eq!(written_set.shares.len(), 3);
+
+        let key = match secret {
+            Secret::DataKey { key, .. } => key,
+            _ => panic!("Expected a data key"),
+        };
+        let encrypted_files = encrypted_files(dir.path());
+        assert_eq!(encrypted_files.len(), 3);
+        for (index, encrypted_file) in encrypted_files.iter().enumerate() {
+            let output = dir.path().join(format!("restored-{}.tar", index));
+            decrypt_file(&key, encrypted_file, &output).unwrap();
+            assert_eq!(fs::read(output).unwrap(), fs::read(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn nothing_is_encrypted_into_dirs_with_shares() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = secret_file(dir.path());
+        fs::create_dir_all(dir.path().join("bob")).unwrap();
+        fs::write(dir.path().join("bob").join("shared-secret-1.json"), "{}").unwrap();
+
+        let result = crate::split_hybrid(&path, &sharing(), &share_dir_args(dir.path()), &[]);
+        assert!(result.is_err());
+        assert!(encrypted_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn failed_split_removes_the_encrypted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = secret_file(dir.path());
+        let output = share_dir_args(dir.path());
+
+        // one recipient for three shares: the shares are never written
+        let recipients = [Recipient {
+            name: String::from("alice"),
+            public_key: String::from("age1recipient"),
+        }];
+        let result = crate::split_hybrid(&path, &sharing(), &output, &recipients);
+        assert!(result.is_err());
+        assert!(encrypted_files(dir.path()).is_empty());
+
+        // so the split can be run again
+        crate::split_hybrid(&path, &sharing(), &output, &[]).unwrap();
+        assert_eq!(encrypted_files(dir.path()).len(), 3);
+    }
+
+    #[test]
+    fn existing_copy_is_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = secret_file(dir.path());
+        fs::create_dir_all(dir.path().join("carol")).unwrap();
+        fs::write(dir.path().join("carol").join("backup.tar.enc"), "old").unwrap();
+
+        let out_dirs = [
+            dir.path().join("alice"),
+            dir.path().join("bob"),
+            dir.path().join("carol"),
+        ];
+        let out_dirs: Vec<&Path> = out_dirs.iter().map(PathBuf::as_path).collect();
+        let result = encrypt_file(&path, &out_dirs);
+        assert!(matches!(result, Err(HybridError::AlreadyExists { .. })));
+
+        // the copies written before are removed, the existing file is left as it is
+        assert_eq!(
+            encrypted_files(dir.path()),
+            [dir.path().join("carol").join("backup.tar.enc")]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("carol").join("backup.tar.enc")).unwrap(),
+            "old"
+        );
+    }
+}
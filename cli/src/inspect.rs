@@ -0,0 +1,366 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::policy::PolicyError;
+use crate::share_files::{
+    self, ImportedEntry, SecretMetadata, ShareFileError, ShareSetInfo, StoredShare,
+};
+
+/// Everything that is known about a share file, except the share itself
+#[derive(Debug, Serialize)]
+pub struct ShareInfo {
+    pub file: PathBuf,
+    pub version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set: Option<ShareSetInfo>,
+    /// Unknown for encrypted shares
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<SecretMetadata>,
+    /// The share is protected with the passphrase of its holder
+    pub encrypted: bool,
+    /// Verifiable shares only, see the `check` command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitments_fingerprint: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    pub dir: PathBuf,
+    pub sets: Vec<SetReport>,
+    /// Files made by the previous versions of the cli, they don't know which set they belong to
+    pub legacy_files: Vec<PathBuf>,
+    pub unreadable_files: Vec<UnreadableFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetReport {
+    pub set_id: String,
+    pub threshold: usize,
+    pub number_of_shares: usize,
+    pub present_indices: Vec<usize>,
+    pub missing_indices: Vec<usize>,
+    pub duplicates: Vec<DuplicateShare>,
+    /// Shares protected with the passphrases of their holders
+    pub encrypted_indices: Vec<usize>,
+    pub files: Vec<PathBuf>,
+    pub recoverable: bool,
+    /// Name of the policy of the set (`split --policy`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    /// Groups of the policy the present shares don't meet
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unmet_policy: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<String>,
+    /// Newer sets of the directory made from this one by `reshare`
+    pub superseded_by: Vec<String>,
+}
+
+/// The same share stored in several files
+/// (a json file and a qr code image of the same share are not counted as duplicates)
+#[derive(Debug, Serialize)]
+pub struct DuplicateShare {
+    pub index: usize,
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnreadableFile {
+    pub file: PathBuf,
+    pub error: String,
+}
+
+pub fn inspect(path: &Path) -> Result<ShareInfo, ShareFileError> {
+    let share_file = share_files::read_stored_share(path)?;
+
+    Ok(ShareInfo {
+        file: path.to_path_buf(),
+        version: share_file.version(),
+        set: share_file.set().cloned(),
+        secret: share_file.secret().cloned(),
+        encrypted: share_file.is_encrypted(),
+        commitments_fingerprint: match &share_file {
+            StoredShare::Plain(share_file) => share_file.share.commitments_fingerprint(),
+            StoredShare::Encrypted(_) => None,
+        },
+    })
+}
+
+pub fn audit(dir: &Path) -> Result<AuditReport, ShareFileError> {
+    let files = share_files::list_files(dir, share_files::is_share_file)?;
+
+    let mut sets: BTreeMap<String, (ShareSetInfo, Vec<SetFile>)> = BTreeMap::new();
+    let mut legacy_files = vec![];
+    let mut unreadable_files = vec![];
+
+    for file in files {
+        match share_files::read_stored_share(&file) {
+            Ok(share_file) => match share_file.set() {
+                Some(set) => {
+                    let set_file = SetFile {
+                        index: set.index,
+                        file,
+                        encrypted: share_file.is_encrypted(),
+                    };
+                    sets.entry(set.set_id.clone())
+                        .or_insert_with(|| (set.clone(), vec![]))
+                        .1
+                        .push(set_file);
+                }
+                None => legacy_files.push(file),
+            },
+            Err(err) => unreadable_files.push(UnreadableFile {
+                file,
+                error: err.to_string(),
+            }),
+        }
+    }
+
+    let mut sets: Vec<SetReport> = sets
+        .into_values()
+        .map(|(set, files)| set_report(set, files))
+        .collect();
+
+    let superseded: Vec<(String, String)> = sets
+        .iter()
+        .filter_map(|set| Some((set.supersedes.clone()?, set.set_id.clone())))
+        .collect();
+    for (old_set_id, new_set_id) in superseded {
+        if let Some(old_set) = sets.iter_mut().find(|set| set.set_id == old_set_id) {
+            old_set.superseded_by.push(new_set_id);
+        }
+    }
+
+    Ok(AuditReport {
+        dir: dir.to_path_buf(),
+        sets,
+        legacy_files,
+        unreadable_files,
+    })
+}
+
+/// A share file of the set found by audit
+struct SetFile {
+    index: usize,
+    file: PathBuf,
+    encrypted: bool,
+}
+
+fn set_report(set: ShareSetInfo, files: Vec<SetFile>) -> SetReport {
+    let mut files_by_index: BTreeMap<usize, Vec<PathBuf>> = BTreeMap::new();
+    let mut encrypted_indices = BTreeSet::new();
+    for set_file in &files {
+        files_by_index
+            .entry(set_file.index)
+            .or_default()
+            .push(set_file.file.clone());
+        if set_file.encrypted {
+            encrypted_indices.insert(set_file.index);
+        }
+    }
+
+    let present_indices: Vec<usize> = files_by_index.keys().copied().collect();
+    let missing_indices = (0..set.number_of_shares)
+        .filter(|index| !files_by_index.contains_key(index))
+        .collect();
+
+    let duplicates = files_by_index
+        .into_iter()
+        .filter(|(_, files)| {
+            let stems: BTreeSet<PathBuf> =
+                files.iter().map(|file| file.with_extension("")).collect();
+            stems.len() > 1
+        })
+        .map(|(index, files)| DuplicateShare { index, files })
+        .collect();
+
+    let unmet_policy = match &set.policy {
+        Some(policy) => match policy.check(present_indices.iter().copied()) {
+            Err(PolicyError::Unmet { unmet, .. }) => unmet,
+            _ => vec![],
+        },
+        None => vec![],
+    };
+
+    SetReport {
+        recoverable: match &set.policy {
+            Some(_) => unmet_policy.is_empty(),
+            None => present_indices.len() >= set.threshold,
+        },
+        policy: set.policy.map(|policy| policy.name),
+        unmet_policy,
+        set_id: set.set_id,
+        threshold: set.threshold,
+        number_of_shares: set.number_of_shares,
+        present_indices,
+        missing_indices,
+        duplicates,
+        encrypted_indices: encrypted_indices.into_iter().collect(),
+        files: files.into_iter().map(|set_file| set_file.file).collect(),
+        supersedes: set.supersedes,
+        superseded_by: vec![],
+    }
+}
+
+impl ShareInfo {
+    pub fn print_text(&self) {
+        println!("File: {}", self.file.display());
+        println!("Format version: {}", self.version);
+
+        match &self.set {
+            Some(set) => {
+                println!("Set id: {}", set.set_id);
+                println!("Share index: {}", set.index);
+                match &set.policy {
+                    Some(policy) => {
+                        println!("Policy: {}", policy.name);
+                        if let Some(holder) = policy.holder(set.index) {
+                            println!("Holder: {}", holder);
+                        }
+                    }
+                    None => println!("Threshold: {}", set.threshold),
+                }
+                println!("Total shares: {}", set.number_of_shares);
+                if let Some(supersedes) = &set.supersedes {
+                    println!("Supersedes set: {}", supersedes);
+                }
+            }
+            None => println!("Set: unknown (made by a previous version of the cli)"),
+        }
+
+        match &self.secret {
+            Some(secret) => println!("Secret: {}", describe_secret(secret)),
+            None => println!("Secret: unknown until the share is decrypted"),
+        }
+        if self.encrypted {
+            println!("Encrypted: yes, restore asks for the passphrase of the holder");
+        }
+        if let Some(commitments_fingerprint) = &self.commitments_fingerprint {
+            println!(
+                "Verifiable: yes, commitments fingerprint {}",
+                commitments_fingerprint
+            );
+        }
+    }
+}
+
+impl AuditReport {
+    pub fn print_text(&self) {
+        println!("Directory: {}", self.dir.display());
+
+        for set in &self.sets {
+            println!();
+            println!("Set {}", set.set_id);
+            match &set.policy {
+                Some(policy) => println!(
+                    "  policy: {}, {} shares, one per holder",
+                    policy, set.number_of_shares
+                ),
+                None => println!(
+                    "  threshold: {} of {} shares",
+                    set.threshold, set.number_of_shares
+                ),
+            }
+            println!("  present shares: {}", join(&set.present_indices));
+            println!("  missing shares: {}", join(&set.missing_indices));
+            for duplicate in &set.duplicates {
+                let files: Vec<String> = duplicate
+                    .files
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect();
+                println!(
+                    "  duplicate share {}: {}",
+                    duplicate.index,
+                    files.join(", ")
+                );
+            }
+            if !set.encrypted_indices.is_empty() {
+                println!(
+                    "  passphrase-protected shares: {}",
+                    join(&set.encrypted_indices)
+                );
+            }
+            if let Some(supersedes) = &set.supersedes {
+                println!("  supersedes set {}", supersedes);
+            }
+            for new_set_id in &set.superseded_by {
+                println!(
+                    "  superseded by set {}: the shares of this set should be destroyed",
+                    new_set_id
+                );
+            }
+
+            if set.recoverable {
+                println!("  status: recoverable");
+            } else if set.policy.is_some() {
+                println!("  status: NOT recoverable, the policy is not met");
+                for unmet in &set.unmet_policy {
+                    println!("    - {}", unmet);
+                }
+            } else {
+                println!(
+                    "  status: NOT recoverable, {} more share(s) needed",
+                    set.threshold - set.present_indices.len()
+                );
+            }
+        }
+
+        if !self.legacy_files.is_empty() {
+            println!();
+            println!("Shares without set information (made by a previous version of the cli):");
+            for file in &self.legacy_files {
+                println!("  {}", file.display());
+            }
+        }
+
+        if !self.unreadable_files.is_empty() {
+            println!();
+            println!("Unreadable files:");
+            for unreadable_file in &self.unreadable_files {
+                println!(
+                    "  {}: {}",
+                    unreadable_file.file.display(),
+                    unreadable_file.error
+                );
+            }
+        }
+    }
+}
+
+fn describe_secret(secret: &SecretMetadata) -> String {
+    let file_name = secret.file_name.as_deref().unwrap_or("unnamed");
+    let content_type = secret.content_type.as_deref().unwrap_or("unknown type");
+
+    match secret.encoding {
+        share_files::SecretEncoding::Text => match &secret.entry {
+            Some(ImportedEntry {
+                title,
+                url: Some(url),
+            }) => format!("password of {} ({})", title, url),
+            Some(ImportedEntry { title, url: None }) => format!("password of {}", title),
+            None => String::from("text"),
+        },
+        share_files::SecretEncoding::Base64 => format!("file {} ({})", file_name, content_type),
+        share_files::SecretEncoding::DataKey => format!(
+            "data key of the encrypted file {} (original file: {}, {})",
+            secret.encrypted_file.as_deref().unwrap_or("unknown"),
+            file_name,
+            content_type
+        ),
+    }
+}
+
+fn join(indices: &[usize]) -> String {
+    if indices.is_empty() {
+        return String::from("-");
+    }
+
+    indices
+        .iter()
+        .map(|index| index.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,207 @@
+//! Import and export of the shares of other tools: SLIP-39 (hardware wallets) and ssss (`ssss-split`).
+//! Shares can't be converted one by one (see the share formats crate),
+//! so the secret is restored in memory and split again
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::{ArgEnum, Args};
+use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+use meta_secret_share_formats::slip39::{self, Slip39Error};
+use meta_secret_share_formats::ssss::{self, SsssError};
+use serde::Serialize;
+
+use crate::secret_input::{self, Secret};
+
+#[derive(Debug, Clone, Copy, ArgEnum, Eq, PartialEq, Serialize)]
+#[clap(rename_all = "kebab_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ShareFormat {
+    /// SLIP-39 mnemonics (Trezor and other hardware wallets)
+    Slip39,
+    /// Shares of ssss-split
+    Ssss,
+}
+
+impl ShareFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ShareFormat::Slip39 => "slip39",
+            ShareFormat::Ssss => "ssss",
+        }
+    }
+}
+
+impl fmt::Display for ShareFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareFormat::Slip39 => write!(f, "SLIP-39"),
+            ShareFormat::Ssss => write!(f, "ssss"),
+        }
+    }
+}
+
+/// Settings of the other tools
+#[derive(Debug, Args)]
+pub struct InteropArgs {
+    /// ssss: the shares are made without the diffusion layer (`ssss-split -D`)
+    #[clap(long)]
+    no_diffusion: bool,
+
+    /// SLIP-39: ask for the passphrase. Every passphrase gives another secret, the default one is empty
+    #[clap(long)]
+    slip39_passphrase: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InteropError {
+    #[error(transparent)]
+    Slip39(#[from] Slip39Error),
+    #[error(transparent)]
+    Ssss(#[from] SsssError),
+    #[error("ssss shares don't store the threshold, it has to be given with --ssss-threshold (like ssss-combine -t)")]
+    MissingThreshold,
+    #[error("The shares hold only the key of an encrypted file (hybrid mode), other tools can't decrypt the file")]
+    HybridSecret,
+    #[error("No shares found in: {path}")]
+    NoShares { path: PathBuf },
+    #[error("Error reading shares from: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+    #[error("Error writing share file: {path}")]
+    WriteError { path: PathBuf, source: io::Error },
+}
+
+impl InteropArgs {
+    /// Restores the secret of the shares of another tool
+    pub fn import(
+        &self,
+        format: ShareFormat,
+        shares: &[String],
+        ssss_threshold: Option<usize>,
+    ) -> Result<Secret> {
+        let content = match format {
+            ShareFormat::Slip39 => {
+                let passphrase = self.passphrase(false)?;
+                slip39::combine(shares, &passphrase).map_err(InteropError::from)?
+            }
+            ShareFormat::Ssss => {
+                let threshold = ssss_threshold.ok_or(InteropError::MissingThreshold)?;
+                let secret = ssss::combine(shares, threshold, !self.no_diffusion)
+                    .map_err(InteropError::from)?;
+                ssss::text_secret(&secret).to_vec()
+            }
+        };
+
+        // wallet seeds are binary, passwords split with ssss are text
+        Ok(match String::from_utf8(content) {
+            Ok(text) => Secret::Text(text),
+            Err(err) => Secret::File {
+                file_name: None,
+                content_type: String::from("application/octet-stream"),
+                content: err.into_bytes(),
+            },
+        })
+    }
+
+    /// Splits the secret into the shares of another tool
+    pub fn export(
+        &self,
+        format: ShareFormat,
+        secret: &[u8],
+        config: SharedSecretConfig,
+    ) -> Result<Vec<String>> {
+        let shares = match format {
+            ShareFormat::Slip39 => {
+                let passphrase = self.passphrase(true)?;
+                slip39::split(
+                    secret,
+                    &passphrase,
+                    config.threshold,
+                    config.number_of_shares,
+                )
+                .map_err(InteropError::from)?
+            }
+            ShareFormat::Ssss => ssss::split(
+                secret,
+                config.threshold,
+                config.number_of_shares,
+                !self.no_diffusion,
+            )
+            .map_err(InteropError::from)?,
+        };
+
+        Ok(shares)
+    }
+
+    fn passphrase(&self, confirm: bool) -> Result<String> {
+        if !self.slip39_passphrase {
+            return Ok(String::new());
+        }
+        secret_input::read_passphrase("SLIP-39 passphrase", confirm)
+    }
+}
+
+/// Reads the shares, one per line. Empty lines and comments (#) are skipped
+pub fn read_shares(files: &[PathBuf]) -> Result<Vec<String>, InteropError> {
+    let mut shares = vec![];
+    for path in files {
+        let content = fs::read_to_string(path).map_err(|source| InteropError::ReadError {
+            path: path.clone(),
+            source,
+        })?;
+
+        let file_shares: Vec<String> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+
+        if file_shares.is_empty() {
+            return Err(InteropError::NoShares { path: path.clone() });
+        }
+        shares.extend(file_shares);
+    }
+
+    Ok(shares)
+}
+
+/// Writes every share into its own file: `<format>-share-<n>.<format>`, never overwriting existing files
+pub fn write_shares(
+    format: ShareFormat,
+    shares: &[String],
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>, InteropError> {
+    fs::create_dir_all(out_dir).map_err(|source| InteropError::WriteError {
+        path: out_dir.to_path_buf(),
+        source,
+    })?;
+
+    let mut files = vec![];
+    for (index, share) in shares.iter().enumerate() {
+        let path = out_dir.join(format!(
+            "{}-share-{}.{}",
+            format.extension(),
+            index + 1,
+            format.extension()
+        ));
+        let write_error = |source| InteropError::WriteError {
+            path: path.clone(),
+            source,
+        };
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(write_error)?;
+        writeln!(file, "{}", share).map_err(write_error)?;
+
+        files.push(path);
+    }
+
+    Ok(files)
+}
@@ -1,17 +1,68 @@
 extern crate core;
 
-use std::fs::File;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::string::FromUtf8Error;
 
 use anyhow::{Context, Result};
 use clap::{ArgEnum, Parser, Subcommand};
+use meta_secret_core::errors::CoreError;
+use meta_secret_core::recover_from_shares;
 use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
-use meta_secret_core::{convert_qr_images_to_json_files, recover, split, RecoveryOperationError};
-use serde::{Deserialize, Serialize};
+use meta_secret_share_formats::vss::{self, VssError};
+use rayon::prelude::*;
+
+use crate::batch::{
+    BatchEntry, BatchError, BatchReport, SecretFailure, SecretReport, SecretSource,
+};
+use crate::config::{ConfigCommand, MetaSecretConfig, SharedSecretArgs, MIN_THRESHOLD};
+use crate::holders::ManifestArgs;
+use crate::interop::{InteropArgs, InteropError, ShareFormat};
+use crate::output::{CommandOutput, OutputFormat};
+use crate::passwords::ExportFormat;
+use crate::policy::{PolicyError, SetPolicy};
+use crate::recipients::Recipient;
+use crate::secret_input::{Secret, SecretArgs};
+use crate::share_files::{
+    ImportedEntry, RestoreInputArgs, SecretEncoding, SecretMetadata, ShareData, ShareFile,
+    ShareFileError, ShareSetInfo, Sharing, SplitOutputArgs, StoredShare, WrittenShare,
+};
+use crate::sheet::{Sheet, SheetFormat};
+use crate::terminal::TerminalShare;
+
+mod batch;
+mod config;
+mod holders;
+mod hybrid;
+mod inspect;
+mod interop;
+mod output;
+mod passwords;
+mod pdf_pages;
+mod policy;
+mod protection;
+mod qr;
+mod recipients;
+mod secret_input;
+mod share_files;
+mod sheet;
+mod terminal;
+mod text_code;
+mod verify;
 
 #[derive(Debug, Parser)]
 #[clap(about = "Meta Secret Command Line Application", long_about = None)]
 struct CmdLine {
+    /// Path to the config file (yaml, toml or json).
+    /// Defaults to $META_SECRET_CONFIG, then $XDG_CONFIG_HOME/meta-secret/config.yaml
+    #[clap(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Output format. `json` prints a single json document with the result or the error,
+    /// see README for the exit codes
+    #[clap(long, arg_enum, global = true, default_value = "text")]
+    format: OutputFormat,
+
     #[clap(subcommand)]
     command: Command,
 }
@@ -20,77 +71,1353 @@ struct CmdLine {
 #[derive(Subcommand, Debug)]
 enum Command {
     Split {
-        #[clap(short, long)]
-        secret: String,
+        #[clap(flatten)]
+        secret: SecretArgs,
+
+        #[clap(flatten)]
+        shared_secret: SharedSecretArgs,
+
+        /// Split with a weighted or nested threshold policy of the `policies` section of the config
+        /// instead of k of n: every holder of the policy gets a share
+        #[clap(
+            long,
+            value_name = "NAME",
+            conflicts_with_all = &["profile", "shares", "threshold", "verifiable", "holders"]
+        )]
+        policy: Option<String>,
+
+        #[clap(flatten)]
+        output: SplitOutputArgs,
+
+        /// Hybrid mode for big files: encrypt the file with a random data key
+        /// and split only the key. The encrypted file is written into --out-dir
+        #[clap(long, requires = "file")]
+        hybrid: bool,
+
+        /// Show every share as a qr code in the terminal, one share per screen
+        #[clap(long, conflicts_with = "recipients")]
+        show_qr: bool,
+
+        /// Make a printable paper sheet for every share, next to the share file
+        #[clap(long, arg_enum, value_name = "FORMAT", conflicts_with = "recipients")]
+        print_sheet: Option<SheetFormat>,
+
+        /// Holder of the share: a name of the `holders` section of the config or any label.
+        /// Printed on the sheet and recorded in the manifest of the set, see the `holders` command.
+        /// Has to be repeated once per share: --holder Alice --holder Bob ...
+        #[clap(long = "holder", value_name = "NAME", conflicts_with = "recipients")]
+        holders: Vec<String>,
+
+        #[clap(flatten)]
+        manifest: ManifestArgs,
+
+        /// Split many named secrets in one run, each with its own holders and threshold: a yaml or csv file, see README.
+        /// Every holder gets a directory in --out-dir with their shares of all the secrets
+        #[clap(
+            long = "manifest",
+            value_name = "FILE",
+            conflicts_with_all = &[
+                "secret", "secret-fd", "secret-env", "file", "policy", "shares", "hybrid",
+                "show-qr", "share-dirs", "share-passphrases", "recipients"
+            ]
+        )]
+        batch_manifest: Option<PathBuf>,
     },
     Restore {
         #[clap(short, long, arg_enum)]
         from: RestoreType,
+
+        #[clap(flatten)]
+        input: RestoreInputArgs,
+
+        /// Write the restored secret into a new file (with 0600 permissions) instead of printing it.
+        /// Required for secrets split with --file
+        #[clap(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Hybrid mode: the encrypted file to decrypt.
+        /// Defaults to the file named in the shares, next to the share files
+        #[clap(long, value_name = "FILE")]
+        encrypted_file: Option<PathBuf>,
+    },
+    /// Split the secret of existing shares again: a new threshold, number of shares or holders.
+    /// The secret is restored in memory only, the new shares record that they supersede the old set
+    Reshare {
+        #[clap(flatten)]
+        input: RestoreInputArgs,
+
+        #[clap(flatten)]
+        shared_secret: SharedSecretArgs,
+
+        /// Split with a policy of the config instead of k of n, see `split --policy`
+        #[clap(
+            long,
+            value_name = "NAME",
+            conflicts_with_all = &["profile", "shares", "threshold", "verifiable", "holders"]
+        )]
+        policy: Option<String>,
+
+        #[clap(flatten)]
+        output: SplitOutputArgs,
+
+        /// Make a printable paper sheet for every new share, next to the share file
+        #[clap(long, arg_enum, value_name = "FORMAT", conflicts_with = "recipients")]
+        print_sheet: Option<SheetFormat>,
+
+        /// Holder of the new share, see `split --holder`. Has to be repeated once per share
+        #[clap(long = "holder", value_name = "NAME", conflicts_with = "recipients")]
+        holders: Vec<String>,
+
+        #[clap(flatten)]
+        manifest: ManifestArgs,
+    },
+    /// Show shares as qr codes in the terminal, one share per screen
+    Show {
+        /// Share files: json or qr code images
+        #[clap(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Make printable paper sheets of existing shares, one per share
+    ExportSheet {
+        #[clap(long, arg_enum, default_value = "pdf")]
+        sheet_format: SheetFormat,
+
+        /// Holder label printed on the sheet. Has to be repeated once per share: --holder Alice --holder Bob ...
+        #[clap(long = "holder", value_name = "LABEL")]
+        holders: Vec<String>,
+
+        /// Directory to write the sheets into. Defaults to the directory of every share file
+        #[clap(short, long)]
+        out_dir: Option<PathBuf>,
+
+        /// Share files: json, qr code images or text codes
+        #[clap(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Write shares as word lists (.words files) to keep on paper or to read out loud.
+    /// Only secrets split as text are supported
+    ExportWords {
+        /// Directory to write the word lists into. Defaults to the directory of every share file
+        #[clap(short, long)]
+        out_dir: Option<PathBuf>,
+
+        /// Share files: json, qr code images or text codes
+        #[clap(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Import the shares of another tool (SLIP-39, ssss): restore the secret and split it into new shares
+    Import {
+        #[clap(long, arg_enum)]
+        from: ShareFormat,
+
+        /// Threshold of the ssss shares, ssss shares don't store it (`ssss-combine -t`)
+        #[clap(long, value_name = "THRESHOLD")]
+        ssss_threshold: Option<usize>,
+
+        #[clap(flatten)]
+        interop: InteropArgs,
+
+        #[clap(flatten)]
+        shared_secret: SharedSecretArgs,
+
+        #[clap(flatten)]
+        output: SplitOutputArgs,
+
+        /// Files with the shares of the other tool, one share per line
+        #[clap(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Split the passwords of a password manager export: KeePass XML, Bitwarden json or the password csv of a browser.
+    /// Every picked entry is split on its own with the shared secret config,
+    /// the title and the url of the entry are kept in its shares, they are not secret
+    ImportPasswords {
+        #[clap(
+            long,
+            arg_enum,
+            conflicts_with_all = &["share-dirs", "share-passphrases", "recipients"]
+        )]
+        from: ExportFormat,
+
+        /// Pick the KeePass entries with the tag. Can be repeated: the entries with any of the tags are picked
+        #[clap(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Pick the entries of the KeePass group or the Bitwarden folder and of its subfolders, e.g. `Infra/Servers`.
+        /// Can be repeated
+        #[clap(long = "folder", value_name = "FOLDER")]
+        folders: Vec<String>,
+
+        #[clap(flatten)]
+        shared_secret: SharedSecretArgs,
+
+        #[clap(flatten)]
+        output: SplitOutputArgs,
+
+        /// Make a printable paper sheet for every share, next to the share file
+        #[clap(long, arg_enum, value_name = "FORMAT")]
+        print_sheet: Option<SheetFormat>,
+
+        /// Holder of the share, like in `split`. With holders every holder gets a directory in --out-dir
+        /// with their shares of all the entries. Has to be repeated once per share
+        #[clap(long = "holder", value_name = "NAME")]
+        holders: Vec<String>,
+
+        #[clap(flatten)]
+        manifest: ManifestArgs,
+
+        /// The exported file. It contains every password in the clear: delete it once the shares are made
+        #[clap(value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// Export the secret as shares of another tool (SLIP-39, ssss), one file per share
+    Export {
+        #[clap(long, arg_enum)]
+        to: ShareFormat,
+
+        #[clap(flatten)]
+        interop: InteropArgs,
+
+        #[clap(flatten)]
+        shared_secret: SharedSecretArgs,
+
+        /// Directory to write the shares into
+        #[clap(short, long, default_value = share_files::DEFAULT_SHARES_DIR)]
+        out_dir: PathBuf,
+
+        #[clap(flatten)]
+        input: RestoreInputArgs,
+    },
+    /// Check that the shares restore the secret they were made from, without printing or writing the secret
+    Verify {
+        #[clap(flatten)]
+        input: RestoreInputArgs,
+    },
+    /// Check verifiable shares (`split --verifiable`) alone, without the other shares:
+    /// every share has to match the commitments of its set
+    Check {
+        #[clap(flatten)]
+        input: RestoreInputArgs,
+
+        /// Fingerprint of the commitments published at split time (or told by another holder),
+        /// the shares have to match it
+        #[clap(long, value_name = "FINGERPRINT")]
+        fingerprint: Option<String>,
+    },
+    /// Show what a share file is about (index, threshold, set) without revealing the secret
+    Inspect {
+        /// Share file: json or qr code image
+        file: PathBuf,
+    },
+    /// Check a directory of share files: group them by set, find missing and duplicate shares
+    /// and tell if the secret can be restored
+    Audit {
+        #[clap(default_value = share_files::DEFAULT_SHARES_DIR)]
+        dir: PathBuf,
+    },
+    /// Show who holds the shares of a set (recorded by `split --holder`) and whom a recovery needs
+    Holders {
+        /// Set id (or its beginning) or a share file of the set. All the recorded sets by default
+        set: Option<String>,
+
+        /// Holder that can't take part in the recovery (left, unreachable). Can be repeated
+        #[clap(long = "unavailable", value_name = "NAME")]
+        unavailable: Vec<String>,
+
+        #[clap(flatten)]
+        manifest: ManifestArgs,
+    },
+    /// Manage the config file
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
     },
 }
 
 #[derive(Debug, Clone, ArgEnum, Eq, PartialEq)]
 #[clap(rename_all = "kebab_case")]
 enum RestoreType {
+    /// Qr code images (png, jpeg, webp) and pdfs: scans of paper sheets or the sheets themselves
     Qr,
     Json,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct MetaSecretConfig {
-    shared_secret: SharedSecretConfig,
+    /// Text codes typed in from the paper sheets (.txt files)
+    Text,
+    /// Word lists (.words files)
+    Words,
+    /// Shares encrypted to recipients (.age files), decrypted with --identity
+    Age,
 }
 
 ///https://kerkour.com/rust-cross-compilation
-fn main() -> Result<()> {
+fn main() {
     let args: CmdLine = CmdLine::parse();
+    let format = args.format;
+
+    let result = run(args);
+    let exit_code = output::report(format, result);
+    std::process::exit(exit_code);
+}
+
+fn run(args: CmdLine) -> Result<CommandOutput> {
+    let config_path = args.config.as_deref();
+    let load_config = || config::load(config_path).with_context(|| "Error loading config");
+
+    let command_output = match args.command {
+        Command::Split {
+            shared_secret,
+            output,
+            print_sheet,
+            holders,
+            manifest,
+            batch_manifest: Some(batch_manifest),
+            ..
+        } => {
+            let config = load_config()?.config;
+            let threshold = config
+                .threshold(&shared_secret)
+                .with_context(|| "Invalid shared secret config")?;
+            let entries = batch::read_manifest(
+                &batch_manifest,
+                &batch::Defaults {
+                    holders: &holders,
+                    threshold,
+                },
+            )?;
+            let manifest_dir = manifest.manifest_dir()?;
+
+            batch::check_summary(output.out_dir())?;
+            let secrets = split_batch(&entries, &output, print_sheet, Some(&manifest_dir))?;
+            let report = BatchReport {
+                secrets,
+                holders: batch::holder_bundles(&entries, output.out_dir()),
+            };
+            let summary_file = batch::write_summary(output.out_dir(), &report)?;
+
+            CommandOutput::SplitBatch {
+                secrets: report.secrets,
+                holders: report.holders,
+                summary_file,
+            }
+        }
+        Command::Split {
+            secret,
+            shared_secret,
+            policy,
+            output,
+            hybrid,
+            show_qr,
+            print_sheet,
+            holders,
+            manifest,
+            batch_manifest: None,
+        } => {
+            let config = load_config()?.config;
+            let sharing = sharing(&config, &shared_secret, policy.as_deref(), &output)?;
+            let recipients = config
+                .recipients(output.recipients())
+                .with_context(|| "Invalid recipients")?;
+            let holders = share_holders(holders, &recipients, &sharing)?;
+            let manifest_dir = if holders.is_empty() {
+                None
+            } else {
+                Some(manifest.manifest_dir()?)
+            };
+
+            let (secret, mut written_set) = match secret.file() {
+                Some(file) if hybrid => split_hybrid(file, &sharing, &output, &recipients)?,
+                _ => {
+                    let secret = secret.read_secret()?;
+                    let written_set =
+                        write_shares(&secret, &sharing, &output, &recipients, None, None)?;
+                    (secret, written_set)
+                }
+            };
+
+            if let Some(sheet_format) = print_sheet {
+                write_share_sheets(&mut written_set.shares, &holders, sheet_format)?;
+            }
+
+            if show_qr {
+                terminal::show_shares(&terminal_shares(&json_files(&written_set.shares))?)?;
+            }
+
+            let encrypted_files = match &secret {
+                Secret::DataKey { encrypted_file, .. } => {
+                    distinct_dirs(output.target_dirs(sharing.number_of_shares()))
+                        .iter()
+                        .map(|dir| dir.join(encrypted_file))
+                        .collect()
+                }
+                _ => vec![],
+            };
+
+            let manifest_file = match manifest_dir {
+                Some(manifest_dir) => Some(record_holders(&manifest_dir, &written_set, &holders)?),
+                None => None,
+            };
+
+            CommandOutput::Split {
+                number_of_shares: sharing.number_of_shares(),
+                threshold: sharing.threshold(),
+                policy: policy_name(&sharing),
+                shares: written_set.shares,
+                encrypted_files,
+                manifest_file,
+                commitments_fingerprint: written_set.commitments_fingerprint,
+            }
+        }
+        Command::Reshare {
+            input,
+            shared_secret,
+            policy,
+            output,
+            print_sheet,
+            holders,
+            manifest,
+        } => {
+            let config = load_config()?.config;
+            let sharing = sharing(&config, &shared_secret, policy.as_deref(), &output)?;
+            let recipients = config
+                .recipients(output.recipients())
+                .with_context(|| "Invalid recipients")?;
+            let holders = share_holders(holders, &recipients, &sharing)?;
+            let manifest_dir = if holders.is_empty() {
+                None
+            } else {
+                Some(manifest.manifest_dir()?)
+            };
+
+            let files = input.share_files(share_files::is_share_file)?;
+            check_reshare_dirs(&files, &output.target_dirs(sharing.number_of_shares()))?;
+            let shares: Vec<ShareFile> = input
+                .read_share_files(&files)?
+                .into_iter()
+                .map(|(_, share_file)| share_file)
+                .collect();
+            let superseded_set = shares
+                .first()
+                .and_then(|share_file| share_file.set.as_ref())
+                .map(|set| set.set_id.clone());
+            let entry = shares
+                .first()
+                .and_then(|share_file| share_file.secret.entry.clone());
+
+            // the secret never leaves the memory
+            let secret = restore_from_json(shares)
+                .and_then(RestoredSecret::into_secret)
+                .with_context(|| "Can't restore the secret of the old shares")?;
+            let mut written_set = write_shares(
+                &secret,
+                &sharing,
+                &output,
+                &recipients,
+                superseded_set.as_deref(),
+                entry.as_ref(),
+            )?;
+
+            if let Some(sheet_format) = print_sheet {
+                write_share_sheets(&mut written_set.shares, &holders, sheet_format)?;
+            }
+
+            let manifest_file = match manifest_dir {
+                Some(manifest_dir) => Some(record_holders(&manifest_dir, &written_set, &holders)?),
+                None => None,
+            };
+
+            let encrypted_file = match secret {
+                Secret::DataKey { encrypted_file, .. } => Some(encrypted_file),
+                _ => None,
+            };
+
+            CommandOutput::Reshare {
+                superseded_set,
+                encrypted_file,
+                number_of_shares: sharing.number_of_shares(),
+                threshold: sharing.threshold(),
+                policy: policy_name(&sharing),
+                shares: written_set.shares,
+                manifest_file,
+                commitments_fingerprint: written_set.commitments_fingerprint,
+            }
+        }
+        Command::Restore {
+            from,
+            input,
+            output,
+            encrypted_file,
+        } => {
+            // qr codes are decoded in memory, no copies of the shares are left on the disk
+            let files = match from {
+                RestoreType::Qr => input.share_files(qr::is_qr_source)?,
+                RestoreType::Json => input.share_files(share_files::is_json_file)?,
+                RestoreType::Text => input.share_files(share_files::is_text_code_file)?,
+                RestoreType::Words => input.share_files(share_files::is_words_file)?,
+                RestoreType::Age => input.share_files(recipients::is_age_file)?,
+            };
+
+            // images and pdf pages without a readable qr code are reported, the shares found elsewhere may be enough
+            let read_shares = input.read_shares(&files)?;
+            let unreadable = read_shares.unreadable;
+            if read_shares.shares.is_empty() {
+                return Err(qr::QrError::NotFound {
+                    locations: unreadable,
+                })
+                .with_context(|| "Can't restore password");
+            }
+
+            // corrupted shares and shares of other secrets are left out, if the other shares are enough
+            let checked_secret = verify::restore(read_shares.shares).with_context(|| {
+                if unreadable.is_empty() {
+                    String::from("Can't restore password")
+                } else {
+                    format!(
+                        "Can't restore password, no qr code could be read in: {}",
+                        qr::display_locations(&unreadable)
+                    )
+                }
+            })?;
+            let faulty_shares = checked_secret.faulty_files;
+            let restored_secret = checked_secret.secret;
+
+            let share_dir = files.first().and_then(|file| file.parent());
+
+            match output {
+                Some(path) if restored_secret.metadata.encoding == SecretEncoding::DataKey => {
+                    let encrypted_file = encrypted_file
+                        .or_else(|| {
+                            let file_name = restored_secret.metadata.encrypted_file.as_ref()?;
+                            Some(share_dir?.join(file_name))
+                        })
+                        .with_context(|| "Encrypted file is unknown, use --encrypted-file")?;
+
+                    hybrid::decrypt_file(&restored_secret.content, &encrypted_file, &path)
+                        .with_context(|| "Error decrypting the file")?;
+
+                    CommandOutput::Restore {
+                        secret: None,
+                        output_file: Some(path),
+                        faulty_shares,
+                        unreadable,
+                    }
+                }
+                Some(path) => {
+                    share_files::write_secret_file(&path, &restored_secret.content)?;
+
+                    CommandOutput::Restore {
+                        secret: None,
+                        output_file: Some(path),
+                        faulty_shares,
+                        unreadable,
+                    }
+                }
+                None => CommandOutput::Restore {
+                    secret: Some(restored_secret.into_text()?),
+                    output_file: None,
+                    faulty_shares,
+                    unreadable,
+                },
+            }
+        }
+        Command::Show { files } => {
+            terminal::show_shares(&terminal_shares(&files)?)?;
+            CommandOutput::Show { files }
+        }
+        Command::ExportSheet {
+            sheet_format,
+            holders,
+            out_dir,
+            files,
+        } => {
+            let sheet_file = |file: &Path| {
+                let sheet_file = file.with_extension(sheet_format.extension());
+                match (&out_dir, sheet_file.file_name()) {
+                    (Some(out_dir), Some(file_name)) => out_dir.join(file_name),
+                    _ => sheet_file,
+                }
+            };
+
+            let sheets = write_sheets(&files, &holders, sheet_format, sheet_file)
+                .with_context(|| "Error making paper sheets")?;
+            CommandOutput::ExportSheet { sheets }
+        }
+        Command::ExportWords { out_dir, files } => {
+            let word_lists = files
+                .iter()
+                .map(|file| share_files::export_words(file, out_dir.as_deref()))
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| "Error making word lists")?;
+            CommandOutput::ExportWords { word_lists }
+        }
+        Command::Import {
+            from,
+            ssss_threshold,
+            interop,
+            shared_secret,
+            output,
+            files,
+        } => {
+            let config = load_config()?.config;
+            let shared_secret_config = config
+                .shared_secret_config(&shared_secret)
+                .with_context(|| "Invalid shared secret config")?;
+            let recipients = config
+                .recipients(output.recipients())
+                .with_context(|| "Invalid recipients")?;
 
-    let config_file = File::open("config.yaml")
-        .with_context(|| "Error reading config.yaml. Please check that file exists.")?;
+            let foreign_shares = interop::read_shares(&files)?;
+            let secret = interop
+                .import(from, &foreign_shares, ssss_threshold)
+                .with_context(|| format!("Error importing {} shares", from))?;
 
-    let app_config: MetaSecretConfig = serde_yaml::from_reader(config_file)
-        .with_context(|| "Error parsing config file. Invalid yaml format")?;
+            let sharing = Sharing::new(shared_secret_config, output.verifiable());
+            let written_set = write_shares(&secret, &sharing, &output, &recipients, None, None)?;
 
-    let shared_secret_config = app_config.shared_secret;
+            CommandOutput::Import {
+                format: from,
+                imported_shares: foreign_shares.len(),
+                number_of_shares: shared_secret_config.number_of_shares,
+                threshold: shared_secret_config.threshold,
+                shares: written_set.shares,
+                commitments_fingerprint: written_set.commitments_fingerprint,
+            }
+        }
+        Command::ImportPasswords {
+            from,
+            tags,
+            folders,
+            shared_secret,
+            output,
+            print_sheet,
+            holders,
+            manifest,
+            file,
+        } => {
+            let config = load_config()?.config;
+            let shared_secret_config = config
+                .shared_secret_config(&shared_secret)
+                .with_context(|| "Invalid shared secret config")?;
+            let sharing = Sharing::new(shared_secret_config, output.verifiable());
+            let holders = share_holders(holders, &[], &sharing)?;
+            let manifest_dir = if holders.is_empty() {
+                None
+            } else {
+                Some(manifest.manifest_dir()?)
+            };
+
+            let import = passwords::import(&file, from, &tags, &folders)
+                .with_context(|| format!("Error importing the {} export", from))?;
+            let names = passwords::secret_names(&import.entries);
+            let entries: Vec<BatchEntry> = import
+                .entries
+                .into_iter()
+                .zip(names)
+                .map(|(entry, name)| {
+                    batch::check_holders(&name, &holders)?;
+                    Ok(BatchEntry {
+                        name,
+                        threshold: shared_secret_config.threshold,
+                        number_of_shares: shared_secret_config.number_of_shares,
+                        holders: holders.clone(),
+                        source: SecretSource::Value(entry.password),
+                        entry: Some(ImportedEntry {
+                            title: entry.title,
+                            url: entry.url,
+                        }),
+                    })
+                })
+                .collect::<Result<_, batch::BatchError>>()?;
+
+            batch::check_summary(output.out_dir())?;
+            let secrets = split_batch(&entries, &output, print_sheet, manifest_dir.as_deref())?;
+            let report = BatchReport {
+                secrets,
+                holders: batch::holder_bundles(&entries, output.out_dir()),
+            };
+            let summary_file = batch::write_summary(output.out_dir(), &report)?;
 
-    match args.command {
-        Command::Split { secret } => {
-            split(secret, shared_secret_config).with_context(|| "Error splitting password")?
+            CommandOutput::ImportPasswords {
+                format: from,
+                secrets: report.secrets,
+                holders: report.holders,
+                without_password: import.without_password,
+                summary_file,
+            }
         }
-        Command::Restore { from } => match from {
-            RestoreType::Qr => {
-                convert_qr_images_to_json_files()
-                    .with_context(|| "Error converting qr codes into json files")?;
+        Command::Export {
+            to,
+            interop,
+            shared_secret,
+            out_dir,
+            input,
+        } => {
+            let shared_secret_config = load_config()?
+                .config
+                .shared_secret_config(&shared_secret)
+                .with_context(|| "Invalid shared secret config")?;
+
+            let files = input.share_files(share_files::is_share_file)?;
+            let shares = input
+                .read_share_files(&files)?
+                .into_iter()
+                .map(|(_, share_file)| share_file)
+                .collect();
+            let restored_secret =
+                restore_from_json(shares).with_context(|| "Can't restore password")?;
+            if restored_secret.metadata.encoding == SecretEncoding::DataKey {
+                return Err(InteropError::HybridSecret.into());
+            }
 
-                let password = restore_from_json().with_context(|| "Can't restore password")?;
+            let shares = interop
+                .export(to, &restored_secret.content, shared_secret_config)
+                .with_context(|| format!("Error exporting {} shares", to))?;
 
-                println!("Restored password: {:?}", password);
+            CommandOutput::Export {
+                format: to,
+                number_of_shares: shared_secret_config.number_of_shares,
+                threshold: shared_secret_config.threshold,
+                files: interop::write_shares(to, &shares, &out_dir)?,
             }
-            RestoreType::Json => {
-                let password = restore_from_json()?;
-                println!("Restored password: {:?}", password);
+        }
+        Command::Verify { input } => {
+            let files = input.share_files(share_files::is_share_file)?;
+            let shares = input.read_share_files(&files)?;
+
+            CommandOutput::Verify(verify::verify(shares).with_context(|| "Verification failed")?)
+        }
+        Command::Check { input, fingerprint } => {
+            let files = input.share_files(share_files::is_share_file)?;
+            let shares = input.read_share_files(&files)?;
+
+            CommandOutput::Check(verify::check(shares, fingerprint.as_deref())?)
+        }
+        Command::Inspect { file } => CommandOutput::Inspect(inspect::inspect(&file)?),
+        Command::Audit { dir } => CommandOutput::Audit(inspect::audit(&dir)?),
+        Command::Holders {
+            set,
+            unavailable,
+            manifest,
+        } => {
+            let config = load_config()?.config;
+            CommandOutput::Holders(holders::report(
+                &manifest.manifest_dir()?,
+                set.as_deref(),
+                &config.holders,
+                &unavailable,
+            )?)
+        }
+        Command::Config { command } => match command {
+            ConfigCommand::Init { force } => CommandOutput::ConfigInit {
+                path: config::init(config_path, force)?,
+            },
+            ConfigCommand::Show => {
+                let loaded_config = load_config()?;
+                CommandOutput::ConfigShow {
+                    source: loaded_config.source.to_string(),
+                    config: loaded_config.config,
+                }
             }
         },
+    };
+
+    Ok(command_output)
+}
+
+/// Shares written by one split
+struct WrittenSet {
+    set: ShareSetInfo,
+    /// Verifiable shares only, see `check`
+    commitments_fingerprint: Option<String>,
+    shares: Vec<WrittenShare>,
+}
+
+/// Splits the secret, writes the shares (encrypted to the recipients, if any are given)
+/// and checks that the written files restore the secret.
+/// `supersedes` is the id of the set the new shares replace,
+/// `entry` the password manager entry the secret was imported from
+fn write_shares(
+    secret: &Secret,
+    sharing: &Sharing,
+    output: &SplitOutputArgs,
+    recipients: &[Recipient],
+    supersedes: Option<&str>,
+    entry: Option<&ImportedEntry>,
+) -> Result<WrittenSet> {
+    if !recipients.is_empty() && recipients.len() != sharing.number_of_shares() {
+        return Err(ShareFileError::RecipientsMismatch {
+            expected: sharing.number_of_shares(),
+            actual: recipients.len(),
+        }
+        .into());
+    }
+
+    let mut shares =
+        share_files::split_secret(secret, sharing).with_context(|| "Error splitting password")?;
+    for share in &mut shares {
+        if let Some(set) = &mut share.set {
+            set.supersedes = supersedes.map(String::from);
+        }
+        share.secret.entry = entry.cloned();
+    }
+    let set = shares
+        .first()
+        .and_then(|share| share.set.clone())
+        .expect("Split always makes at least one share with the set information");
+    let commitments_fingerprint = shares
+        .first()
+        .and_then(|share| share.share.commitments_fingerprint());
+
+    let passphrases: Vec<Option<String>> = if output.share_passphrases() {
+        protection::read_passphrases(shares.len())?
+            .into_iter()
+            .map(Some)
+            .collect()
+    } else {
+        vec![None; shares.len()]
+    };
+    let stored_shares: Vec<StoredShare> = shares
+        .iter()
+        .cloned()
+        .zip(&passphrases)
+        .map(|(share, passphrase)| match passphrase {
+            Some(passphrase) => StoredShare::Encrypted(protection::encrypt(&share, passphrase)),
+            None => StoredShare::Plain(share),
+        })
+        .collect();
+
+    if !recipients.is_empty() {
+        let written_shares = output
+            .write_recipient_shares(&stored_shares, recipients)
+            .with_context(|| "Error saving shares")?;
+
+        // only the recipients can decrypt the written files, so the shares are checked before the encryption
+        let share_files = written_shares
+            .iter()
+            .filter_map(|written_share| written_share.age_file.clone())
+            .zip(shares)
+            .collect();
+        check_round_trip(share_files, sharing)?;
+
+        return Ok(WrittenSet {
+            set,
+            commitments_fingerprint,
+            shares: written_shares,
+        });
+    }
+
+    let written_shares = output
+        .write_shares(&stored_shares)
+        .with_context(|| "Error saving shares")?;
+
+    // the passphrases are known here, so encrypted shares are checked without asking for them again
+    let written_share_files = json_files(&written_shares)
+        .into_iter()
+        .zip(&passphrases)
+        .map(|(json_file, passphrase)| {
+            let share_file = share_files::read_stored_share(&json_file)?
+                .open(&json_file, passphrase.as_deref())?;
+            Ok((json_file, share_file))
+        })
+        .collect::<Result<Vec<_>, ShareFileError>>()?;
+    check_round_trip(written_share_files, sharing)?;
+
+    Ok(WrittenSet {
+        set,
+        commitments_fingerprint,
+        shares: written_shares,
+    })
+}
+
+/// Restores the secret from the written shares: from all of them, so that a corrupted file is named,
+/// then from a smallest set of them that has to be enough, see [threshold_subset]
+fn check_round_trip(share_files: Vec<(PathBuf, ShareFile)>, sharing: &Sharing) -> Result<()> {
+    let subset = threshold_subset(sharing);
+    let subset_files: Vec<(PathBuf, ShareFile)> = subset
+        .iter()
+        .filter_map(|&index| share_files.get(index).cloned())
+        .collect();
+
+    verify::verify(share_files).with_context(|| "Round-trip check of the written shares failed")?;
+    verify::verify(subset_files).with_context(|| {
+        format!(
+            "Round-trip check failed, the shares {:?} alone don't restore the secret",
+            subset
+        )
+    })?;
+    Ok(())
+}
+
+/// Indices of a smallest set of shares that restores the secret: the first `threshold` shares,
+/// for a policy the shares of a smallest set of holders meeting it
+fn threshold_subset(sharing: &Sharing) -> Vec<usize> {
+    match sharing.policy() {
+        Some(policy) => {
+            let min_holder_set = policy.root.min_holder_set();
+            policy
+                .root
+                .holders()
+                .iter()
+                .enumerate()
+                .filter(|(_, holder)| min_holder_set.contains(holder))
+                .map(|(index, _)| index)
+                .collect()
+        }
+        None => (0..sharing.threshold()).collect(),
+    }
+}
+
+/// `split --manifest`: every secret of the batch is split among its holders, the secrets in parallel.
+/// All the secrets are read before any share is written
+fn split_batch(
+    entries: &[BatchEntry],
+    output: &SplitOutputArgs,
+    print_sheet: Option<SheetFormat>,
+    manifest_dir: Option<&Path>,
+) -> Result<Vec<SecretReport>> {
+    let mut secrets = vec![];
+    let mut failures = vec![];
+    for entry in entries {
+        match entry.read_secret() {
+            Ok(secret) => secrets.push(secret),
+            Err(error) => failures.push(SecretFailure {
+                name: entry.name.clone(),
+                error: error.context("Error reading the secret"),
+            }),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(BatchError::SecretsFailed {
+            total: entries.len(),
+            split: vec![],
+            failures,
+        }
+        .into());
+    }
+
+    // every secret is split even if another one fails, the failures are reported together
+    let results: Vec<Result<SecretReport>> = entries
+        .par_iter()
+        .zip(secrets.par_iter())
+        .map(|(entry, secret)| {
+            let sharing = Sharing::new(
+                SharedSecretConfig {
+                    number_of_shares: entry.number_of_shares,
+                    threshold: entry.threshold,
+                },
+                output.verifiable(),
+            );
+
+            let mut written_set = write_shares(
+                secret,
+                &sharing,
+                &output.with_share_dirs(entry.share_dirs(output.out_dir())),
+                &[],
+                None,
+                entry.entry.as_ref(),
+            )?;
+            if let Some(sheet_format) = print_sheet {
+                write_share_sheets(&mut written_set.shares, &entry.holders, sheet_format)?;
+            }
+            let manifest_file = match manifest_dir {
+                Some(manifest_dir) if !entry.holders.is_empty() => {
+                    Some(record_holders(manifest_dir, &written_set, &entry.holders)?)
+                }
+                _ => None,
+            };
+
+            Ok(SecretReport {
+                name: entry.name.clone(),
+                set_id: written_set.set.set_id,
+                threshold: entry.threshold,
+                number_of_shares: entry.number_of_shares,
+                holders: entry.holders.clone(),
+                entry: entry.entry.clone(),
+                commitments_fingerprint: written_set.commitments_fingerprint,
+                manifest_file,
+            })
+        })
+        .collect();
+
+    let mut reports = vec![];
+    for (entry, result) in entries.iter().zip(results) {
+        match result {
+            Ok(report) => reports.push(report),
+            Err(error) => failures.push(SecretFailure {
+                name: entry.name.clone(),
+                error,
+            }),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(BatchError::SecretsFailed {
+            total: entries.len(),
+            split: reports.into_iter().map(|report| report.name).collect(),
+            failures,
+        }
+        .into());
+    }
+
+    Ok(reports)
+}
+
+/// The policy named by `--policy`, or k of n of the shared secret config
+fn sharing(
+    config: &MetaSecretConfig,
+    shared_secret: &SharedSecretArgs,
+    policy: Option<&str>,
+    output: &SplitOutputArgs,
+) -> Result<Sharing> {
+    let sharing = match policy {
+        Some(name) => Sharing::Policy(config.policy(name)?),
+        None => Sharing::new(
+            config
+                .shared_secret_config(shared_secret)
+                .with_context(|| "Invalid shared secret config")?,
+            output.verifiable(),
+        ),
+    };
+
+    Ok(sharing)
+}
+
+fn policy_name(sharing: &Sharing) -> Option<String> {
+    sharing.policy().map(|policy| policy.name.clone())
+}
+
+/// Holders of the new shares: the recipients, if the shares are encrypted to them
+/// (in the order of the holders of the policy, if there is one), the holders of the policy or the `--holder` names.
+/// Checked before anything is written
+fn share_holders(
+    holders: Vec<String>,
+    recipients: &[Recipient],
+    sharing: &Sharing,
+) -> Result<Vec<String>> {
+    let holders = match sharing.policy() {
+        Some(policy) => policy
+            .root
+            .holders()
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        None => holders,
+    };
+    sheet::holder_labels(&holders, sharing.number_of_shares())?;
+
+    if recipients.is_empty() {
+        return Ok(holders);
+    }
+
+    Ok(recipients
+        .iter()
+        .map(|recipient| recipient.name.clone())
+        .collect())
+}
+
+/// The old shares stay untouched until the new ones are written and checked
+/// Hybrid mode: encrypts the file next to every share and splits its data key.
+/// Nothing is encrypted when the shares can't be written, the encrypted files of a failed split are removed
+fn split_hybrid(
+    file: &Path,
+    sharing: &Sharing,
+    output: &SplitOutputArgs,
+    recipients: &[Recipient],
+) -> Result<(Secret, WrittenSet)> {
+    output.check_target_dirs(sharing.number_of_shares())?;
+    let target_dirs = distinct_dirs(output.target_dirs(sharing.number_of_shares()));
+    let secret =
+        hybrid::encrypt_file(file, &target_dirs).with_context(|| "Error encrypting the file")?;
+
+    match write_shares(&secret, sharing, output, recipients, None, None) {
+        Ok(written_set) => Ok((secret, written_set)),
+        Err(err) => {
+            if let Secret::DataKey { encrypted_file, .. } = &secret {
+                hybrid::remove_encrypted_files(encrypted_file, &target_dirs);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// The directories in the order of the shares, each only once
+fn distinct_dirs(dirs: Vec<&Path>) -> Vec<&Path> {
+    let mut distinct: Vec<&Path> = vec![];
+    for dir in dirs {
+        if !distinct.contains(&dir) {
+            distinct.push(dir);
+        }
+    }
+    distinct
+}
+
+fn check_reshare_dirs(old_files: &[PathBuf], target_dirs: &[&Path]) -> Result<(), ShareFileError> {
+    let target_dirs: HashSet<PathBuf> = target_dirs
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .collect();
+
+    for old_file in old_files {
+        let old_dir = old_file
+            .canonicalize()
+            .ok()
+            .and_then(|file| file.parent().map(Path::to_path_buf));
+        if let Some(old_dir) = old_dir.filter(|old_dir| target_dirs.contains(old_dir)) {
+            return Err(ShareFileError::SameDirReshare { dir: old_dir });
+        }
     }
 
-    println!("Finished");
     Ok(())
 }
 
+/// Makes a paper sheet next to every written share
+fn write_share_sheets(
+    written_shares: &mut [WrittenShare],
+    holders: &[String],
+    sheet_format: SheetFormat,
+) -> Result<()> {
+    let sheet_files = write_sheets(
+        &json_files(written_shares),
+        holders,
+        sheet_format,
+        |json_file| json_file.with_extension(sheet_format.extension()),
+    )
+    .with_context(|| "Error making paper sheets")?;
+
+    for (written_share, sheet_file) in written_shares.iter_mut().zip(sheet_files) {
+        written_share.sheet_file = Some(sheet_file);
+    }
+
+    Ok(())
+}
+
+/// Writes the manifest of the holders of the written shares, see the `holders` command
+fn record_holders(
+    manifest_dir: &Path,
+    written_set: &WrittenSet,
+    holders: &[String],
+) -> Result<PathBuf> {
+    let manifest = holders::manifest(&written_set.set, holders, &written_set.shares);
+    Ok(holders::write_manifest(manifest_dir, &manifest)?)
+}
+
+fn json_files(written_shares: &[WrittenShare]) -> Vec<PathBuf> {
+    written_shares
+        .iter()
+        .filter_map(|written_share| written_share.json_file.clone())
+        .collect()
+}
+
+fn terminal_shares(files: &[PathBuf]) -> Result<Vec<TerminalShare>, ShareFileError> {
+    files
+        .iter()
+        .map(|file| {
+            let (share_file, share_json) = share_files::read_compact_share(file)?;
+            Ok(TerminalShare {
+                path: file.clone(),
+                share_file,
+                share_json,
+            })
+        })
+        .collect()
+}
+
+/// Makes a sheet for every share file, `sheet_file` maps the share file to the path of its sheet
+fn write_sheets(
+    files: &[PathBuf],
+    holders: &[String],
+    format: SheetFormat,
+    sheet_file: impl Fn(&Path) -> PathBuf,
+) -> Result<Vec<PathBuf>> {
+    let holders = sheet::holder_labels(holders, files.len())?;
+    let date = sheet::today();
+
+    let mut sheet_files = vec![];
+    for (file, holder) in files.iter().zip(holders) {
+        let (share_file, share_json) = share_files::read_compact_share(file)?;
+        let path = sheet_file(file);
+
+        let sheet = Sheet {
+            share_file: &share_file,
+            share_json: &share_json,
+            holder,
+            date: &date,
+        };
+        sheet::write_sheet(&path, &sheet, format)?;
+
+        sheet_files.push(path);
+    }
+
+    Ok(sheet_files)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RestoreError {
     /// https://dailydevsblog.com/troubleshoot/resolved-issue-with-a-string-and-thiserror-as_dyn_error-exists-for-reference-string-but-its-trait-bounds-were-not-satisfied-in-rust-139876/
     #[error(transparent)]
-    RecoveryError(#[from] RecoveryOperationError),
+    RecoveryError(#[from] CoreError),
     #[error("Error parse binary data. Non utf8 encoding.")]
     ParsingError {
         #[from]
         source: FromUtf8Error,
     },
+    #[error("Error decoding restored data. Invalid base64 encoding.")]
+    DecodingError {
+        #[from]
+        source: base64::DecodeError,
+    },
+    #[error("Not enough shares: found {found}, at least {required} required")]
+    NotEnoughShares { found: usize, required: usize },
+    #[error("Shares belong to different secrets")]
+    MixedShares,
+    #[error("The secret is a binary file ({file_name}), use --output <FILE> to save it")]
+    BinarySecret { file_name: String },
+    #[error(transparent)]
+    VssError(#[from] VssError),
+    #[error(transparent)]
+    PolicyError(#[from] PolicyError),
+}
+
+struct RestoredSecret {
+    content: Vec<u8>,
+    metadata: SecretMetadata,
+}
+
+impl RestoredSecret {
+    fn into_text(self) -> Result<String, RestoreError> {
+        if self.metadata.encoding != SecretEncoding::Text {
+            return Err(RestoreError::BinarySecret {
+                file_name: self
+                    .metadata
+                    .file_name
+                    .unwrap_or_else(|| String::from("unnamed")),
+            });
+        }
+
+        let password = String::from_utf8(self.content)?;
+        Ok(password)
+    }
+
+    /// The secret as it was split, to split it again
+    fn into_secret(self) -> Result<Secret, RestoreError> {
+        let metadata = self.metadata;
+        let content_type = metadata
+            .content_type
+            .unwrap_or_else(|| String::from("application/octet-stream"));
+
+        let secret = match metadata.encoding {
+            SecretEncoding::Text => Secret::Text(String::from_utf8(self.content)?),
+            SecretEncoding::Base64 => Secret::File {
+                file_name: metadata.file_name,
+                content_type,
+                content: self.content,
+            },
+            // the encrypted file stays as it is, only its key is split again
+            SecretEncoding::DataKey => Secret::DataKey {
+                key: self.content,
+                encrypted_file: metadata.encrypted_file.unwrap_or_default(),
+                file_name: metadata.file_name,
+                content_type,
+            },
+        };
+
+        Ok(secret)
+    }
+}
+
+fn restore_from_json(share_files: Vec<ShareFile>) -> Result<RestoredSecret, RestoreError> {
+    let (metadata, set) = match share_files.first() {
+        Some(share_file) => (share_file.secret.clone(), share_file.set.clone()),
+        None => (SecretMetadata::default(), None),
+    };
+
+    let set_id = set.as_ref().map(|set| &set.set_id);
+    let mixed_shares = share_files.iter().any(|share_file| {
+        share_file.secret != metadata || share_file.set.as_ref().map(|set| &set.set_id) != set_id
+    });
+    if mixed_shares {
+        return Err(RestoreError::MixedShares);
+    }
+
+    // the same share can be provided several times (e.g. as a json file and as a qr code)
+    let mut share_files = share_files;
+    if set.is_some() {
+        let mut indices = HashSet::new();
+        share_files.retain(|share_file| match &share_file.set {
+            Some(set) => indices.insert(set.index),
+            None => true,
+        });
+    }
+
+    let policy = set.as_ref().and_then(|set| set.policy.clone());
+    match &policy {
+        // tells which groups of the policy are still missing
+        Some(policy) => policy.check(
+            share_files
+                .iter()
+                .filter_map(|share_file| share_file.set.as_ref())
+                .map(|set| set.index),
+        )?,
+        None => {
+            let required = set.map(|set| set.threshold).unwrap_or(MIN_THRESHOLD);
+            if share_files.len() < required {
+                return Err(RestoreError::NotEnoughShares {
+                    found: share_files.len(),
+                    required,
+                });
+            }
+        }
+    }
+
+    let shares = share_files
+        .into_iter()
+        .map(|share_file| share_file.share)
+        .collect();
+    let text = recover_text(shares, policy.as_ref())?;
+    let content = share_files::decode_secret(text, &metadata)?;
+    Ok(RestoredSecret { content, metadata })
 }
 
-fn restore_from_json() -> Result<String, RestoreError> {
-    let text = recover()?;
-    let password = String::from_utf8(text.text)?;
-    Ok(password)
+/// Restores the split text from the shares of the core, from the verifiable shares
+/// or from the shares of the holders of the policy
+fn recover_text(
+    shares: Vec<ShareData>,
+    policy: Option<&SetPolicy>,
+) -> Result<Vec<u8>, RestoreError> {
+    let mut core_shares = vec![];
+    let mut verifiable_shares = vec![];
+    let mut policy_shares = vec![];
+    for share in shares {
+        match share {
+            ShareData::Core(share) => core_shares.push(share),
+            ShareData::Verifiable(share) => verifiable_shares.push(share),
+            ShareData::Policy(share) => policy_shares.push(share),
+        }
+    }
+
+    if !policy_shares.is_empty() {
+        if !core_shares.is_empty() || !verifiable_shares.is_empty() {
+            return Err(RestoreError::MixedShares);
+        }
+        let policy = policy.ok_or(PolicyError::NoPolicy)?;
+        return Ok(policy.root.recover(&policy_shares)?);
+    }
+
+    if verifiable_shares.is_empty() {
+        return Ok(recover_from_shares(core_shares)?.text.into_bytes());
+    }
+    if !core_shares.is_empty() {
+        return Err(RestoreError::MixedShares);
+    }
+
+    let commitments = &verifiable_shares[0].commitments;
+    if verifiable_shares
+        .iter()
+        .any(|share| share.commitments != *commitments)
+    {
+        return Err(VssError::DifferentCommitments.into());
+    }
+
+    let commitments = vss::Commitments::decode(commitments)?;
+    let shares = verifiable_shares
+        .iter()
+        .map(|share| vss::Share::decode(&share.vss_share))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(vss::combine(&shares, &commitments)?)
 }
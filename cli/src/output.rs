@@ -0,0 +1,685 @@
+use std::path::{Path, PathBuf};
+
+use clap::ArgEnum;
+use meta_secret_share_formats::qr_parts::PartsError;
+use meta_secret_share_formats::slip39::Slip39Error;
+use meta_secret_share_formats::ssss::SsssError;
+use meta_secret_share_formats::vss::VssError;
+use serde::Serialize;
+
+use crate::batch::{BatchError, HolderBundle, SecretReport};
+use crate::config::{ConfigError, MetaSecretConfig};
+use crate::holders::{HoldersError, HoldersReport};
+use crate::hybrid::HybridError;
+use crate::inspect::{AuditReport, ShareInfo};
+use crate::interop::{InteropError, ShareFormat};
+use crate::passwords::{ExportFormat, ImportError};
+use crate::policy::PolicyError;
+use crate::qr::QrError;
+use crate::recipients::RecipientError;
+use crate::share_files::{ShareFileError, WordsFile, WrittenShare};
+use crate::sheet::SheetError;
+use crate::terminal::TerminalError;
+use crate::verify::{CheckReport, VerifyError, VerifyReport};
+use crate::RestoreError;
+
+const WORDS_PER_ROW: usize = 6;
+
+#[derive(Debug, Clone, Copy, ArgEnum, Eq, PartialEq)]
+#[clap(rename_all = "kebab_case")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Result of a successfully executed command
+#[derive(Debug, Serialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum CommandOutput {
+    Split {
+        number_of_shares: usize,
+        threshold: usize,
+        /// Name of the policy of `split --policy`, `threshold` is then the smallest number of holders meeting it
+        #[serde(skip_serializing_if = "Option::is_none")]
+        policy: Option<String>,
+        shares: Vec<WrittenShare>,
+        /// Hybrid mode: the encrypted file, one copy next to the shares of every share directory
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        encrypted_files: Vec<PathBuf>,
+        /// Manifest of the holders, see the `holders` command
+        #[serde(skip_serializing_if = "Option::is_none")]
+        manifest_file: Option<PathBuf>,
+        /// Verifiable shares only, see the `check` command
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commitments_fingerprint: Option<String>,
+    },
+    /// `split --manifest`
+    SplitBatch {
+        secrets: Vec<SecretReport>,
+        /// Every holder's directory with their shares
+        holders: Vec<HolderBundle>,
+        /// The same report as a json file, it contains no secrets
+        summary_file: PathBuf,
+    },
+    ImportPasswords {
+        format: ExportFormat,
+        secrets: Vec<SecretReport>,
+        /// Every holder's directory with their shares, if the shares have holders
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        holders: Vec<HolderBundle>,
+        /// Titles of the picked entries without a password, nothing is written for them
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        without_password: Vec<String>,
+        /// The same report as a json file, it contains no passwords
+        summary_file: PathBuf,
+    },
+    Reshare {
+        /// Id of the old set, its shares should be destroyed
+        #[serde(skip_serializing_if = "Option::is_none")]
+        superseded_set: Option<String>,
+        /// Hybrid mode: name of the encrypted file, the new shares restore it as well
+        #[serde(skip_serializing_if = "Option::is_none")]
+        encrypted_file: Option<String>,
+        number_of_shares: usize,
+        threshold: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        policy: Option<String>,
+        shares: Vec<WrittenShare>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        manifest_file: Option<PathBuf>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commitments_fingerprint: Option<String>,
+    },
+    Restore {
+        /// The restored secret, when it's not written into a file
+        #[serde(skip_serializing_if = "Option::is_none")]
+        secret: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output_file: Option<PathBuf>,
+        /// Corrupted shares and shares of other secrets, left out of the recovery
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        faulty_shares: Vec<PathBuf>,
+        /// Images and pdf pages where no qr code could be read
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        unreadable: Vec<PathBuf>,
+    },
+    Show {
+        files: Vec<PathBuf>,
+    },
+    ExportSheet {
+        sheets: Vec<PathBuf>,
+    },
+    ExportWords {
+        word_lists: Vec<WordsFile>,
+    },
+    Import {
+        format: ShareFormat,
+        imported_shares: usize,
+        number_of_shares: usize,
+        threshold: usize,
+        shares: Vec<WrittenShare>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commitments_fingerprint: Option<String>,
+    },
+    Export {
+        format: ShareFormat,
+        number_of_shares: usize,
+        threshold: usize,
+        files: Vec<PathBuf>,
+    },
+    Verify(VerifyReport),
+    Check(CheckReport),
+    Inspect(ShareInfo),
+    Audit(AuditReport),
+    Holders(HoldersReport),
+    ConfigInit {
+        path: PathBuf,
+    },
+    ConfigShow {
+        source: String,
+        config: MetaSecretConfig,
+    },
+}
+
+/// Process exit codes. They are a part of the cli interface, so existing values must never change.
+/// Usage errors (invalid command line arguments) are reported by clap with code 2
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitCode {
+    Success = 0,
+    /// Any error not covered by the other codes
+    Failure = 1,
+    BadConfig = 3,
+    Io = 4,
+    NotEnoughShares = 5,
+    MismatchedShares = 6,
+    CorruptShare = 7,
+    RecoveryFailed = 8,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum JsonReport<'a> {
+    Ok {
+        #[serde(flatten)]
+        output: &'a CommandOutput,
+    },
+    Error {
+        exit_code: i32,
+        kind: ExitCode,
+        message: String,
+        causes: Vec<String>,
+    },
+}
+
+/// Prints the result of the command in the requested format and returns the exit code of the process
+pub fn report(format: OutputFormat, result: anyhow::Result<CommandOutput>) -> i32 {
+    let exit_code = match &result {
+        Ok(_) => ExitCode::Success,
+        Err(err) => exit_code(err),
+    };
+
+    match format {
+        OutputFormat::Text => match &result {
+            Ok(output) => output.print_text(),
+            Err(err) => eprintln!("Error: {:?}", err),
+        },
+        OutputFormat::Json => {
+            let report = match &result {
+                Ok(output) => JsonReport::Ok { output },
+                Err(err) => JsonReport::Error {
+                    exit_code: exit_code as i32,
+                    kind: exit_code,
+                    message: err.to_string(),
+                    causes: err.chain().skip(1).map(|cause| cause.to_string()).collect(),
+                },
+            };
+
+            let json = serde_json::to_string_pretty(&report).expect("Error serializing output");
+            println!("{}", json);
+        }
+    }
+
+    exit_code as i32
+}
+
+/// Finds the most specific known error in the chain
+fn exit_code(err: &anyhow::Error) -> ExitCode {
+    for cause in err.chain() {
+        if cause.is::<ConfigError>() {
+            return ExitCode::BadConfig;
+        }
+
+        if let Some(restore_error) = cause.downcast_ref::<RestoreError>() {
+            return restore_exit_code(restore_error);
+        }
+
+        if let Some(verify_error) = cause.downcast_ref::<VerifyError>() {
+            return match verify_error {
+                VerifyError::CorruptedShares { .. } | VerifyError::NoMatchingShares { .. } => {
+                    ExitCode::CorruptShare
+                }
+                VerifyError::CommitmentMismatch | VerifyError::CheckKeyError(_) => {
+                    ExitCode::RecoveryFailed
+                }
+                VerifyError::RestoreError(restore_error) => restore_exit_code(restore_error),
+                VerifyError::NotEnoughGoodShares { source, .. } => match source.as_ref() {
+                    VerifyError::RestoreError(restore_error) => restore_exit_code(restore_error),
+                    _ => ExitCode::CorruptShare,
+                },
+                VerifyError::InvalidShare { .. } | VerifyError::FingerprintMismatch { .. } => {
+                    ExitCode::CorruptShare
+                }
+                VerifyError::NotVerifiable { .. } | VerifyError::TooManyCombinations { .. } => {
+                    ExitCode::Failure
+                }
+            };
+        }
+
+        if let Some(share_file_error) = cause.downcast_ref::<ShareFileError>() {
+            return share_file_exit_code(share_file_error);
+        }
+
+        if let Some(recipient_error) = cause.downcast_ref::<RecipientError>() {
+            return recipient_exit_code(recipient_error);
+        }
+
+        if let Some(interop_error) = cause.downcast_ref::<InteropError>() {
+            return interop_exit_code(interop_error);
+        }
+
+        if let Some(hybrid_error) = cause.downcast_ref::<HybridError>() {
+            return match hybrid_error {
+                HybridError::ReadError { .. }
+                | HybridError::WriteError { .. }
+                | HybridError::AlreadyExists { .. } => ExitCode::Io,
+                HybridError::InvalidFormat { .. } | HybridError::DecryptionError { .. } => {
+                    ExitCode::CorruptShare
+                }
+                HybridError::InvalidKey { .. } => ExitCode::RecoveryFailed,
+            };
+        }
+
+        if let Some(sheet_error) = cause.downcast_ref::<SheetError>() {
+            return match sheet_error {
+                SheetError::HoldersMismatch { .. } => ExitCode::Failure,
+                SheetError::WriteError { .. } => ExitCode::Io,
+            };
+        }
+
+        if let Some(batch_error) = cause.downcast_ref::<BatchError>() {
+            return match batch_error {
+                BatchError::ReadError { .. } => ExitCode::Io,
+                BatchError::SecretsFailed { .. } => ExitCode::Failure,
+                _ => ExitCode::BadConfig,
+            };
+        }
+
+        if let Some(import_error) = cause.downcast_ref::<ImportError>() {
+            return match import_error {
+                ImportError::ReadError { .. } => ExitCode::Io,
+                _ => ExitCode::Failure,
+            };
+        }
+
+        if let Some(holders_error) = cause.downcast_ref::<HoldersError>() {
+            return match holders_error {
+                HoldersError::WriteError { .. } | HoldersError::ReadError { .. } => ExitCode::Io,
+                HoldersError::ShareFileError(share_file_error) => {
+                    share_file_exit_code(share_file_error)
+                }
+                HoldersError::NoDataDir
+                | HoldersError::InvalidManifest { .. }
+                | HoldersError::UnknownSet { .. }
+                | HoldersError::AmbiguousSet { .. }
+                | HoldersError::NoSet { .. } => ExitCode::Failure,
+            };
+        }
+
+        if let Some(terminal_error) = cause.downcast_ref::<TerminalError>() {
+            return match terminal_error {
+                TerminalError::NotATerminal
+                | TerminalError::TooBig { .. }
+                | TerminalError::QrError { .. } => ExitCode::Failure,
+                TerminalError::Io(_) => ExitCode::Io,
+            };
+        }
+
+        if cause.is::<QrError>() {
+            return ExitCode::CorruptShare;
+        }
+
+        if cause.is::<std::io::Error>() {
+            return ExitCode::Io;
+        }
+    }
+
+    ExitCode::Failure
+}
+
+fn share_file_exit_code(share_file_error: &ShareFileError) -> ExitCode {
+    match share_file_error {
+        ShareFileError::InvalidShare { .. }
+        | ShareFileError::InvalidTextCode { .. }
+        | ShareFileError::InvalidWords { .. }
+        | ShareFileError::InvalidEnvelope { .. }
+        | ShareFileError::KdfTooExpensive { .. }
+        | ShareFileError::WrongPassphrase { .. } => ExitCode::CorruptShare,
+        ShareFileError::NoShares { .. } | ShareFileError::QrPartAlone { .. } => {
+            ExitCode::NotEnoughShares
+        }
+        ShareFileError::QrError(_) | ShareFileError::InvalidQrPart { .. } => ExitCode::CorruptShare,
+        ShareFileError::QrParts { source, .. } => match source {
+            PartsError::MissingParts { .. } => ExitCode::NotEnoughShares,
+            _ => ExitCode::CorruptShare,
+        },
+        ShareFileError::VssError(vss_error) => vss_exit_code(vss_error),
+        ShareFileError::PolicyError(policy_error) => policy_exit_code(policy_error),
+        ShareFileError::RecipientError(recipient_error) => recipient_exit_code(recipient_error),
+        ShareFileError::ShareDirsMismatch { .. }
+        | ShareFileError::RecipientsMismatch { .. }
+        | ShareFileError::DuplicateRecipientFile { .. }
+        | ShareFileError::SameDirReshare { .. }
+        | ShareFileError::VerifiableWords { .. }
+        | ShareFileError::PolicyWords { .. }
+        | ShareFileError::SplitError(_)
+        | ShareFileError::NotATextSecret { .. } => ExitCode::Failure,
+        ShareFileError::WriteError { .. }
+        | ShareFileError::ReadError { .. }
+        | ShareFileError::ReadDirError { .. }
+        | ShareFileError::PassphraseError { .. }
+        | ShareFileError::VerificationError { .. }
+        | ShareFileError::AlreadyExists { .. }
+        | ShareFileError::SharesExist { .. } => ExitCode::Io,
+    }
+}
+
+fn restore_exit_code(restore_error: &RestoreError) -> ExitCode {
+    match restore_error {
+        RestoreError::NotEnoughShares { .. } => ExitCode::NotEnoughShares,
+        RestoreError::MixedShares => ExitCode::MismatchedShares,
+        RestoreError::RecoveryError(_)
+        | RestoreError::ParsingError { .. }
+        | RestoreError::DecodingError { .. } => ExitCode::RecoveryFailed,
+        RestoreError::BinarySecret { .. } => ExitCode::Failure,
+        RestoreError::VssError(vss_error) => vss_exit_code(vss_error),
+        RestoreError::PolicyError(policy_error) => policy_exit_code(policy_error),
+    }
+}
+
+fn policy_exit_code(policy_error: &PolicyError) -> ExitCode {
+    match policy_error {
+        PolicyError::Unmet { .. } => ExitCode::NotEnoughShares,
+        PolicyError::GroupNotMet { .. } | PolicyError::InvalidPart { .. } => ExitCode::CorruptShare,
+        PolicyError::NoPolicy => ExitCode::MismatchedShares,
+        PolicyError::CoreError(_) => ExitCode::RecoveryFailed,
+        PolicyError::EmptyGroup { .. }
+        | PolicyError::ZeroThreshold { .. }
+        | PolicyError::ThresholdExceedsWeight { .. }
+        | PolicyError::TooManyShares { .. }
+        | PolicyError::ZeroWeight { .. }
+        | PolicyError::DuplicateHolder { .. } => ExitCode::BadConfig,
+    }
+}
+
+fn vss_exit_code(vss_error: &VssError) -> ExitCode {
+    match vss_error {
+        VssError::InvalidShare | VssError::InvalidCommitments | VssError::ShareMismatch { .. } => {
+            ExitCode::CorruptShare
+        }
+        VssError::DifferentCommitments | VssError::MixedShares => ExitCode::MismatchedShares,
+        VssError::NotEnoughShares { .. } => ExitCode::NotEnoughShares,
+        VssError::InvalidThreshold { .. }
+        | VssError::TooBig { .. }
+        | VssError::DuplicateShare { .. } => ExitCode::Failure,
+    }
+}
+
+impl CommandOutput {
+    fn print_text(&self) {
+        match self {
+            CommandOutput::Split {
+                shares,
+                encrypted_files,
+                manifest_file,
+                commitments_fingerprint,
+                policy,
+                ..
+            } => {
+                for encrypted_file in encrypted_files {
+                    println!("Encrypted file: {}", encrypted_file.display());
+                }
+
+                print_written_shares(shares);
+                print_commitments_fingerprint(commitments_fingerprint);
+                if let Some(manifest_file) = manifest_file {
+                    println!("Holders recorded in: {}", manifest_file.display());
+                }
+                if let Some(policy) = policy {
+                    println!(
+                        "The holders meeting the policy {} restore the secret",
+                        policy
+                    );
+                }
+                println!("Finished");
+            }
+            CommandOutput::SplitBatch {
+                secrets,
+                holders,
+                summary_file,
+            } => {
+                print_batch(secrets, holders, summary_file);
+                println!("Finished");
+            }
+            CommandOutput::ImportPasswords {
+                secrets,
+                holders,
+                without_password,
+                summary_file,
+                ..
+            } => {
+                print_batch(secrets, holders, summary_file);
+                if !without_password.is_empty() {
+                    println!(
+                        "Entries without a password, left out: {}",
+                        without_password.join(", ")
+                    );
+                }
+                println!("Delete the export, it contains every password in the clear");
+                println!("Finished");
+            }
+            CommandOutput::Reshare {
+                superseded_set,
+                encrypted_file,
+                number_of_shares,
+                threshold,
+                policy,
+                shares,
+                manifest_file,
+                commitments_fingerprint,
+            } => {
+                print_written_shares(shares);
+                print_commitments_fingerprint(commitments_fingerprint);
+                if let Some(manifest_file) = manifest_file {
+                    println!("Holders recorded in: {}", manifest_file.display());
+                }
+                match policy {
+                    Some(policy) => println!(
+                        "The holders meeting the policy {} restore the secret",
+                        policy
+                    ),
+                    None => println!(
+                        "Any {} of the {} new shares restore the secret",
+                        threshold, number_of_shares
+                    ),
+                }
+                if let Some(encrypted_file) = encrypted_file {
+                    println!(
+                        "The encrypted file {} is not changed, keep it next to the new shares",
+                        encrypted_file
+                    );
+                }
+                if let Some(superseded_set) = superseded_set {
+                    println!(
+                        "The new shares supersede the set {}, its shares should be destroyed",
+                        superseded_set
+                    );
+                }
+                println!("Finished");
+            }
+            CommandOutput::Restore {
+                secret,
+                output_file,
+                faulty_shares,
+                unreadable,
+            } => {
+                for location in unreadable {
+                    println!("No qr code could be read in: {}", location.display());
+                }
+                for faulty_share in faulty_shares {
+                    println!(
+                        "Left out corrupted or foreign share: {}",
+                        faulty_share.display()
+                    );
+                }
+                if let Some(secret) = secret {
+                    println!("Restored password: {}", secret);
+                }
+                if let Some(output_file) = output_file {
+                    println!(
+                        "Restored secret has been written to: {}",
+                        output_file.display()
+                    );
+                }
+                println!("Finished");
+            }
+            CommandOutput::Show { files } => {
+                println!("Shown shares: {}", files.len());
+            }
+            CommandOutput::ExportSheet { sheets } => {
+                for sheet in sheets {
+                    println!("Sheet: {}", sheet.display());
+                }
+                println!("Finished");
+            }
+            CommandOutput::ExportWords { word_lists } => {
+                for word_list in word_lists {
+                    println!("Word list: {}", word_list.file.display());
+                    print_numbered_words(&word_list.words);
+                    println!();
+                }
+                println!("Finished");
+            }
+            CommandOutput::Import {
+                format,
+                imported_shares,
+                shares,
+                commitments_fingerprint,
+                ..
+            } => {
+                println!("Imported {} shares: {}", format, imported_shares);
+                print_written_shares(shares);
+                print_commitments_fingerprint(commitments_fingerprint);
+                println!("Finished");
+            }
+            CommandOutput::Export {
+                format,
+                threshold,
+                files,
+                ..
+            } => {
+                for file in files {
+                    println!("{} share: {}", format, file.display());
+                }
+                println!("Any {} of the shares restore the secret", threshold);
+                println!("Finished");
+            }
+            CommandOutput::Verify(verify_report) => verify_report.print_text(),
+            CommandOutput::Check(check_report) => check_report.print_text(),
+            CommandOutput::Inspect(share_info) => share_info.print_text(),
+            CommandOutput::Audit(audit_report) => audit_report.print_text(),
+            CommandOutput::Holders(holders_report) => holders_report.print_text(),
+            CommandOutput::ConfigInit { path } => {
+                println!("Config has been written to: {}", path.display());
+            }
+            CommandOutput::ConfigShow { source, config } => {
+                println!("# Config source: {}", source);
+                print!("{}", config.to_yaml());
+            }
+        }
+    }
+}
+
+fn recipient_exit_code(recipient_error: &RecipientError) -> ExitCode {
+    match recipient_error {
+        RecipientError::DecryptionError { .. } => ExitCode::CorruptShare,
+        RecipientError::ReadIdentityError { .. } | RecipientError::ReadError { .. } => ExitCode::Io,
+        RecipientError::InvalidPublicKey { .. }
+        | RecipientError::InvalidIdentity { .. }
+        | RecipientError::NoIdentities { .. } => ExitCode::Failure,
+    }
+}
+
+fn interop_exit_code(interop_error: &InteropError) -> ExitCode {
+    match interop_error {
+        InteropError::Slip39(slip39_error) => match slip39_error {
+            Slip39Error::UnknownWord { .. }
+            | Slip39Error::InvalidChecksum { .. }
+            | Slip39Error::InvalidLength { .. }
+            | Slip39Error::InvalidPadding { .. }
+            | Slip39Error::InvalidDigest => ExitCode::CorruptShare,
+            Slip39Error::MixedShares => ExitCode::MismatchedShares,
+            Slip39Error::NotEnoughShares { .. } | Slip39Error::NotEnoughGroups { .. } => {
+                ExitCode::NotEnoughShares
+            }
+            Slip39Error::SecretLength { .. }
+            | Slip39Error::InvalidThreshold { .. }
+            | Slip39Error::InvalidPassphrase => ExitCode::Failure,
+        },
+        InteropError::Ssss(ssss_error) => match ssss_error {
+            SsssError::InvalidShare { .. } => ExitCode::CorruptShare,
+            SsssError::MixedShares => ExitCode::MismatchedShares,
+            SsssError::NotEnoughShares { .. } => ExitCode::NotEnoughShares,
+            SsssError::DuplicateShare { .. }
+            | SsssError::SecretLength { .. }
+            | SsssError::InvalidThreshold { .. } => ExitCode::Failure,
+        },
+        InteropError::NoShares { .. } => ExitCode::NotEnoughShares,
+        InteropError::MissingThreshold | InteropError::HybridSecret => ExitCode::Failure,
+        InteropError::ReadError { .. } | InteropError::WriteError { .. } => ExitCode::Io,
+    }
+}
+
+fn print_commitments_fingerprint(commitments_fingerprint: &Option<String>) {
+    if let Some(commitments_fingerprint) = commitments_fingerprint {
+        println!(
+            "Commitments fingerprint: {} (holders check their shares against it with `check --fingerprint`)",
+            commitments_fingerprint
+        );
+    }
+}
+
+fn print_written_shares(shares: &[WrittenShare]) {
+    for share in shares {
+        if let (Some(recipient), Some(age_file)) = (&share.recipient, &share.age_file) {
+            println!("Share of {}: {}", recipient, age_file.display());
+            continue;
+        }
+
+        let mut files = vec![];
+        if let Some(json_file) = &share.json_file {
+            files.push(json_file.display().to_string());
+        }
+        if let Some(qr_file) = &share.qr_file {
+            files.push(qr_file.display().to_string());
+        }
+        for qr_part in &share.qr_parts {
+            files.push(qr_part.display().to_string());
+        }
+        if let Some(sheet_file) = &share.sheet_file {
+            files.push(sheet_file.display().to_string());
+        }
+
+        println!("Share: {}", files.join(", "));
+    }
+}
+
+fn print_batch(secrets: &[SecretReport], holders: &[HolderBundle], summary_file: &Path) {
+    for secret in secrets {
+        let title = match &secret.entry {
+            Some(entry) if entry.title != secret.name => format!(" ({})", entry.title),
+            _ => String::new(),
+        };
+        let holders = if secret.holders.is_empty() {
+            format!("{} shares", secret.number_of_shares)
+        } else {
+            secret.holders.join(", ")
+        };
+        println!(
+            "Secret {}{}: any {} of {} restore it, set {}",
+            secret.name, title, secret.threshold, holders, secret.set_id
+        );
+    }
+    for bundle in holders {
+        println!(
+            "Shares of {}: {} ({})",
+            bundle.holder,
+            bundle.dir.display(),
+            bundle.secrets.join(", ")
+        );
+    }
+    println!("Summary: {}", summary_file.display());
+}
+
+/// Numbered words, a few per row, easier to copy by hand than a single long line
+fn print_numbered_words(words: &str) {
+    let words: Vec<&str> = words.split_whitespace().collect();
+    for (row, row_words) in words.chunks(WORDS_PER_ROW).enumerate() {
+        let numbered: Vec<String> = row_words
+            .iter()
+            .enumerate()
+            .map(|(column, word)| format!("{:>3}. {:<8}", row * WORDS_PER_ROW + column + 1, word))
+            .collect();
+        println!("{}", numbered.join(" ").trim_end());
+    }
+}
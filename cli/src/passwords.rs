@@ -0,0 +1,850 @@
+//! Password manager exports (`import-passwords`): KeePass XML, Bitwarden json and the password csv of the browsers.
+//!
+//! Every picked entry is split on its own, its password is the secret. The title and the url of the entry
+//! are kept in the shares as non-secret metadata, the user name and the notes are left out.
+//! KeePass entries are picked by tag or group, Bitwarden entries by folder, browser exports have neither.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::ArgEnum;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+
+use crate::batch::{self, CsvRow};
+
+/// A picked entry is named by its title, the title may not be a valid directory name
+const UNTITLED: &str = "untitled";
+
+#[derive(Debug, Clone, Copy, ArgEnum, Eq, PartialEq, Serialize)]
+#[clap(rename_all = "kebab_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// KeePass 2 XML export (File > Export > KeePass XML (2.x))
+    Keepass,
+    /// Unencrypted Bitwarden json export
+    Bitwarden,
+    /// Password csv of Chrome, Edge, Firefox or Safari
+    Browser,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Keepass => write!(f, "KeePass XML"),
+            ExportFormat::Bitwarden => write!(f, "Bitwarden json"),
+            ExportFormat::Browser => write!(f, "browser csv"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("Error reading the export: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+    #[error("Invalid KeePass XML export: {path}")]
+    InvalidXml {
+        path: PathBuf,
+        source: roxmltree::Error,
+    },
+    #[error("Not a KeePass XML export: {path}")]
+    NotKeepass { path: PathBuf },
+    #[error("The KeePass export has protected values: {path}. Export the database with File > Export > KeePass XML (2.x)")]
+    ProtectedValue { path: PathBuf },
+    #[error("Invalid Bitwarden json export: {path}")]
+    InvalidJson {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("The Bitwarden export is encrypted: {path}. Export the vault as unencrypted json")]
+    EncryptedExport { path: PathBuf },
+    #[error("Invalid browser csv export: {path}, line {line}: {reason}")]
+    InvalidCsv {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+    #[error("{format} exports have no tags, only KeePass entries are picked by --tag")]
+    NoTags { format: ExportFormat },
+    #[error("{format} exports have no folders")]
+    NoFolders { format: ExportFormat },
+    #[error("No entry with a password is picked from: {path}")]
+    NothingPicked { path: PathBuf },
+}
+
+/// An entry of the export, the password is the secret
+#[derive(Debug)]
+pub struct PasswordEntry {
+    pub title: String,
+    pub url: Option<String>,
+    /// The KeePass group or the Bitwarden folder: `Infra/Servers`, the root group is left out
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+    pub password: String,
+}
+
+/// Entries picked from an export
+#[derive(Debug)]
+pub struct Import {
+    /// Entries with a password, in the order of the export
+    pub entries: Vec<PasswordEntry>,
+    /// Titles of the picked entries without a password, left out
+    pub without_password: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<BitwardenFolder>,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitwardenFolder {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenItem {
+    name: String,
+    folder_id: Option<String>,
+    /// Missing in cards, identities and notes
+    login: Option<BitwardenLogin>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitwardenLogin {
+    password: Option<String>,
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+/// Reads the export and picks the entries with any of the `tags` (KeePass only)
+/// in any of the `folders` or their subfolders. Without tags and folders every entry is picked
+pub fn import(
+    path: &Path,
+    format: ExportFormat,
+    tags: &[String],
+    folders: &[String],
+) -> Result<Import, ImportError> {
+    if !tags.is_empty() && format != ExportFormat::Keepass {
+        return Err(ImportError::NoTags { format });
+    }
+    if !folders.is_empty() && format == ExportFormat::Browser {
+        return Err(ImportError::NoFolders { format });
+    }
+
+    let content = fs::read_to_string(path).map_err(|source| ImportError::ReadError {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let entries = match format {
+        ExportFormat::Keepass => keepass_entries(path, &content)?,
+        ExportFormat::Bitwarden => bitwarden_entries(path, &content)?,
+        ExportFormat::Browser => browser_entries(path, &content)?,
+    };
+
+    let (entries, without_password): (Vec<PasswordEntry>, Vec<PasswordEntry>) = entries
+        .into_iter()
+        .filter(|entry| has_tag(entry, tags) && in_folder(entry, folders))
+        .partition(|entry| !entry.password.is_empty());
+
+    if entries.is_empty() {
+        return Err(ImportError::NothingPicked {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(Import {
+        entries,
+        without_password: without_password
+            .into_iter()
+            .map(|entry| entry.title)
+            .collect(),
+    })
+}
+
+/// Names of the secrets, from the titles of the entries: they name directories, so they have to be valid
+/// names of `split --manifest` and unique. `GitHub (work)` becomes `GitHub _work_`, the second `GitHub` is `GitHub-2`
+pub fn secret_names(entries: &[PasswordEntry]) -> Vec<String> {
+    let mut names = HashSet::new();
+    entries
+        .iter()
+        .map(|entry| {
+            let base: String = entry
+                .title
+                .trim()
+                .chars()
+                .map(|c| {
+                    if c.is_alphanumeric() || matches!(c, ' ' | '.' | '-' | '_') {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let base = base.trim_start_matches(|c: char| !c.is_alphanumeric());
+            let base = if base.is_empty() { UNTITLED } else { base };
+
+            let mut name = base.to_string();
+            let mut number = 2;
+            while !names.insert(name.to_lowercase()) {
+                name = format!("{}-{}", base, number);
+                number += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+fn has_tag(entry: &PasswordEntry, tags: &[String]) -> bool {
+    tags.is_empty()
+        || tags.iter().any(|tag| {
+            entry
+                .tags
+                .iter()
+                .any(|entry_tag| entry_tag.eq_ignore_ascii_case(tag))
+        })
+}
+
+fn in_folder(entry: &PasswordEntry, folders: &[String]) -> bool {
+    if folders.is_empty() {
+        return true;
+    }
+
+    let entry_folder = match &entry.folder {
+        Some(entry_folder) => entry_folder,
+        None => return false,
+    };
+    folders.iter().any(|folder| {
+        let folder = folder.trim_matches('/');
+        entry_folder == folder
+            || matches!(entry_folder.strip_prefix(folder), Some(rest) if rest.starts_with('/'))
+    })
+}
+
+fn keepass_entries(path: &Path, content: &str) -> Result<Vec<PasswordEntry>, ImportError> {
+    let document = Document::parse(content).map_err(|source| ImportError::InvalidXml {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let keepass_file = document.root_element();
+    if !keepass_file.has_tag_name("KeePassFile") {
+        return Err(ImportError::NotKeepass {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let recycle_bin =
+        child(keepass_file, "Meta").and_then(|meta| child_text(meta, "RecycleBinUUID"));
+    let mut entries = vec![];
+    if let Some(root) = child(keepass_file, "Root") {
+        // the top group is the database itself, the folders start below it
+        for group in children(root, "Group") {
+            keepass_group(path, group, None, recycle_bin, &mut entries)?;
+        }
+    }
+
+    Ok(entries)
+}
+
+fn keepass_group(
+    path: &Path,
+    group: Node,
+    folder: Option<&str>,
+    recycle_bin: Option<&str>,
+    entries: &mut Vec<PasswordEntry>,
+) -> Result<(), ImportError> {
+    if recycle_bin.is_some() && child_text(group, "UUID") == recycle_bin {
+        return Ok(());
+    }
+
+    for entry in children(group, "Entry") {
+        entries.push(keepass_entry(path, entry, folder)?);
+    }
+
+    for subgroup in children(group, "Group") {
+        let name = child_text(subgroup, "Name").unwrap_or_default();
+        let subfolder = match folder {
+            Some(folder) => format!("{}/{}", folder, name),
+            None => name.to_string(),
+        };
+        keepass_group(path, subgroup, Some(&subfolder), recycle_bin, entries)?;
+    }
+
+    Ok(())
+}
+
+/// The history of the entry is a child of the entry, it is never picked
+fn keepass_entry(
+    path: &Path,
+    entry: Node,
+    folder: Option<&str>,
+) -> Result<PasswordEntry, ImportError> {
+    let mut fields = HashMap::new();
+    for field in children(entry, "String") {
+        let key = child_text(field, "Key").unwrap_or_default();
+        let value = match child(field, "Value") {
+            Some(value) if value.attribute("Protected") == Some("True") => {
+                return Err(ImportError::ProtectedValue {
+                    path: path.to_path_buf(),
+                })
+            }
+            Some(value) => value.text().unwrap_or_default(),
+            None => "",
+        };
+        fields.insert(key, value);
+    }
+
+    let tags = child_text(entry, "Tags")
+        .unwrap_or_default()
+        .split([';', ','])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect();
+    let url = fields.get("URL").copied().unwrap_or_default();
+
+    Ok(PasswordEntry {
+        title: title(fields.get("Title").copied().unwrap_or_default(), url),
+        url: non_empty(url),
+        folder: folder.map(String::from),
+        tags,
+        password: fields
+            .get("Password")
+            .copied()
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+}
+
+fn bitwarden_entries(path: &Path, content: &str) -> Result<Vec<PasswordEntry>, ImportError> {
+    let export: BitwardenExport =
+        serde_json::from_str(content).map_err(|source| ImportError::InvalidJson {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    if export.encrypted {
+        return Err(ImportError::EncryptedExport {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let folders: HashMap<String, String> = export
+        .folders
+        .into_iter()
+        .map(|folder| (folder.id, folder.name))
+        .collect();
+
+    Ok(export
+        .items
+        .into_iter()
+        .map(|item| {
+            let (password, url) = match item.login {
+                Some(login) => (
+                    login.password.unwrap_or_default(),
+                    login
+                        .uris
+                        .unwrap_or_default()
+                        .into_iter()
+                        .find_map(|uri| uri.uri)
+                        .unwrap_or_default(),
+                ),
+                None => (String::new(), String::new()),
+            };
+
+            PasswordEntry {
+                title: title(&item.name, &url),
+                url: non_empty(&url),
+                // nested Bitwarden folders are named `Parent/Child`
+                folder: item
+                    .folder_id
+                    .and_then(|folder_id| folders.get(&folder_id).cloned()),
+                tags: vec![],
+                password,
+            }
+        })
+        .collect())
+}
+
+/// The columns are found by name: `name` (Chrome, Edge) or `title` (Safari), `url` and `password`.
+/// Firefox has no title column, its entries are named by the host of the url
+fn browser_entries(path: &Path, content: &str) -> Result<Vec<PasswordEntry>, ImportError> {
+    let invalid = |line, reason: &str| ImportError::InvalidCsv {
+        path: path.to_path_buf(),
+        line,
+        reason: reason.to_string(),
+    };
+
+    let mut rows: Vec<CsvRow> =
+        batch::parse_csv(content).map_err(|(line, reason)| invalid(line, &reason))?;
+    if rows.is_empty() {
+        return Ok(vec![]);
+    }
+    let (header_line, header) = rows.remove(0);
+
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|column| names.contains(&column.trim().to_lowercase().as_str()))
+    };
+    let title_column = column(&["name", "title"]);
+    let url_column = column(&["url"]);
+    let password_column =
+        column(&["password"]).ok_or_else(|| invalid(header_line, "no 'password' column"))?;
+
+    rows.into_iter()
+        .map(|(line, cells)| {
+            if cells.len() != header.len() {
+                return Err(invalid(
+                    line,
+                    "the row has another number of cells than the header",
+                ));
+            }
+            let cell = |column: Option<usize>| column.map_or("", |column| cells[column].trim());
+
+            Ok(PasswordEntry {
+                title: title(cell(title_column), cell(url_column)),
+                url: non_empty(cell(url_column)),
+                folder: None,
+                tags: vec![],
+                // the password is taken as is, spaces included
+                password: cells[password_column].clone(),
+            })
+        })
+        .collect()
+}
+
+/// The title, or the host of the url for the entries without one
+fn title(title: &str, url: &str) -> String {
+    let title = title.trim();
+    if !title.is_empty() {
+        return title.to_string();
+    }
+
+    let host = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default()
+        .rsplit('@')
+        .next()
+        .unwrap_or_default();
+    if host.is_empty() {
+        UNTITLED.to_string()
+    } else {
+        host.to_string()
+    }
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEEPASS_XML: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<KeePassFile>
+    <Meta>
+        <Generator>KeePass</Generator>
+        <RecycleBinUUID>cmVjeWNsZWJpbg==</RecycleBinUUID>
+    </Meta>
+    <Root>
+        <Group>
+            <UUID>ZGF0YWJhc2U=</UUID>
+            <Name>Database</Name>
+            <Entry>
+                <String><Key>Title</Key><Value>Email</Value></String>
+                <String><Key>UserName</Key><Value>alice</Value></String>
+                <String><Key>Password</Key><Value>mail password</Value></String>
+                <String><Key>URL</Key><Value>https://mail.example.com</Value></String>
+                <Tags>personal</Tags>
+            </Entry>
+            <Group>
+                <UUID>aW5mcmE=</UUID>
+                <Name>Infra</Name>
+                <Entry>
+                    <String><Key>Title</Key><Value></Value></String>
+                    <String><Key>Password</Key><Value> spaced </Value></String>
+                    <String><Key>URL</Key><Value>https://admin@router.example.com:8443/login</Value></String>
+                    <Tags>backup; shared,network</Tags>
+                </Entry>
+                <Group>
+                    <UUID>c2VydmVycw==</UUID>
+                    <Name>Servers</Name>
+                    <Entry>
+                        <String><Key>Title</Key><Value>db root</Value></String>
+                        <String><Key>Password</Key><Value>new root password</Value></String>
+                        <Tags>backup</Tags>
+                        <History>
+                            <Entry>
+                                <String><Key>Title</Key><Value>db root</Value></String>
+                                <String><Key>Password</Key><Value>old root password</Value></String>
+                            </Entry>
+                        </History>
+                    </Entry>
+                    <Entry>
+                        <String><Key>Title</Key><Value>ssh key note</Value></String>
+                        <String><Key>Password</Key><Value></Value></String>
+                    </Entry>
+                </Group>
+            </Group>
+            <Group>
+                <UUID>cmVjeWNsZWJpbg==</UUID>
+                <Name>Recycle Bin</Name>
+                <Entry>
+                    <String><Key>Title</Key><Value>deleted</Value></String>
+                    <String><Key>Password</Key><Value>deleted password</Value></String>
+                </Entry>
+            </Group>
+        </Group>
+    </Root>
+</KeePassFile>
+"#;
+
+    const BITWARDEN_JSON: &str = r#"{
+  "encrypted": false,
+  "folders": [
+    { "id": "f1", "name": "Infra" },
+    { "id": "f2", "name": "Infra/Servers" }
+  ],
+  "items": [
+    {
+      "id": "i1",
+      "folderId": "f2",
+      "type": 1,
+      "name": "db root",
+      "login": {
+        "username": "root",
+        "password": "root password",
+        "uris": [{ "match": null, "uri": null }, { "match": null, "uri": "https://db.example.com" }]
+      }
+    },
+    {
+      "id": "i2",
+      "folderId": null,
+      "type": 1,
+      "name": "",
+      "login": { "username": "bob", "password": "forum password", "uris": [{ "uri": "https://forum.example.org/login" }] }
+    },
+    {
+      "id": "i3",
+      "folderId": "f1",
+      "type": 2,
+      "name": "recovery codes",
+      "notes": "not a login",
+      "secureNote": { "type": 0 }
+    },
+    {
+      "id": "i4",
+      "folderId": "f1",
+      "type": 1,
+      "name": "router",
+      "login": { "username": "admin", "password": null }
+    }
+  ]
+}
+"#;
+
+    fn import_fixture(
+        file_name: &str,
+        content: &str,
+        format: ExportFormat,
+        tags: &[&str],
+        folders: &[&str],
+    ) -> Result<Import, ImportError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(file_name);
+        fs::write(&path, content).unwrap();
+
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        let folders: Vec<String> = folders.iter().map(|folder| folder.to_string()).collect();
+        import(&path, format, &tags, &folders)
+    }
+
+    #[test]
+    fn keepass_nested_groups() {
+        let import =
+            import_fixture("export.xml", KEEPASS_XML, ExportFormat::Keepass, &[], &[]).unwrap();
+
+        let entries = &import.entries;
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].title, "Email");
+        assert_eq!(entries[0].url.as_deref(), Some("https://mail.example.com"));
+        assert_eq!(entries[0].folder, None);
+        assert_eq!(entries[0].tags, ["personal"]);
+        assert_eq!(entries[0].password, "mail password");
+
+        assert_eq!(entries[1].title, "router.example.com:8443");
+        assert_eq!(entries[1].folder.as_deref(), Some("Infra"));
+        assert_eq!(entries[1].tags, ["backup", "shared", "network"]);
+        assert_eq!(entries[1].password, " spaced ");
+
+        // the history is left out, the recycle bin too
+        assert_eq!(entries[2].title, "db root");
+        assert_eq!(entries[2].url, None);
+        assert_eq!(entries[2].folder.as_deref(), Some("Infra/Servers"));
+        assert_eq!(entries[2].password, "new root password");
+
+        assert_eq!(import.without_password, ["ssh key note"]);
+    }
+
+    #[test]
+    fn keepass_tags_and_folders() {
+        let titles = |tags: &[&str], folders: &[&str]| -> Vec<String> {
+            import_fixture(
+                "export.xml",
+                KEEPASS_XML,
+                ExportFormat::Keepass,
+                tags,
+                folders,
+            )
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.title)
+            .collect()
+        };
+
+        assert_eq!(
+            titles(&["BACKUP"], &[]),
+            ["router.example.com:8443", "db root"]
+        );
+        assert_eq!(
+            titles(&[], &["Infra/"]),
+            ["router.example.com:8443", "db root"]
+        );
+        assert_eq!(titles(&[], &["Infra/Servers"]), ["db root"]);
+
+        let err = import_fixture(
+            "export.xml",
+            KEEPASS_XML,
+            ExportFormat::Keepass,
+            &["personal"],
+            &["Infra"],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ImportError::NothingPicked { .. }));
+    }
+
+    #[test]
+    fn keepass_protected_values() {
+        let protected = KEEPASS_XML.replace(
+            "<Value>mail password</Value>",
+            "<Value Protected=\"True\">bWFpbA==</Value>",
+        );
+        let err =
+            import_fixture("export.xml", &protected, ExportFormat::Keepass, &[], &[]).unwrap_err();
+        assert!(matches!(err, ImportError::ProtectedValue { .. }));
+    }
+
+    #[test]
+    fn bitwarden_items() {
+        let import = import_fixture(
+            "export.json",
+            BITWARDEN_JSON,
+            ExportFormat::Bitwarden,
+            &[],
+            &[],
+        )
+        .unwrap();
+
+        let entries = &import.entries;
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].title, "db root");
+        assert_eq!(entries[0].url.as_deref(), Some("https://db.example.com"));
+        assert_eq!(entries[0].folder.as_deref(), Some("Infra/Servers"));
+        assert!(entries[0].tags.is_empty());
+        assert_eq!(entries[0].password, "root password");
+
+        assert_eq!(entries[1].title, "forum.example.org");
+        assert_eq!(entries[1].folder, None);
+        assert_eq!(entries[1].password, "forum password");
+
+        // the secure note has no login, the router login has no password
+        assert_eq!(import.without_password, ["recovery codes", "router"]);
+
+        let import = import_fixture(
+            "export.json",
+            BITWARDEN_JSON,
+            ExportFormat::Bitwarden,
+            &[],
+            &["Infra"],
+        )
+        .unwrap();
+        assert_eq!(import.entries.len(), 1);
+        assert_eq!(import.entries[0].title, "db root");
+        assert_eq!(import.without_password, ["recovery codes", "router"]);
+    }
+
+    #[test]
+    fn bitwarden_encrypted_export() {
+        let err = import_fixture(
+            "export.json",
+            r#"{"encrypted": true, "passwordProtected": true, "data": "2.abc"}"#,
+            ExportFormat::Bitwarden,
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ImportError::EncryptedExport { .. }));
+
+        let err = import_fixture(
+            "export.json",
+            BITWARDEN_JSON,
+            ExportFormat::Bitwarden,
+            &["backup"],
+            &[],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ImportError::NoTags {
+                format: ExportFormat::Bitwarden
+            }
+        ));
+    }
+
+    #[test]
+    fn chrome_csv() {
+        let import = import_fixture(
+            "Chrome Passwords.csv",
+            "name,url,username,password,note\r\n\
+             example.com,https://example.com/,alice,\" pass, \"\"quoted\"\" \",\r\n\
+             \"Bank, main\",https://bank.example.com/,alice,bank password,\"two\nlines\"\r\n\
+             ,https://empty.example.com/,bob,,\r\n",
+            ExportFormat::Browser,
+            &[],
+            &[],
+        )
+        .unwrap();
+
+        let entries = &import.entries;
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].title, "example.com");
+        assert_eq!(entries[0].url.as_deref(), Some("https://example.com/"));
+        assert_eq!(entries[0].password, " pass, \"quoted\" ");
+
+        assert_eq!(entries[1].title, "Bank, main");
+        assert_eq!(entries[1].folder, None);
+        assert!(entries[1].tags.is_empty());
+        assert_eq!(entries[1].password, "bank password");
+
+        assert_eq!(import.without_password, ["empty.example.com"]);
+    }
+
+    #[test]
+    fn firefox_csv() {
+        let import = import_fixture(
+            "logins.csv",
+            "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\n\
+             \"https://accounts.example.net\",\"alice\",\"firefox password\",,\"https://accounts.example.net\",\"{0a}\",\"1\",\"2\",\"3\"\n",
+            ExportFormat::Browser,
+            &[],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(import.entries.len(), 1);
+        assert_eq!(import.entries[0].title, "accounts.example.net");
+        assert_eq!(
+            import.entries[0].url.as_deref(),
+            Some("https://accounts.example.net")
+        );
+        assert_eq!(import.entries[0].password, "firefox password");
+        assert!(import.without_password.is_empty());
+    }
+
+    #[test]
+    fn invalid_browser_csv() {
+        let err = import_fixture(
+            "passwords.csv",
+            "name,url,username\nexample.com,https://example.com/,alice\n",
+            ExportFormat::Browser,
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ImportError::InvalidCsv { line: 1, .. }));
+
+        let err = import_fixture(
+            "passwords.csv",
+            "name,url,password\nexample.com,https://example.com/\n",
+            ExportFormat::Browser,
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ImportError::InvalidCsv { line: 2, .. }));
+
+        let err = import_fixture(
+            "passwords.csv",
+            "name,url,password\n",
+            ExportFormat::Browser,
+            &[],
+            &["Infra"],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ImportError::NoFolders { .. }));
+    }
+
+    #[test]
+    fn unique_secret_names() {
+        let entries: Vec<PasswordEntry> = ["GitHub (work)", "GitHub", "github", "  ", "../etc"]
+            .iter()
+            .map(|title| PasswordEntry {
+                title: title.to_string(),
+                url: None,
+                folder: None,
+                tags: vec![],
+                password: String::from("password"),
+            })
+            .collect();
+
+        assert_eq!(
+            secret_names(&entries),
+            ["GitHub _work_", "GitHub", "github-2", "untitled", "etc"]
+        );
+    }
+}
@@ -0,0 +1,1513 @@
+//! Just enough of a pdf reader to find the qr codes of scanned and printed shares: every page becomes
+//! the greyscale images placed on it (scans and photos) plus one image of its filled rectangles
+//! (the qr codes drawn by `split --print-sheet pdf`). Text, curves and fonts are not rendered.
+//!
+//! The objects are found by scanning the file for `obj` keywords instead of following the
+//! cross-reference table, which also reads files with a broken or missing table.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use image::{GrayImage, ImageFormat, Luma};
+
+/// Pixels per point of the rendered rectangles, about 216 dpi
+const RENDER_SCALE: f32 = 3.0;
+/// The longest side of a rendered page, in pixels
+const MAX_RENDER_SIZE: f32 = 6000.0;
+/// Deeper page trees, forms in forms and reference chains are treated as broken
+const MAX_DEPTH: usize = 32;
+/// US Letter, the page size when a page has no media box
+const DEFAULT_MEDIA_BOX: [f32; 4] = [0.0, 0.0, 612.0, 792.0];
+/// Decoded streams bigger than that are taken for decompression bombs,
+/// a 600 dpi color scan of an A4 page takes about 100 MiB
+const MAX_STREAM_SIZE: usize = 256 * 1024 * 1024;
+/// Pixels of an image placed on a page, a 600 dpi scan of an A3 page has about 70 million
+const MAX_IMAGE_PIXELS: f32 = 256.0 * 1024.0 * 1024.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PdfError {
+    #[error("Not a pdf file")]
+    NotAPdf,
+    #[error("No pages found in the pdf")]
+    NoPages,
+    #[error("A stream of the pdf decodes to more than {MAX_STREAM_SIZE} bytes")]
+    StreamTooBig,
+    #[error("Invalid stream length in the pdf: {length}")]
+    InvalidStreamLength { length: f32 },
+    #[error("An image of the pdf is too big: {width} x {height} pixels")]
+    ImageTooBig { width: f32, height: f32 },
+}
+
+/// The images a page is made of, to look for qr codes in
+pub struct Page {
+    pub images: Vec<GrayImage>,
+}
+
+/// Pages of the pdf in reading order
+pub fn pages(data: &[u8]) -> Result<Vec<Page>, PdfError> {
+    if !data.starts_with(b"%PDF-") {
+        return Err(PdfError::NotAPdf);
+    }
+
+    let document = Document::parse(data)?;
+    let catalog = document.catalog().ok_or(PdfError::NoPages)?;
+
+    let mut page_dicts = vec![];
+    let inherited = Inherited {
+        resources: None,
+        media_box: DEFAULT_MEDIA_BOX,
+    };
+    document.collect_pages(catalog.get("Pages"), inherited, 0, &mut page_dicts);
+    if page_dicts.is_empty() {
+        return Err(PdfError::NoPages);
+    }
+
+    page_dicts
+        .into_iter()
+        .map(|(page, inherited)| document.render_page(page, &inherited))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+enum Object {
+    Null,
+    Bool(bool),
+    Number(f32),
+    Name(String),
+    String(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Ref(u32),
+    Stream(Dict, Vec<u8>),
+}
+
+type Dict = HashMap<String, Object>;
+
+impl Object {
+    fn as_number(&self) -> Option<f32> {
+        match self {
+            Object::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Tokenizer of pdf objects, for the file itself, object streams and content streams alike
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    is_whitespace(byte) || b"()<>[]{}/%".contains(&byte)
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Parser { data, pos }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos.min(self.data.len())..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if is_whitespace(byte) {
+                self.pos += 1;
+            } else if byte == b'%' {
+                while !matches!(self.peek(), None | Some(b'\r') | Some(b'\n')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// A run of regular characters: a keyword, an operator or a number
+    fn keyword(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek().map(|byte| !is_delimiter(byte)).unwrap_or(false) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    /// The next object, or None (without moving) if the next token is an operator
+    fn object(&mut self, depth: usize) -> Option<Object> {
+        self.skip_whitespace();
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        match self.peek()? {
+            b'<' if self.rest().starts_with(b"<<") => {
+                self.pos += 2;
+                let mut dict = Dict::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.rest().starts_with(b">>") || self.at_end() {
+                        self.pos += 2;
+                        return Some(Object::Dict(dict));
+                    }
+                    let key = match self.object(depth + 1) {
+                        Some(Object::Name(key)) => key,
+                        // a broken dictionary ends here
+                        _ => return Some(Object::Dict(dict)),
+                    };
+                    let value = self.object(depth + 1).unwrap_or(Object::Null);
+                    dict.insert(key, value);
+                }
+            }
+            b'<' => {
+                self.pos += 1;
+                let mut digits = vec![];
+                while let Some(byte) = self.peek() {
+                    self.pos += 1;
+                    if byte == b'>' {
+                        break;
+                    }
+                    if byte.is_ascii_hexdigit() {
+                        digits.push(byte);
+                    }
+                }
+                if digits.len() % 2 == 1 {
+                    digits.push(b'0');
+                }
+                let bytes = digits
+                    .chunks(2)
+                    .map(|pair| hex_value(pair[0]) << 4 | hex_value(pair[1]))
+                    .collect();
+                Some(Object::String(bytes))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut array = vec![];
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None => return Some(Object::Array(array)),
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Some(Object::Array(array));
+                        }
+                        _ => match self.object(depth + 1) {
+                            Some(object) => array.push(object),
+                            // operators don't belong into arrays, skip them
+                            None => {
+                                if self.keyword().is_empty() {
+                                    self.pos += 1;
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+            b'(' => Some(Object::String(self.literal_string())),
+            b'/' => {
+                self.pos += 1;
+                Some(Object::Name(self.name()))
+            }
+            b'+' | b'-' | b'.' | b'0'..=b'9' => {
+                let start = self.pos;
+                let number = parse_number(self.keyword());
+                if let Some(reference) = self.reference(start) {
+                    return Some(reference);
+                }
+                Some(Object::Number(number.unwrap_or(0.0)))
+            }
+            _ => {
+                let start = self.pos;
+                let object = match self.keyword() {
+                    b"true" => Object::Bool(true),
+                    b"false" => Object::Bool(false),
+                    b"null" => Object::Null,
+                    _ => {
+                        self.pos = start;
+                        return None;
+                    }
+                };
+                Some(object)
+            }
+        }
+    }
+
+    /// `<number> <generation> R` starting at `start`, the number is already read
+    fn reference(&mut self, start: usize) -> Option<Object> {
+        let number = std::str::from_utf8(&self.data[start..self.pos])
+            .ok()?
+            .parse::<u32>()
+            .ok()?;
+
+        let after_number = self.pos;
+        self.skip_whitespace();
+        let generation = self.keyword();
+        self.skip_whitespace();
+        let is_reference = !generation.is_empty()
+            && generation.iter().all(u8::is_ascii_digit)
+            && self.keyword() == b"R";
+
+        if is_reference {
+            Some(Object::Ref(number))
+        } else {
+            self.pos = after_number;
+            None
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let raw = self.keyword();
+        let mut name = vec![];
+        let mut i = 0;
+        while i < raw.len() {
+            if raw[i] == b'#'
+                && i + 2 < raw.len()
+                && raw[i + 1..=i + 2].iter().all(u8::is_ascii_hexdigit)
+            {
+                name.push(hex_value(raw[i + 1]) << 4 | hex_value(raw[i + 2]));
+                i += 3;
+            } else {
+                name.push(raw[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8_lossy(&name).to_string()
+    }
+
+    fn literal_string(&mut self) -> Vec<u8> {
+        // the opening parenthesis
+        self.pos += 1;
+        let mut bytes = vec![];
+        let mut nesting = 0;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            match byte {
+                b'(' => {
+                    nesting += 1;
+                    bytes.push(byte);
+                }
+                b')' if nesting == 0 => break,
+                b')' => {
+                    nesting -= 1;
+                    bytes.push(byte);
+                }
+                b'\\' => {
+                    let escaped = match self.peek() {
+                        Some(escaped) => escaped,
+                        None => break,
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'b' => bytes.push(b'\x08'),
+                        b'f' => bytes.push(b'\x0c'),
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(digit - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            bytes.push(value as u8);
+                        }
+                        // a line break after the backslash continues the string on the next line
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        _ => bytes.push(escaped),
+                    }
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        bytes
+    }
+}
+
+fn hex_value(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        b'A'..=b'F' => digit - b'A' + 10,
+        _ => 0,
+    }
+}
+
+fn parse_number(token: &[u8]) -> Option<f32> {
+    std::str::from_utf8(token).ok()?.parse().ok()
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| from + position)
+}
+
+/// The page attributes a page inherits from the page tree nodes above it
+#[derive(Clone)]
+struct Inherited<'a> {
+    resources: Option<&'a Dict>,
+    media_box: [f32; 4],
+}
+
+struct Document {
+    objects: HashMap<u32, Object>,
+}
+
+impl Document {
+    fn parse(data: &[u8]) -> Result<Self, PdfError> {
+        let mut objects = HashMap::new();
+        let mut pos = 0;
+
+        while let Some(found) = find(data, b"obj", pos) {
+            pos = found + 3;
+            if data
+                .get(pos)
+                .map(|&byte| !is_delimiter(byte))
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            let number = match object_number(data, found) {
+                Some(number) => number,
+                None => continue,
+            };
+
+            let mut parser = Parser::new(data, pos);
+            let object = match parser.object(0) {
+                Some(object) => object,
+                None => continue,
+            };
+            parser.skip_whitespace();
+
+            let object = match object {
+                Object::Dict(dict) if parser.rest().starts_with(b"stream") => {
+                    let (stream_data, end) = stream_data(data, parser.pos + 6, &dict)?;
+                    pos = end;
+                    Object::Stream(dict, stream_data)
+                }
+                object => {
+                    pos = parser.pos;
+                    object
+                }
+            };
+            // later revisions of an object (incremental updates) replace the earlier ones
+            objects.insert(number, object);
+        }
+
+        let mut document = Document { objects };
+        document.unpack_object_streams()?;
+        Ok(document)
+    }
+
+    /// Objects compressed into object streams (pdf 1.5 and newer)
+    fn unpack_object_streams(&mut self) -> Result<(), PdfError> {
+        let mut unpacked = vec![];
+        for object in self.objects.values() {
+            let (dict, data) = match object {
+                Object::Stream(dict, data)
+                    if dict.get("Type").and_then(Object::as_name) == Some("ObjStm") =>
+                {
+                    match self.decode_stream(dict, data)? {
+                        Some(Decoded::Raw(data)) => (dict, data),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            let count = self.number(dict.get("N")).unwrap_or(0.0) as usize;
+            let first = self.number(dict.get("First")).unwrap_or(0.0) as usize;
+
+            let mut header = Parser::new(&data, 0);
+            for _ in 0..count {
+                let (number, offset) = match (header.object(0), header.object(0)) {
+                    (Some(Object::Number(number)), Some(Object::Number(offset))) => {
+                        (number, offset)
+                    }
+                    _ => break,
+                };
+                let object = first
+                    .checked_add(offset as usize)
+                    .and_then(|position| Parser::new(&data, position).object(0));
+                if let Some(object) = object {
+                    unpacked.push((number as u32, object));
+                }
+            }
+        }
+
+        for (number, object) in unpacked {
+            self.objects.entry(number).or_insert(object);
+        }
+        Ok(())
+    }
+
+    fn resolve<'a>(&'a self, object: Option<&'a Object>) -> Option<&'a Object> {
+        let mut object = object?;
+        for _ in 0..MAX_DEPTH {
+            match object {
+                Object::Ref(number) => object = self.objects.get(number)?,
+                _ => return Some(object),
+            }
+        }
+        None
+    }
+
+    fn dict<'a>(&'a self, object: Option<&'a Object>) -> Option<&'a Dict> {
+        match self.resolve(object)? {
+            Object::Dict(dict) | Object::Stream(dict, _) => Some(dict),
+            _ => None,
+        }
+    }
+
+    fn number(&self, object: Option<&Object>) -> Option<f32> {
+        self.resolve(object)?.as_number()
+    }
+
+    fn numbers(&self, object: Option<&Object>) -> Vec<f32> {
+        match self.resolve(object) {
+            Some(Object::Array(array)) => array
+                .iter()
+                .filter_map(|item| self.number(Some(item)))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn catalog(&self) -> Option<&Dict> {
+        // the highest object number is the newest catalog if there are several
+        self.objects
+            .iter()
+            .filter_map(|(number, object)| match object {
+                Object::Dict(dict)
+                    if dict.get("Type").and_then(Object::as_name) == Some("Catalog") =>
+                {
+                    Some((number, dict))
+                }
+                _ => None,
+            })
+            .max_by_key(|(number, _)| **number)
+            .map(|(_, dict)| dict)
+    }
+
+    fn collect_pages<'a>(
+        &'a self,
+        node: Option<&'a Object>,
+        mut inherited: Inherited<'a>,
+        depth: usize,
+        pages: &mut Vec<(&'a Dict, Inherited<'a>)>,
+    ) {
+        let node = match self.dict(node) {
+            Some(node) if depth <= MAX_DEPTH => node,
+            _ => return,
+        };
+
+        if let Some(resources) = self.dict(node.get("Resources")) {
+            inherited.resources = Some(resources);
+        }
+        if let [x0, y0, x1, y1] = self.numbers(node.get("MediaBox"))[..] {
+            inherited.media_box = [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)];
+        }
+
+        match self.resolve(node.get("Kids")) {
+            Some(Object::Array(kids)) => {
+                for kid in kids {
+                    self.collect_pages(Some(kid), inherited.clone(), depth + 1, pages);
+                }
+            }
+            _ => pages.push((node, inherited)),
+        }
+    }
+
+    fn render_page(&self, page: &Dict, inherited: &Inherited) -> Result<Page, PdfError> {
+        let content = match self.resolve(page.get("Contents")) {
+            Some(Object::Array(streams)) => {
+                let mut content = vec![];
+                for stream in streams {
+                    if let Some(data) = self.raw_stream(Some(stream))? {
+                        if !content.is_empty() {
+                            content.push(b'\n');
+                        }
+                        content.extend(data);
+                    }
+                }
+                content
+            }
+            contents => self.raw_stream(contents)?.unwrap_or_default(),
+        };
+
+        let mut canvas = Canvas::new(inherited.media_box);
+        let mut images = vec![];
+        self.run_content(
+            &content,
+            inherited.resources,
+            IDENTITY,
+            0,
+            &mut canvas,
+            &mut images,
+        )?;
+
+        if let Some(rendered) = canvas.into_image() {
+            images.push(rendered);
+        }
+        Ok(Page { images })
+    }
+
+    /// Fills the rectangles of the content into the canvas and collects the images it places
+    fn run_content(
+        &self,
+        content: &[u8],
+        resources: Option<&Dict>,
+        matrix: Matrix,
+        depth: usize,
+        canvas: &mut Canvas,
+        images: &mut Vec<GrayImage>,
+    ) -> Result<(), PdfError> {
+        if depth > MAX_DEPTH {
+            return Ok(());
+        }
+
+        let mut state = GraphicsState {
+            matrix,
+            fill_gray: 0,
+        };
+        let mut saved_states = vec![];
+        let mut path = vec![];
+        let mut operands = vec![];
+        let mut parser = Parser::new(content, 0);
+
+        loop {
+            parser.skip_whitespace();
+            if parser.at_end() {
+                break;
+            }
+            if let Some(operand) = parser.object(0) {
+                operands.push(operand);
+                continue;
+            }
+
+            let operator = parser.keyword();
+            if operator.is_empty() {
+                // a stray delimiter
+                parser.pos += 1;
+                operands.clear();
+                continue;
+            }
+
+            let numbers: Vec<f32> = operands.iter().filter_map(Object::as_number).collect();
+            match operator {
+                b"q" => saved_states.push(state),
+                b"Q" => state = saved_states.pop().unwrap_or(state),
+                b"cm" => {
+                    if let [a, b, c, d, e, f] = numbers[..] {
+                        state.matrix = multiply([a, b, c, d, e, f], state.matrix);
+                    }
+                }
+                b"g" => {
+                    if let [gray] = numbers[..] {
+                        state.fill_gray = to_byte(gray);
+                    }
+                }
+                b"rg" => {
+                    if let [r, g, b] = numbers[..] {
+                        state.fill_gray = to_byte(luma(r, g, b));
+                    }
+                }
+                b"k" => {
+                    if let [c, m, y, k] = numbers[..] {
+                        let (r, g, b) = cmyk_to_rgb(c, m, y, k);
+                        state.fill_gray = to_byte(luma(r, g, b));
+                    }
+                }
+                b"re" => {
+                    if let [x, y, width, height] = numbers[..] {
+                        path.push(transform_rect(state.matrix, x, y, width, height));
+                    }
+                }
+                b"f" | b"F" | b"f*" | b"B" | b"B*" | b"b" | b"b*" => {
+                    for rect in path.drain(..) {
+                        canvas.fill(rect, state.fill_gray);
+                    }
+                }
+                b"n" | b"S" | b"s" => path.clear(),
+                b"Do" => {
+                    if let Some(Object::Name(name)) = operands.last() {
+                        self.draw_xobject(name, resources, state.matrix, depth, canvas, images)?;
+                    }
+                }
+                b"BI" => {
+                    // inline images are small (logos, icons) and their binary data would confuse the parser
+                    let end = find(content, b"EI", parser.pos)
+                        .filter(|_| find(content, b"ID", parser.pos).is_some());
+                    parser.pos = end.map(|end| end + 2).unwrap_or(content.len());
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+
+        Ok(())
+    }
+
+    fn draw_xobject(
+        &self,
+        name: &str,
+        resources: Option<&Dict>,
+        matrix: Matrix,
+        depth: usize,
+        canvas: &mut Canvas,
+        images: &mut Vec<GrayImage>,
+    ) -> Result<(), PdfError> {
+        let xobject = resources
+            .and_then(|resources| self.dict(resources.get("XObject")))
+            .and_then(|xobjects| self.resolve(xobjects.get(name)));
+        let (dict, data) = match xobject {
+            Some(Object::Stream(dict, data)) => (dict, data),
+            _ => return Ok(()),
+        };
+
+        match dict.get("Subtype").and_then(Object::as_name) {
+            Some("Image") => {
+                if let Some(image) = self.decode_image(dict, data)? {
+                    images.push(image);
+                }
+            }
+            Some("Form") => {
+                let content = match self.decode_stream(dict, data)? {
+                    Some(Decoded::Raw(content)) => content,
+                    _ => return Ok(()),
+                };
+                let form_matrix = match self.numbers(dict.get("Matrix"))[..] {
+                    [a, b, c, d, e, f] => multiply([a, b, c, d, e, f], matrix),
+                    _ => matrix,
+                };
+                let form_resources = self.dict(dict.get("Resources")).or(resources);
+                self.run_content(
+                    &content,
+                    form_resources,
+                    form_matrix,
+                    depth + 1,
+                    canvas,
+                    images,
+                )?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn raw_stream(&self, object: Option<&Object>) -> Result<Option<Vec<u8>>, PdfError> {
+        match self.resolve(object) {
+            Some(Object::Stream(dict, data)) => match self.decode_stream(dict, data)? {
+                Some(Decoded::Raw(data)) => Ok(Some(data)),
+                Some(Decoded::Jpeg(_)) | None => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Undoes the filters of the stream, jpeg images are left to the image decoder.
+    /// None for the filters that are not supported
+    fn decode_stream(&self, dict: &Dict, data: &[u8]) -> Result<Option<Decoded>, PdfError> {
+        let filters = match self.resolve(dict.get("Filter")) {
+            Some(Object::Name(filter)) => vec![filter.as_str()],
+            Some(Object::Array(filters)) => filters
+                .iter()
+                .filter_map(|filter| self.resolve(Some(filter))?.as_name())
+                .collect(),
+            _ => vec![],
+        };
+        let parameters = match self.resolve(dict.get("DecodeParms")) {
+            Some(Object::Array(parameters)) => parameters
+                .iter()
+                .map(|parameters| self.dict(Some(parameters)))
+                .collect(),
+            Some(Object::Dict(parameters)) => vec![Some(parameters)],
+            _ => vec![],
+        };
+
+        let mut data = data.to_vec();
+        for (i, filter) in filters.iter().enumerate() {
+            let parameters = parameters.get(i).copied().flatten();
+            match *filter {
+                "FlateDecode" | "Fl" => {
+                    // one byte more than allowed tells a bomb from a stream of exactly the maximum size
+                    let mut inflated = vec![];
+                    let inflation = ZlibDecoder::new(&data[..])
+                        .take(MAX_STREAM_SIZE as u64 + 1)
+                        .read_to_end(&mut inflated);
+                    if inflation.is_err() {
+                        return Ok(None);
+                    }
+                    if inflated.len() > MAX_STREAM_SIZE {
+                        return Err(PdfError::StreamTooBig);
+                    }
+                    data = match self.undo_predictor(inflated, parameters)? {
+                        Some(data) => data,
+                        None => return Ok(None),
+                    };
+                }
+                "DCTDecode" | "DCT" if i == filters.len() - 1 => {
+                    return Ok(Some(Decoded::Jpeg(data)))
+                }
+                // CCITT fax, JBIG2 and JPEG 2000 scans are not supported
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(Some(Decoded::Raw(data)))
+    }
+
+    /// Png predictors of flate compressed data
+    fn undo_predictor(
+        &self,
+        data: Vec<u8>,
+        parameters: Option<&Dict>,
+    ) -> Result<Option<Vec<u8>>, PdfError> {
+        let parameter = |key: &str, default: f32| {
+            parameters
+                .and_then(|p| self.number(p.get(key)))
+                .unwrap_or(default) as usize
+        };
+
+        let predictor = parameter("Predictor", 1.0);
+        if predictor < 10 {
+            // tiff predictors are not supported
+            return Ok((predictor == 1).then_some(data));
+        }
+
+        let bits_per_pixel = parameter("Colors", 1.0)
+            .checked_mul(parameter("BitsPerComponent", 8.0))
+            .ok_or(PdfError::StreamTooBig)?;
+        let pixel_size = (bits_per_pixel / 8).max(1);
+        let row_size = match bits_per_pixel.checked_mul(parameter("Columns", 1.0)) {
+            Some(row_bits) if row_bits.div_ceil(8) <= MAX_STREAM_SIZE => row_bits.div_ceil(8),
+            _ => return Err(PdfError::StreamTooBig),
+        };
+
+        let mut previous_row = vec![0u8; row_size];
+        let mut output = Vec::with_capacity(data.len());
+        for encoded_row in data.chunks(row_size + 1) {
+            let (filter, encoded) = match encoded_row.split_first() {
+                Some(split) => split,
+                None => return Ok(None),
+            };
+            let mut row = encoded.to_vec();
+            row.resize(row_size, 0);
+            for i in 0..row_size {
+                let left = if i >= pixel_size {
+                    row[i - pixel_size]
+                } else {
+                    0
+                };
+                let up = previous_row[i];
+                let up_left = if i >= pixel_size {
+                    previous_row[i - pixel_size]
+                } else {
+                    0
+                };
+                let prediction = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+                    4 => paeth(left, up, up_left),
+                    _ => return Ok(None),
+                };
+                row[i] = row[i].wrapping_add(prediction);
+            }
+            output.extend_from_slice(&row);
+            previous_row = row;
+        }
+
+        Ok(Some(output))
+    }
+
+    fn decode_image(&self, dict: &Dict, data: &[u8]) -> Result<Option<GrayImage>, PdfError> {
+        let data = match self.decode_stream(dict, data)? {
+            Some(Decoded::Jpeg(jpeg)) => {
+                // the image decoder has its own limits
+                return Ok(
+                    image::load_from_memory_with_format(&jpeg, ImageFormat::Jpeg)
+                        .ok()
+                        .map(|image| image.to_luma8()),
+                );
+            }
+            Some(Decoded::Raw(data)) => data,
+            None => return Ok(None),
+        };
+
+        let (width, height) = match (
+            self.number(dict.get("Width")),
+            self.number(dict.get("Height")),
+        ) {
+            (Some(width), Some(height)) if width >= 1.0 && height >= 1.0 => (width, height),
+            _ => return Ok(None),
+        };
+        // before anything is allocated for the image
+        if width * height > MAX_IMAGE_PIXELS {
+            return Err(PdfError::ImageTooBig { width, height });
+        }
+        let (width, height) = (width as usize, height as usize);
+
+        let is_mask = matches!(
+            self.resolve(dict.get("ImageMask")),
+            Some(Object::Bool(true))
+        );
+        let (color_space, bits) = if is_mask {
+            (ColorSpace::Gray, 1)
+        } else {
+            let bits = self.number(dict.get("BitsPerComponent")).unwrap_or(8.0) as usize;
+            match self.color_space(dict.get("ColorSpace"), 0)? {
+                Some(color_space) => (color_space, bits),
+                None => return Ok(None),
+            }
+        };
+        if !matches!(bits, 1 | 2 | 4 | 8 | 16) {
+            return Ok(None);
+        }
+        // a decode array of [1 0] inverts single component images, e.g. scans made black on white by it
+        let inverted =
+            matches!(self.numbers(dict.get("Decode"))[..], [first, second, ..] if first > second);
+
+        let components = color_space.components();
+        let row_size = match width.checked_mul(components * bits) {
+            Some(row_bits) => row_bits.div_ceil(8),
+            None => {
+                return Err(PdfError::ImageTooBig {
+                    width: width as f32,
+                    height: height as f32,
+                })
+            }
+        };
+        match row_size.checked_mul(height) {
+            Some(image_size) if image_size <= data.len() => {}
+            // truncated image data
+            _ => return Ok(None),
+        }
+
+        let mut image = GrayImage::new(width as u32, height as u32);
+        let mut samples = vec![0u16; components];
+        for (y, row) in data.chunks(row_size).take(height).enumerate() {
+            for x in 0..width {
+                for (c, sample) in samples.iter_mut().enumerate() {
+                    *sample = read_sample(row, (x * components + c) * bits, bits);
+                }
+                let mut gray = color_space.luma(&samples, bits);
+                if inverted && components == 1 {
+                    gray = 255 - gray;
+                }
+                image.put_pixel(x as u32, y as u32, Luma([gray]));
+            }
+        }
+
+        Ok(Some(image))
+    }
+
+    fn color_space(
+        &self,
+        object: Option<&Object>,
+        depth: usize,
+    ) -> Result<Option<ColorSpace>, PdfError> {
+        if depth > MAX_DEPTH {
+            return Ok(None);
+        }
+
+        let color_space = match self.resolve(object) {
+            Some(Object::Name(name)) => match name.as_str() {
+                "DeviceGray" | "CalGray" | "G" => Some(ColorSpace::Gray),
+                "DeviceRGB" | "CalRGB" | "RGB" => Some(ColorSpace::Rgb),
+                "DeviceCMYK" | "CMYK" => Some(ColorSpace::Cmyk),
+                _ => None,
+            },
+            Some(Object::Array(array)) => match array.first().and_then(Object::as_name) {
+                Some("ICCBased") => {
+                    let components = self
+                        .dict(array.get(1))
+                        .and_then(|profile| self.number(profile.get("N")));
+                    match components.map(|components| components as usize) {
+                        Some(1) => Some(ColorSpace::Gray),
+                        Some(3) => Some(ColorSpace::Rgb),
+                        Some(4) => Some(ColorSpace::Cmyk),
+                        _ => None,
+                    }
+                }
+                Some("Indexed" | "I") => {
+                    let base = match self.color_space(array.get(1), depth + 1)? {
+                        Some(base) => base,
+                        None => return Ok(None),
+                    };
+                    let lookup = match self.resolve(array.get(3)) {
+                        Some(Object::String(lookup)) => Some(lookup.clone()),
+                        Some(lookup @ Object::Stream(..)) => self.raw_stream(Some(lookup))?,
+                        _ => None,
+                    };
+                    lookup.map(|lookup| ColorSpace::Indexed(Box::new(base), lookup))
+                }
+                Some("CalGray") => Some(ColorSpace::Gray),
+                Some("CalRGB") => Some(ColorSpace::Rgb),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        Ok(color_space)
+    }
+}
+
+/// The number in front of `obj`: `<number> <generation> obj`
+fn object_number(data: &[u8], obj_pos: usize) -> Option<u32> {
+    let mut pos = obj_pos;
+    let mut numbers = vec![];
+    for _ in 0..2 {
+        let end = pos;
+        while pos > 0 && is_whitespace(data[pos - 1]) {
+            pos -= 1;
+        }
+        if pos == end {
+            return None;
+        }
+        let digits_end = pos;
+        while pos > 0 && data[pos - 1].is_ascii_digit() {
+            pos -= 1;
+        }
+        if pos == digits_end {
+            return None;
+        }
+        numbers.push(
+            std::str::from_utf8(&data[pos..digits_end])
+                .ok()?
+                .parse::<u32>()
+                .ok()?,
+        );
+    }
+    if pos > 0 && !is_delimiter(data[pos - 1]) {
+        return None;
+    }
+    numbers.last().copied()
+}
+
+/// Data of the stream starting at `start` (right after the `stream` keyword) and the position after it
+fn stream_data(data: &[u8], start: usize, dict: &Dict) -> Result<(Vec<u8>, usize), PdfError> {
+    let start = match data.get(start..) {
+        Some([b'\r', b'\n', ..]) => start + 2,
+        Some([b'\n', ..]) | Some([b'\r', ..]) => start + 1,
+        _ => start,
+    };
+
+    // the length can be an indirect object, written after the stream: then the stream ends at `endstream`
+    let direct_length = match dict.get("Length").and_then(Object::as_number) {
+        Some(length) => {
+            let end = Some(length)
+                .filter(|length| *length >= 0.0)
+                .and_then(|length| start.checked_add(length as usize))
+                .filter(|&end| end <= data.len())
+                .ok_or(PdfError::InvalidStreamLength { length })?;
+            Some(end)
+        }
+        None => None,
+    }
+    .filter(|&end| {
+        let mut parser = Parser::new(data, end);
+        parser.skip_whitespace();
+        parser.rest().starts_with(b"endstream")
+    });
+
+    let end = direct_length.unwrap_or_else(|| {
+        let mut end = find(data, b"endstream", start).unwrap_or(data.len());
+        if data[..end].ends_with(b"\r\n") {
+            end -= 2;
+        } else if data[..end].ends_with(b"\n") || data[..end].ends_with(b"\r") {
+            end -= 1;
+        }
+        end.max(start)
+    });
+
+    Ok((data[start..end].to_vec(), end))
+}
+
+enum Decoded {
+    Raw(Vec<u8>),
+    Jpeg(Vec<u8>),
+}
+
+enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+    /// The base color space and the colors of the palette
+    Indexed(Box<ColorSpace>, Vec<u8>),
+}
+
+impl ColorSpace {
+    fn components(&self) -> usize {
+        match self {
+            ColorSpace::Gray | ColorSpace::Indexed(..) => 1,
+            ColorSpace::Rgb => 3,
+            ColorSpace::Cmyk => 4,
+        }
+    }
+
+    /// Brightness of the samples of a pixel, `bits` per sample
+    fn luma(&self, samples: &[u16], bits: usize) -> u8 {
+        let max = ((1u32 << bits) - 1) as f32;
+        let value = |i: usize| f32::from(samples[i]) / max;
+
+        match self {
+            ColorSpace::Gray => to_byte(value(0)),
+            ColorSpace::Rgb => to_byte(luma(value(0), value(1), value(2))),
+            ColorSpace::Cmyk => {
+                let (r, g, b) = cmyk_to_rgb(value(0), value(1), value(2), value(3));
+                to_byte(luma(r, g, b))
+            }
+            ColorSpace::Indexed(base, lookup) => {
+                let components = base.components();
+                let start = usize::from(samples[0]) * components;
+                match lookup.get(start..start + components) {
+                    Some(color) => {
+                        let color: Vec<u16> = color.iter().map(|&byte| u16::from(byte)).collect();
+                        base.luma(&color, 8)
+                    }
+                    // out of the palette
+                    None => 255,
+                }
+            }
+        }
+    }
+}
+
+fn read_sample(row: &[u8], bit_offset: usize, bits: usize) -> u16 {
+    let byte = row[bit_offset / 8];
+    match bits {
+        16 => u16::from_be_bytes([byte, row[bit_offset / 8 + 1]]),
+        8 => u16::from(byte),
+        _ => {
+            let shift = 8 - bits - bit_offset % 8;
+            u16::from((byte >> shift) & ((1 << bits) - 1) as u8)
+        }
+    }
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let left_distance = (estimate - i16::from(left)).abs();
+    let up_distance = (estimate - i16::from(up)).abs();
+    let up_left_distance = (estimate - i16::from(up_left)).abs();
+    if left_distance <= up_distance && left_distance <= up_left_distance {
+        left
+    } else if up_distance <= up_left_distance {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn luma(r: f32, g: f32, b: f32) -> f32 {
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+fn cmyk_to_rgb(c: f32, m: f32, y: f32, k: f32) -> (f32, f32, f32) {
+    (
+        (1.0 - c) * (1.0 - k),
+        (1.0 - m) * (1.0 - k),
+        (1.0 - y) * (1.0 - k),
+    )
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Affine transformation `[a b c d e f]` of pdf user space
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// `first` applied before `second`
+fn multiply(first: Matrix, second: Matrix) -> Matrix {
+    let [a1, b1, c1, d1, e1, f1] = first;
+    let [a2, b2, c2, d2, e2, f2] = second;
+    [
+        a1 * a2 + b1 * c2,
+        a1 * b2 + b1 * d2,
+        c1 * a2 + d1 * c2,
+        c1 * b2 + d1 * d2,
+        e1 * a2 + f1 * c2 + e2,
+        e1 * b2 + f1 * d2 + f2,
+    ]
+}
+
+/// Bounding box `[x0 y0 x1 y1]` of the transformed rectangle
+fn transform_rect(matrix: Matrix, x: f32, y: f32, width: f32, height: f32) -> [f32; 4] {
+    let [a, b, c, d, e, f] = matrix;
+    let corners = [
+        (x, y),
+        (x + width, y),
+        (x, y + height),
+        (x + width, y + height),
+    ]
+    .map(|(x, y)| (a * x + c * y + e, b * x + d * y + f));
+
+    let xs = corners.map(|(x, _)| x);
+    let ys = corners.map(|(_, y)| y);
+    [
+        xs.iter().copied().fold(f32::INFINITY, f32::min),
+        ys.iter().copied().fold(f32::INFINITY, f32::min),
+        xs.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        ys.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+    ]
+}
+
+#[derive(Clone, Copy)]
+struct GraphicsState {
+    matrix: Matrix,
+    fill_gray: u8,
+}
+
+/// White page the filled rectangles are painted on, only allocated once there is a rectangle
+struct Canvas {
+    media_box: [f32; 4],
+    scale: f32,
+    image: Option<GrayImage>,
+}
+
+impl Canvas {
+    fn new(media_box: [f32; 4]) -> Self {
+        let [x0, y0, x1, y1] = media_box;
+        let longest_side = (x1 - x0).max(y1 - y0).max(1.0);
+        Canvas {
+            media_box,
+            scale: RENDER_SCALE.min(MAX_RENDER_SIZE / longest_side),
+            image: None,
+        }
+    }
+
+    fn fill(&mut self, rect: [f32; 4], gray: u8) {
+        let [page_x0, page_y0, page_x1, page_y1] = self.media_box;
+        let scale = self.scale;
+        let image = self.image.get_or_insert_with(|| {
+            let width = ((page_x1 - page_x0) * scale).ceil().max(1.0) as u32;
+            let height = ((page_y1 - page_y0) * scale).ceil().max(1.0) as u32;
+            GrayImage::from_pixel(width, height, Luma([255]))
+        });
+
+        // pdf y goes up, image y goes down
+        let [x0, y0, x1, y1] = rect;
+        let to_column =
+            |x: f32| (((x - page_x0) * scale).round().max(0.0) as u32).min(image.width());
+        let to_row = |y: f32| (((page_y1 - y) * scale).round().max(0.0) as u32).min(image.height());
+        let (left, right) = (to_column(x0), to_column(x1));
+        let (top, bottom) = (to_row(y1), to_row(y0));
+
+        for y in top..bottom {
+            for x in left..right {
+                image.put_pixel(x, y, Luma([gray]));
+            }
+        }
+    }
+
+    fn into_image(self) -> Option<GrayImage> {
+        self.image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use image::codecs::jpeg::JpegEncoder;
+    use qrcode::QrCode;
+
+    use super::*;
+
+    /// A pdf with a cross-reference table, `objects[i]` is the object number i + 1
+    fn pdf(objects: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(data.len());
+            data.extend(format!("{} 0 obj\n", i + 1).as_bytes());
+            data.extend(object);
+            data.extend(b"\nendobj\n");
+        }
+
+        let xref = data.len();
+        data.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            data.extend(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        data.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        data
+    }
+
+    fn stream(dict: &str, content: &[u8]) -> Vec<u8> {
+        let mut object = format!("<< {} /Length {} >>\nstream\n", dict, content.len()).into_bytes();
+        object.extend(content);
+        object.extend(b"\nendstream");
+        object
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// A single A4 page covered by one image, the way scanner software writes its pdfs
+    fn scanned_pdf(image: Vec<u8>) -> Vec<u8> {
+        pdf(&[
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+            b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+              /Resources << /XObject << /Im0 4 0 R >> >> /Contents 5 0 R >>"
+                .to_vec(),
+            image,
+            stream("", b"q 595 0 0 842 0 0 cm /Im0 Do Q"),
+        ])
+    }
+
+    fn qr_image() -> GrayImage {
+        QrCode::new(b"{\"share\":\"scanned\"}")
+            .unwrap()
+            .render::<Luma<u8>>()
+            .build()
+    }
+
+    fn dark_pixels(image: &GrayImage) -> Vec<bool> {
+        image.pixels().map(|Luma([gray])| *gray < 128).collect()
+    }
+
+    #[test]
+    fn scanned_jpeg_page() {
+        let qr_image = qr_image();
+        let mut jpeg = vec![];
+        JpegEncoder::new_with_quality(&mut Cursor::new(&mut jpeg), 90)
+            .encode_image(&qr_image)
+            .unwrap();
+        let image = stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /DCTDecode",
+                qr_image.width(),
+                qr_image.height()
+            ),
+            &jpeg,
+        );
+
+        let pages = pages(&scanned_pdf(image)).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].images.len(), 1);
+
+        let scanned = &pages[0].images[0];
+        assert_eq!(scanned.dimensions(), qr_image.dimensions());
+        let mismatches = dark_pixels(scanned)
+            .into_iter()
+            .zip(dark_pixels(&qr_image))
+            .filter(|(scanned, original)| scanned != original)
+            .count();
+        assert!(mismatches < qr_image.pixels().len() / 100);
+    }
+
+    #[test]
+    fn flate_image_with_png_predictor() {
+        let qr_image = qr_image();
+        let (width, height) = qr_image.dimensions();
+
+        // every row is encoded with the `up` filter
+        let mut encoded = vec![];
+        let mut previous_row = vec![0u8; width as usize];
+        for row in qr_image.as_raw().chunks(width as usize) {
+            encoded.push(2);
+            encoded.extend(
+                row.iter()
+                    .zip(&previous_row)
+                    .map(|(x, up)| x.wrapping_sub(*up)),
+            );
+            previous_row = row.to_vec();
+        }
+        let image = stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode \
+                 /DecodeParms << /Predictor 15 /Columns {} >>",
+                width, height, width
+            ),
+            &deflate(&encoded),
+        );
+
+        let pages = pages(&scanned_pdf(image)).unwrap();
+        assert_eq!(pages[0].images, vec![qr_image]);
+    }
+
+    #[test]
+    fn printed_sheet() {
+        use crate::secret_input::Secret;
+        use crate::share_files::{self, Sharing, StoredShare};
+        use crate::sheet::{self, Sheet, SheetFormat};
+        use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+
+        let sharing = Sharing::Core(SharedSecretConfig {
+            number_of_shares: 2,
+            threshold: 2,
+        });
+        let share_file =
+            share_files::split_secret(&Secret::Text("top secret".to_string()), &sharing)
+                .unwrap()
+                .remove(0);
+        let share_json = serde_json::to_string(&share_file).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sheet.pdf");
+        let sheet = Sheet {
+            share_file: &StoredShare::Plain(share_file),
+            share_json: &share_json,
+            holder: Some("alice"),
+            date: "2024-01-01",
+        };
+        sheet::write_sheet(&path, &sheet, SheetFormat::Pdf).unwrap();
+
+        let pages = pages(&std::fs::read(&path).unwrap()).unwrap();
+        // the qr code is drawn as rectangles, on the first page
+        let rendered = pages[0].images.last().unwrap();
+        let dark = dark_pixels(rendered)
+            .into_iter()
+            .filter(|dark| *dark)
+            .count();
+        assert!(dark > 0 && dark < rendered.pixels().len());
+    }
+
+    #[test]
+    fn malformed_pdfs() {
+        assert!(matches!(pages(b"hello"), Err(PdfError::NotAPdf)));
+        assert!(matches!(pages(b"%PDF-1.4\n"), Err(PdfError::NoPages)));
+        assert!(matches!(
+            pages(&pdf(&[b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()])),
+            Err(PdfError::NoPages)
+        ));
+
+        let image = stream(
+            "/Type /XObject /Subtype /Image /Width 20 /Height 20 /ColorSpace /DeviceGray \
+             /BitsPerComponent 8 /Filter /FlateDecode",
+            &deflate(&[128; 400]),
+        );
+        let data = scanned_pdf(image);
+
+        // truncated files and flipped bytes fail or give what can be read, but never panic
+        for end in (0..data.len()).step_by(7) {
+            let _ = pages(&data[..end]);
+        }
+        let mut seed: u32 = 12345;
+        for _ in 0..500 {
+            let mut corrupted = data.clone();
+            for _ in 0..4 {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let position = seed as usize % corrupted.len();
+                corrupted[position] = (seed >> 8) as u8;
+            }
+            let _ = pages(&corrupted);
+        }
+    }
+
+    #[test]
+    fn invalid_stream_lengths() {
+        for length in ["-5", "99999999", "1e30"] {
+            let mut image = format!(
+                "<< /Type /XObject /Subtype /Image /Length {} >>\nstream\n",
+                length
+            )
+            .into_bytes();
+            image.extend(b"0123456789\nendstream");
+
+            assert!(matches!(
+                pages(&scanned_pdf(image)),
+                Err(PdfError::InvalidStreamLength { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn decompression_bomb() {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::fast());
+        let zeros = vec![0u8; 1024 * 1024];
+        for _ in 0..MAX_STREAM_SIZE / zeros.len() {
+            encoder.write_all(&zeros).unwrap();
+        }
+        encoder.write_all(&[0]).unwrap();
+        let bomb = encoder.finish().unwrap();
+        let image = stream(
+            "/Type /XObject /Subtype /Image /Width 16 /Height 16 /ColorSpace /DeviceGray \
+             /BitsPerComponent 8 /Filter /FlateDecode",
+            &bomb,
+        );
+
+        assert!(matches!(
+            pages(&scanned_pdf(image)),
+            Err(PdfError::StreamTooBig)
+        ));
+    }
+
+    #[test]
+    fn huge_image_dimensions() {
+        let image = stream(
+            "/Type /XObject /Subtype /Image /Width 1000000 /Height 1000000 \
+             /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+            &deflate(&[0; 100]),
+        );
+        assert!(matches!(
+            pages(&scanned_pdf(image)),
+            Err(PdfError::ImageTooBig { .. })
+        ));
+
+        let image = stream(
+            "/Type /XObject /Subtype /Image /Width 16 /Height 16 \
+             /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode \
+             /DecodeParms << /Predictor 12 /Columns 1e15 >>",
+            &deflate(&[0; 100]),
+        );
+        assert!(matches!(
+            pages(&scanned_pdf(image)),
+            Err(PdfError::StreamTooBig)
+        ));
+    }
+}
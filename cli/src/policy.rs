@@ -0,0 +1,665 @@
+//! Weighted and nested threshold policies (`split --policy <name>`).
+//!
+//! A policy is a group: a threshold and its members, holders or other groups.
+//! "2 from the family AND 1 from the lawyers" is a group with the threshold 2 of two groups,
+//! "the owner's share counts double" is a holder with the weight 2.
+//!
+//! The secret of a group is split among its members: a holder gets as many shares as its weight,
+//! a nested group gets one share, and that share is the secret the nested group splits among its own members.
+//! Every holder ends up with one [PolicyShare]: its parts of all the groups it belongs to.
+//! A group with the threshold 1 (OR) gives every member the whole secret of the group.
+
+use std::collections::{HashMap, HashSet};
+
+use meta_secret_core::errors::CoreError;
+use meta_secret_core::recover_from_shares;
+use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+use meta_secret_core::shared_secret::shared_secret::{
+    PlainText, SharedSecretEncryption, UserShareDto,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::MAX_NUMBER_OF_SHARES;
+
+/// A threshold group, as written in the `policies` section of the config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyGroup {
+    /// Used in the messages about what is still missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Weight of the members required: the number of members for AND, 1 for OR
+    pub threshold: usize,
+    pub of: Vec<PolicyMember>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PolicyMember {
+    /// A holder with the weight 1
+    Holder(String),
+    Weighted(WeightedHolder),
+    Group(PolicyGroup),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeightedHolder {
+    pub holder: String,
+    /// Number of shares of the group the holder gets
+    pub weight: usize,
+}
+
+/// The policy a set was split with, stored in every share of the set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetPolicy {
+    pub name: String,
+    pub root: PolicyGroup,
+}
+
+/// Share of one holder: its parts of the groups it belongs to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyShare {
+    pub policy_parts: Vec<PolicyPart>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyPart {
+    /// Position of the group in the policy: indices of the members from the root group down
+    pub group: Vec<usize>,
+    pub share: PartShare,
+}
+
+/// A share of the secret of a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PartShare {
+    Core(UserShareDto),
+    /// Groups with the threshold 1: every member gets the whole secret of the group
+    Whole(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    #[error("{group} has no members")]
+    EmptyGroup { group: String },
+    #[error("Threshold of {group} can't be 0")]
+    ZeroThreshold { group: String },
+    #[error("Threshold of {group} ({threshold}) can't be greater than the weight of its members ({weight})")]
+    ThresholdExceedsWeight {
+        group: String,
+        threshold: usize,
+        weight: usize,
+    },
+    #[error("{group} gives out {weight} shares, at most {MAX_NUMBER_OF_SHARES} are possible")]
+    TooManyShares { group: String, weight: usize },
+    #[error("Weight of {holder} can't be 0")]
+    ZeroWeight { holder: String },
+    #[error("{holder} is named twice in the policy, every holder gets a single share")]
+    DuplicateHolder { holder: String },
+    #[error("The policy {name} is not met:{}", display_unmet(unmet))]
+    Unmet { name: String, unmet: Vec<String> },
+    #[error("The shares don't meet {group}, they don't match the policy of their set")]
+    GroupNotMet { group: String },
+    #[error("Invalid share of {group}")]
+    InvalidPart { group: String },
+    #[error("The shares were split with a policy, but the policy of the set is unknown")]
+    NoPolicy,
+    #[error(transparent)]
+    CoreError(#[from] CoreError),
+}
+
+impl PolicyGroup {
+    pub fn validate(&self) -> Result<(), PolicyError> {
+        self.validate_group(&mut vec![])?;
+
+        let mut holders = HashSet::new();
+        match self
+            .holders()
+            .into_iter()
+            .find(|holder| !holders.insert(*holder))
+        {
+            Some(holder) => Err(PolicyError::DuplicateHolder {
+                holder: holder.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_group(&self, path: &mut Vec<usize>) -> Result<(), PolicyError> {
+        let group = group_label(self, path);
+        if self.of.is_empty() {
+            return Err(PolicyError::EmptyGroup { group });
+        }
+        if self.threshold == 0 {
+            return Err(PolicyError::ZeroThreshold { group });
+        }
+
+        for (member_index, member) in self.of.iter().enumerate() {
+            match member {
+                PolicyMember::Holder(_) => {}
+                PolicyMember::Weighted(holder) if holder.weight == 0 => {
+                    return Err(PolicyError::ZeroWeight {
+                        holder: holder.holder.clone(),
+                    });
+                }
+                PolicyMember::Weighted(_) => {}
+                PolicyMember::Group(nested_group) => {
+                    path.push(member_index);
+                    nested_group.validate_group(path)?;
+                    path.pop();
+                }
+            }
+        }
+
+        let weight = self.weight();
+        if self.threshold > weight {
+            return Err(PolicyError::ThresholdExceedsWeight {
+                group,
+                threshold: self.threshold,
+                weight,
+            });
+        }
+        if weight > MAX_NUMBER_OF_SHARES {
+            return Err(PolicyError::TooManyShares { group, weight });
+        }
+
+        Ok(())
+    }
+
+    /// All the holders of the policy, in the order of the shares of the set
+    pub fn holders(&self) -> Vec<&str> {
+        self.of
+            .iter()
+            .flat_map(|member| match member {
+                PolicyMember::Holder(holder) => vec![holder.as_str()],
+                PolicyMember::Weighted(holder) => vec![holder.holder.as_str()],
+                PolicyMember::Group(group) => group.holders(),
+            })
+            .collect()
+    }
+
+    /// Number of the shares of the group given out to its members
+    fn weight(&self) -> usize {
+        self.of
+            .iter()
+            .map(|member| match member {
+                PolicyMember::Weighted(holder) => holder.weight,
+                PolicyMember::Holder(_) | PolicyMember::Group(_) => 1,
+            })
+            .sum()
+    }
+
+    /// The smallest number of holders that can meet the group
+    pub fn min_holders(&self) -> usize {
+        self.min_holder_set().len()
+    }
+
+    /// A smallest set of holders that meets the group
+    pub fn min_holder_set(&self) -> Vec<&str> {
+        // the holders needed for every weight up to the threshold, a knapsack over the members
+        let mut sets_for_weight: Vec<Option<Vec<&str>>> = vec![None; self.threshold + 1];
+        sets_for_weight[0] = Some(vec![]);
+
+        for member in &self.of {
+            let (weight, holders) = match member {
+                PolicyMember::Holder(holder) => (1, vec![holder.as_str()]),
+                PolicyMember::Weighted(holder) => (holder.weight, vec![holder.holder.as_str()]),
+                PolicyMember::Group(group) => (1, group.min_holder_set()),
+            };
+
+            for reached in (0..=self.threshold).rev() {
+                let reached_set = match &sets_for_weight[reached] {
+                    Some(reached_set) => reached_set,
+                    None => continue,
+                };
+                let next = (reached + weight).min(self.threshold);
+                let size = reached_set.len() + holders.len();
+                if matches!(&sets_for_weight[next], Some(next_set) if next_set.len() <= size) {
+                    continue;
+                }
+
+                let mut next_set = reached_set.clone();
+                next_set.extend(&holders);
+                sets_for_weight[next] = Some(next_set);
+            }
+        }
+
+        // a valid group can always be met: its threshold is at most the weight of its members
+        sets_for_weight[self.threshold]
+            .take()
+            .expect("Validated policies can be met")
+    }
+
+    /// Splits the text into a share for every holder, in the order of [PolicyGroup::holders]
+    pub fn split(&self, text: &str) -> Result<Vec<PolicyShare>, PolicyError> {
+        let mut parts: HashMap<String, Vec<PolicyPart>> = HashMap::new();
+        self.split_group(text, &mut vec![], &mut parts)?;
+
+        Ok(self
+            .holders()
+            .into_iter()
+            .map(|holder| PolicyShare {
+                policy_parts: parts.remove(holder).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    fn split_group(
+        &self,
+        text: &str,
+        path: &mut Vec<usize>,
+        parts: &mut HashMap<String, Vec<PolicyPart>>,
+    ) -> Result<(), PolicyError> {
+        let mut shares = split_text(text, self.threshold, self.weight())?.into_iter();
+        let mut give = |holder: &str, share: PartShare| {
+            parts
+                .entry(holder.to_string())
+                .or_default()
+                .push(PolicyPart {
+                    group: path.clone(),
+                    share,
+                })
+        };
+
+        let mut nested_groups = vec![];
+        for (member_index, member) in self.of.iter().enumerate() {
+            // the group is split into exactly the weight of its members
+            let mut next_share = || shares.next().expect("A share for every unit of weight");
+            match member {
+                PolicyMember::Holder(holder) => give(holder, next_share()),
+                PolicyMember::Weighted(holder) => {
+                    for _ in 0..holder.weight {
+                        give(&holder.holder, next_share());
+                    }
+                }
+                PolicyMember::Group(group) => {
+                    nested_groups.push((member_index, group, next_share()))
+                }
+            }
+        }
+
+        for (member_index, group, share) in nested_groups {
+            // a share is a plain struct of numbers and strings, serialization can't fail
+            let share_json = serde_json::to_string(&share).expect("Error serializing share");
+            path.push(member_index);
+            group.split_group(&share_json, path, parts)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Restores the text from the shares of the holders
+    pub fn recover(&self, shares: &[PolicyShare]) -> Result<Vec<u8>, PolicyError> {
+        let mut parts: HashMap<&[usize], Vec<&PartShare>> = HashMap::new();
+        for part in shares.iter().flat_map(|share| &share.policy_parts) {
+            parts
+                .entry(part.group.as_slice())
+                .or_default()
+                .push(&part.share);
+        }
+
+        self.recover_group(&mut vec![], &parts)
+    }
+
+    fn recover_group(
+        &self,
+        path: &mut Vec<usize>,
+        parts: &HashMap<&[usize], Vec<&PartShare>>,
+    ) -> Result<Vec<u8>, PolicyError> {
+        let mut shares: Vec<PartShare> = parts
+            .get(path.as_slice())
+            .into_iter()
+            .flatten()
+            .map(|share| (*share).clone())
+            .collect();
+
+        for (member_index, member) in self.of.iter().enumerate() {
+            if let PolicyMember::Group(group) = member {
+                path.push(member_index);
+                let group_secret = group.recover_group(path, parts);
+                let group_name = group_label(group, path);
+                path.pop();
+
+                match group_secret {
+                    Ok(share_json) => {
+                        let share = serde_json::from_slice(&share_json)
+                            .map_err(|_| PolicyError::InvalidPart { group: group_name })?;
+                        shares.push(share);
+                    }
+                    // the other members may still meet the threshold
+                    Err(PolicyError::GroupNotMet { .. }) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        if shares.len() < self.threshold {
+            return Err(PolicyError::GroupNotMet {
+                group: group_label(self, path),
+            });
+        }
+
+        join_text(shares)
+    }
+
+    /// What is still missing to meet the group with the shares of the `present` holders, empty if it is met
+    pub fn unmet(&self, present: &HashSet<&str>) -> Vec<String> {
+        let mut unmet = vec![];
+        self.evaluate(&mut vec![], present, &mut unmet);
+        unmet
+    }
+
+    /// Tells if the group is met. Otherwise adds it to `unmet`, followed by its own unmet groups
+    fn evaluate(
+        &self,
+        path: &mut Vec<usize>,
+        present: &HashSet<&str>,
+        unmet: &mut Vec<String>,
+    ) -> bool {
+        let position = unmet.len();
+        let mut weight = 0;
+        let mut missing = vec![];
+
+        for (member_index, member) in self.of.iter().enumerate() {
+            match member {
+                PolicyMember::Holder(holder) if present.contains(holder.as_str()) => weight += 1,
+                PolicyMember::Holder(holder) => missing.push(holder.clone()),
+                PolicyMember::Weighted(holder) if present.contains(holder.holder.as_str()) => {
+                    weight += holder.weight
+                }
+                PolicyMember::Weighted(holder) => {
+                    missing.push(format!("{} (counts {})", holder.holder, holder.weight))
+                }
+                PolicyMember::Group(group) => {
+                    path.push(member_index);
+                    if group.evaluate(path, present, unmet) {
+                        weight += 1;
+                    } else {
+                        missing.push(group_label(group, path));
+                    }
+                    path.pop();
+                }
+            }
+        }
+
+        if weight >= self.threshold {
+            // the unmet nested groups don't matter anymore
+            unmet.truncate(position);
+            return true;
+        }
+
+        unmet.insert(
+            position,
+            format!(
+                "{}: {} of {}, {} more needed from {}",
+                group_label(self, path),
+                weight,
+                self.threshold,
+                self.threshold - weight,
+                missing.join(", ")
+            ),
+        );
+        false
+    }
+}
+
+impl SetPolicy {
+    /// Checks that the shares with the given indices meet the policy
+    pub fn check(&self, indices: impl IntoIterator<Item = usize>) -> Result<(), PolicyError> {
+        let holders = self.root.holders();
+        let present: HashSet<&str> = indices
+            .into_iter()
+            .filter_map(|index| holders.get(index).copied())
+            .collect();
+
+        let unmet = self.root.unmet(&present);
+        if !unmet.is_empty() {
+            return Err(PolicyError::Unmet {
+                name: self.name.clone(),
+                unmet,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Holder of the share with the index
+    pub fn holder(&self, index: usize) -> Option<&str> {
+        self.root.holders().get(index).copied()
+    }
+}
+
+fn split_text(
+    text: &str,
+    threshold: usize,
+    number_of_shares: usize,
+) -> Result<Vec<PartShare>, CoreError> {
+    if threshold == 1 {
+        return Ok(vec![PartShare::Whole(text.to_string()); number_of_shares]);
+    }
+
+    let config = SharedSecretConfig {
+        number_of_shares,
+        threshold,
+    };
+    let shared_secret = SharedSecretEncryption::new(config, &PlainText::from(text))?;
+    Ok((0..number_of_shares)
+        .map(|share_index| PartShare::Core(shared_secret.get_share(share_index)))
+        .collect())
+}
+
+fn join_text(shares: Vec<PartShare>) -> Result<Vec<u8>, PolicyError> {
+    let mut core_shares = vec![];
+    for share in shares {
+        match share {
+            PartShare::Whole(text) => return Ok(text.into_bytes()),
+            PartShare::Core(share) => core_shares.push(share),
+        }
+    }
+
+    Ok(recover_from_shares(core_shares)?.text.into_bytes())
+}
+
+/// How a group is called in the messages: its name or its position in the policy
+fn group_label(group: &PolicyGroup, path: &[usize]) -> String {
+    match &group.name {
+        Some(name) => name.clone(),
+        None if path.is_empty() => String::from("the policy"),
+        None => {
+            let position: Vec<String> = path.iter().map(|index| (index + 1).to_string()).collect();
+            format!("group {}", position.join("."))
+        }
+    }
+}
+
+fn display_unmet(unmet: &[String]) -> String {
+    unmet.iter().map(|line| format!("\n  - {}", line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "top secret";
+
+    fn policy(yaml: &str) -> PolicyGroup {
+        let group: PolicyGroup = serde_yaml::from_str(yaml).unwrap();
+        group.validate().unwrap();
+        group
+    }
+
+    /// Shares of the given holders only
+    fn shares_of(
+        group: &PolicyGroup,
+        shares: &[PolicyShare],
+        holders: &[&str],
+    ) -> Vec<PolicyShare> {
+        group
+            .holders()
+            .into_iter()
+            .zip(shares)
+            .filter(|(holder, _)| holders.contains(holder))
+            .map(|(_, share)| share.clone())
+            .collect()
+    }
+
+    fn indices_of(group: &PolicyGroup, holders: &[&str]) -> Vec<usize> {
+        group
+            .holders()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, holder)| holders.contains(holder))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    #[test]
+    fn nested_group() {
+        let group = policy(
+            "
+            threshold: 2
+            of:
+              - alice
+              - name: lawyers
+                threshold: 2
+                of: [bob, carol, dave]
+            ",
+        );
+        assert_eq!(group.holders(), vec!["alice", "bob", "carol", "dave"]);
+        assert_eq!(group.min_holders(), 3);
+        assert_eq!(group.min_holder_set(), ["alice", "bob", "carol"]);
+
+        let shares = group.split(SECRET).unwrap();
+        assert_eq!(shares.len(), 4);
+        let set_policy = SetPolicy {
+            name: "estate".to_string(),
+            root: group.clone(),
+        };
+
+        for holders in [
+            &["alice", "bob", "carol"][..],
+            &["alice", "carol", "dave"],
+            &["alice", "bob", "carol", "dave"],
+        ] {
+            let recovered = group.recover(&shares_of(&group, &shares, holders)).unwrap();
+            assert_eq!(recovered, SECRET.as_bytes());
+            set_policy.check(indices_of(&group, holders)).unwrap();
+        }
+
+        // one share too few: the lawyers need two of them, alice can't do without them
+        for holders in [&["alice", "bob"][..], &["bob", "carol", "dave"]] {
+            let result = group.recover(&shares_of(&group, &shares, holders));
+            assert!(matches!(result, Err(PolicyError::GroupNotMet { .. })));
+
+            let result = set_policy.check(indices_of(&group, holders));
+            assert!(matches!(result, Err(PolicyError::Unmet { .. })));
+        }
+    }
+
+    #[test]
+    fn weighted_holders() {
+        let group = policy(
+            "
+            threshold: 3
+            of:
+              - holder: owner
+                weight: 2
+              - bob
+              - carol
+            ",
+        );
+        assert_eq!(group.min_holders(), 2);
+        assert_eq!(group.min_holder_set(), ["owner", "bob"]);
+
+        let shares = group.split(SECRET).unwrap();
+        assert_eq!(shares.len(), 3);
+        assert_eq!(shares[0].policy_parts.len(), 2);
+
+        for holders in [
+            &["owner", "bob"][..],
+            &["owner", "carol"],
+            &["bob", "carol", "owner"],
+        ] {
+            let recovered = group.recover(&shares_of(&group, &shares, holders)).unwrap();
+            assert_eq!(recovered, SECRET.as_bytes());
+        }
+
+        // one share too few
+        for holders in [&["owner"][..], &["bob", "carol"]] {
+            let result = group.recover(&shares_of(&group, &shares, holders));
+            assert!(matches!(result, Err(PolicyError::GroupNotMet { .. })));
+        }
+    }
+
+    #[test]
+    fn smallest_holder_set() {
+        // the nested group comes first, but two single holders are fewer than the group needs
+        let group = policy(
+            "
+            threshold: 2
+            of:
+              - name: board
+                threshold: 2
+                of: [alice, bob]
+              - carol
+              - dave
+            ",
+        );
+        assert_eq!(group.min_holder_set(), ["carol", "dave"]);
+
+        let shares = group.split(SECRET).unwrap();
+        let recovered = group
+            .recover(&shares_of(&group, &shares, &group.min_holder_set()))
+            .unwrap();
+        assert_eq!(recovered, SECRET.as_bytes());
+    }
+
+    #[test]
+    fn weight_meeting_the_threshold_alone() {
+        let group = policy(
+            "
+            threshold: 2
+            of:
+              - holder: owner
+                weight: 2
+              - bob
+              - carol
+            ",
+        );
+        assert_eq!(group.min_holders(), 1);
+        assert_eq!(group.min_holder_set(), ["owner"]);
+
+        let shares = group.split(SECRET).unwrap();
+        let recovered = group
+            .recover(&shares_of(&group, &shares, &["owner"]))
+            .unwrap();
+        assert_eq!(recovered, SECRET.as_bytes());
+
+        let result = group.recover(&shares_of(&group, &shares, &["bob"]));
+        assert!(matches!(result, Err(PolicyError::GroupNotMet { .. })));
+    }
+
+    #[test]
+    fn invalid_policies() {
+        let group: PolicyGroup = serde_yaml::from_str("{threshold: 3, of: [alice, bob]}").unwrap();
+        assert!(matches!(
+            group.validate(),
+            Err(PolicyError::ThresholdExceedsWeight {
+                threshold: 3,
+                weight: 2,
+                ..
+            })
+        ));
+
+        let group: PolicyGroup =
+            serde_yaml::from_str("{threshold: 1, of: [alice, {threshold: 1, of: [alice]}]}")
+                .unwrap();
+        assert!(matches!(
+            group.validate(),
+            Err(PolicyError::DuplicateHolder { .. })
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+//! Passphrase-protected shares: every share file can be encrypted with its own passphrase, chosen by its holder,
+//! so a stolen share file (or a photo of its sheet) is useless without the passphrase.
+//!
+//! The key is derived from the passphrase with Argon2id, the share file is encrypted with XChaCha20Poly1305
+//! and stored in an envelope. The envelope keeps the set information in the clear, so `inspect` and `audit`
+//! work without the passphrase. The set information is authenticated as associated data, so it can't be altered.
+
+use std::io;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+use crate::secret_input;
+use crate::share_files::{ShareFile, ShareFileError, ShareSetInfo};
+
+/// Version of the envelope format, see [EncryptedShareFile]
+pub const ENCRYPTED_SHARE_FILE_VERSION: u32 = 1;
+
+/// Argon2id defaults of RFC 9106 for memory constrained environments: 64 MiB, 3 passes, 4 lanes
+const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+const ARGON2_ITERATIONS: u32 = 3;
+const ARGON2_PARALLELISM: u32 = 4;
+const SALT_SIZE: usize = 16;
+const KEY_SIZE: usize = 32;
+/// Wrong passphrases typed in a row before giving up
+const PASSPHRASE_ATTEMPTS: usize = 3;
+
+/// Share file encrypted with the passphrase of its holder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedShareFile {
+    pub version: u32,
+    /// Copy of the set information of the encrypted share, missing for the shares of the previous versions of the cli
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set: Option<ShareSetInfo>,
+    pub encryption: Encryption,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Encryption {
+    pub kdf: Kdf,
+    pub cipher: Cipher,
+    /// base64
+    pub nonce: String,
+    /// The share file as json, encrypted, base64
+    pub ciphertext: String,
+}
+
+/// How the key is derived from the passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Kdf {
+    pub algorithm: KdfAlgorithm,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// base64
+    pub salt: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KdfAlgorithm {
+    Argon2id,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cipher {
+    Xchacha20poly1305,
+}
+
+/// Encrypts the share file with the passphrase
+pub fn encrypt(share_file: &ShareFile, passphrase: &str) -> EncryptedShareFile {
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+
+    let kdf = Kdf {
+        algorithm: KdfAlgorithm::Argon2id,
+        memory_kib: ARGON2_MEMORY_KIB,
+        iterations: ARGON2_ITERATIONS,
+        parallelism: ARGON2_PARALLELISM,
+        salt: BASE64.encode(salt),
+    };
+    let key = derive_key(&kdf, &salt, passphrase).expect("Default Argon2 parameters are valid");
+
+    // a share file is a plain struct of numbers and strings, serialization can't fail
+    let plaintext = serde_json::to_vec(share_file).expect("Error serializing share");
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(key.as_slice().into())
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &plaintext,
+                aad: &associated_data(&share_file.set),
+            },
+        )
+        .expect("Share encryption can't fail");
+
+    EncryptedShareFile {
+        version: ENCRYPTED_SHARE_FILE_VERSION,
+        set: share_file.set.clone(),
+        encryption: Encryption {
+            kdf,
+            cipher: Cipher::Xchacha20poly1305,
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        },
+    }
+}
+
+/// Decrypts the share file with the passphrase
+pub fn decrypt(
+    path: &Path,
+    share_file: &EncryptedShareFile,
+    passphrase: &str,
+) -> Result<ShareFile, ShareFileError> {
+    let invalid_envelope = || ShareFileError::InvalidEnvelope {
+        path: path.to_path_buf(),
+    };
+
+    let encryption = &share_file.encryption;
+    check_kdf(path, &encryption.kdf)?;
+    let salt = BASE64
+        .decode(&encryption.kdf.salt)
+        .map_err(|_| invalid_envelope())?;
+    let nonce = BASE64
+        .decode(&encryption.nonce)
+        .map_err(|_| invalid_envelope())?;
+    let ciphertext = BASE64
+        .decode(&encryption.ciphertext)
+        .map_err(|_| invalid_envelope())?;
+    if nonce.len() != XNonce::default().len() {
+        return Err(invalid_envelope());
+    }
+
+    let key = derive_key(&encryption.kdf, &salt, passphrase).map_err(|_| invalid_envelope())?;
+    let plaintext = XChaCha20Poly1305::new(key.as_slice().into())
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &associated_data(&share_file.set),
+            },
+        )
+        .map_err(|_| ShareFileError::WrongPassphrase {
+            path: path.to_path_buf(),
+        })?;
+
+    serde_json::from_slice(&plaintext).map_err(|source| ShareFileError::InvalidShare {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Asks for the passphrase of the share in a hidden terminal prompt and decrypts the share.
+/// A wrong passphrase can be typed again a couple of times
+pub fn unlock(path: &Path, share_file: &EncryptedShareFile) -> Result<ShareFile, ShareFileError> {
+    // don't ask for a passphrase that can't be used anyway
+    check_kdf(path, &share_file.encryption.kdf)?;
+
+    let prompt = match &share_file.set {
+        Some(set) => format!(
+            "Enter passphrase of share {} of {} ({}): ",
+            set.index + 1,
+            set.number_of_shares,
+            path.display()
+        ),
+        None => format!("Enter passphrase of share {}: ", path.display()),
+    };
+
+    let mut attempt = 1;
+    loop {
+        let passphrase = rpassword::prompt_password(&prompt).map_err(|source: io::Error| {
+            ShareFileError::PassphraseError {
+                path: path.to_path_buf(),
+                source,
+            }
+        })?;
+
+        match decrypt(path, share_file, &passphrase) {
+            Err(ShareFileError::WrongPassphrase { .. }) if attempt < PASSPHRASE_ATTEMPTS => {
+                eprintln!("Wrong passphrase, try again");
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Asks for a new passphrase for every share
+pub fn read_passphrases(number_of_shares: usize) -> Result<Vec<String>> {
+    let mut passphrases = vec![];
+    for share_index in 0..number_of_shares {
+        let name = format!(
+            "passphrase of share {} of {}",
+            share_index + 1,
+            number_of_shares
+        );
+
+        let passphrase = secret_input::read_passphrase(&name, true)?;
+        if passphrase.is_empty() {
+            bail!("The {} is empty", name);
+        }
+
+        passphrases.push(passphrase);
+    }
+
+    Ok(passphrases)
+}
+
+/// The envelope comes from the share file, so a forged one could make the key derivation
+/// take all the memory and time of the machine. Split never uses more than the defaults
+fn check_kdf(path: &Path, kdf: &Kdf) -> Result<(), ShareFileError> {
+    if kdf.memory_kib > ARGON2_MEMORY_KIB
+        || kdf.iterations > ARGON2_ITERATIONS
+        || kdf.parallelism > ARGON2_PARALLELISM
+    {
+        return Err(ShareFileError::KdfTooExpensive {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(())
+}
+
+fn derive_key(kdf: &Kdf, salt: &[u8], passphrase: &str) -> Result<Vec<u8>, argon2::Error> {
+    let params = Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(KEY_SIZE),
+    )?;
+
+    let mut key = vec![0u8; KEY_SIZE];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+        passphrase.as_bytes(),
+        salt,
+        &mut key,
+    )?;
+
+    Ok(key)
+}
+
+/// The set information stays readable, binding it to the ciphertext keeps it from being swapped
+fn associated_data(set: &Option<ShareSetInfo>) -> Vec<u8> {
+    serde_json::to_vec(set).expect("Error serializing set information")
+}
+
+#[cfg(test)]
+mod tests {
+    use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+
+    use super::*;
+    use crate::secret_input::Secret;
+    use crate::share_files::{self, Sharing};
+
+    const PASSPHRASE: &str = "blue sky over the harbour";
+
+    fn share_file() -> ShareFile {
+        let sharing = Sharing::Core(SharedSecretConfig {
+            number_of_shares: 3,
+            threshold: 2,
+        });
+        share_files::split_secret(&Secret::Text("top secret".to_string()), &sharing)
+            .unwrap()
+            .remove(0)
+    }
+
+    fn as_json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let share_file = share_file();
+        let encrypted = encrypt(&share_file, PASSPHRASE);
+        assert_eq!(as_json(&encrypted.set), as_json(&share_file.set));
+
+        let decrypted = decrypt(Path::new("share.json"), &encrypted, PASSPHRASE).unwrap();
+        assert_eq!(as_json(&decrypted), as_json(&share_file));
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let encrypted = encrypt(&share_file(), PASSPHRASE);
+
+        let result = decrypt(
+            Path::new("share.json"),
+            &encrypted,
+            "blue sky over the harbor",
+        );
+        assert!(matches!(
+            result,
+            Err(ShareFileError::WrongPassphrase { .. })
+        ));
+    }
+
+    #[test]
+    fn tampered_set_information() {
+        let mut encrypted = encrypt(&share_file(), PASSPHRASE);
+        if let Some(set) = &mut encrypted.set {
+            set.index += 1;
+        }
+
+        let result = decrypt(Path::new("share.json"), &encrypted, PASSPHRASE);
+        assert!(matches!(
+            result,
+            Err(ShareFileError::WrongPassphrase { .. })
+        ));
+    }
+
+    #[test]
+    fn expensive_kdf_is_rejected() {
+        let kdf = Kdf {
+            algorithm: KdfAlgorithm::Argon2id,
+            memory_kib: ARGON2_MEMORY_KIB,
+            iterations: ARGON2_ITERATIONS,
+            parallelism: ARGON2_PARALLELISM,
+            salt: BASE64.encode([0u8; SALT_SIZE]),
+        };
+        let mut encrypted = encrypt(&share_file(), PASSPHRASE);
+
+        for forged_kdf in [
+            Kdf {
+                memory_kib: u32::MAX,
+                ..kdf.clone()
+            },
+            Kdf {
+                iterations: u32::MAX,
+                ..kdf.clone()
+            },
+            Kdf {
+                parallelism: ARGON2_PARALLELISM + 1,
+                ..kdf.clone()
+            },
+        ] {
+            encrypted.encryption.kdf = forged_kdf;
+            let result = decrypt(Path::new("share.json"), &encrypted, PASSPHRASE);
+            assert!(matches!(
+                result,
+                Err(ShareFileError::KdfTooExpensive { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, ImageFormat, Luma};
+use meta_secret_share_formats::qr_parts::{self, PartsError};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+
+use crate::pdf_pages::{self, PdfError};
+
+/// Image formats the qr codes of shares are read from
+pub const QR_IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+/// Scans and printed sheets, every page can hold qr codes
+pub const PDF_EXTENSION: &str = "pdf";
+
+/// The most bytes written into one qr code: bigger codes get too dense to be read from a photo or a print.
+/// Bigger shares are written as several qr codes, see `generate_qr_codes`
+pub const MAX_QR_CODE_DATA: usize = 1000;
+
+/// Big photos are also looked at scaled down to this size: the finder patterns of a code
+/// that fills the photo get clearer, the noise of the sensor and the halftone dots of prints are smoothed out
+const SCALED_DOWN_SIZE: u32 = 1200;
+/// Share of the darkest and of the brightest pixels ignored when stretching the contrast
+const CONTRAST_CLIP: f32 = 0.01;
+
+#[derive(Debug, thiserror::Error)]
+pub enum QrError {
+    #[error("Error encoding data into a qr code")]
+    EncodingError(#[from] qrcode::types::QrError),
+    #[error(transparent)]
+    ImageError(#[from] image::ImageError),
+    #[error("No qr code could be read in: {}", display_locations(locations))]
+    NotFound { locations: Vec<PathBuf> },
+    #[error("Error reading: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+    #[error("Error reading pdf: {path}")]
+    PdfError { path: PathBuf, source: PdfError },
+    #[error(transparent)]
+    PartsError(#[from] PartsError),
+}
+
+/// A qr code read from an image or from a page of a pdf
+pub struct ScannedCode {
+    /// The file, with the page (`scan.pdf#page=2`) and the code (`photo.jpg#code=3`)
+    /// when the file has several of them
+    pub location: PathBuf,
+    pub content: String,
+}
+
+/// Every qr code of an image or a pdf
+#[derive(Default)]
+pub struct Scan {
+    pub codes: Vec<ScannedCode>,
+    /// The image, or the pages of the pdf, where no qr code could be read
+    pub unreadable: Vec<PathBuf>,
+}
+
+pub fn is_qr_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| QR_IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case(PDF_EXTENSION))
+        .unwrap_or(false)
+}
+
+/// Files qr codes are read from: images and pdfs
+pub fn is_qr_source(path: &Path) -> bool {
+    is_qr_image(path) || is_pdf(path)
+}
+
+/// Renders the data as a qr code in png format
+pub fn generate_qr_code(data: &str) -> Result<Vec<u8>, QrError> {
+    let code = QrCode::new(data.as_bytes())?;
+    let image = code.render::<Luma<u8>>().build();
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// Renders the data as one qr code in png format, or as several qr codes with part headers
+/// (`qr_parts`) if it's too big for one readable code. The set id goes into the part headers
+pub fn generate_qr_codes(data: &str, set_id: &str) -> Result<Vec<Vec<u8>>, QrError> {
+    if data.len() <= MAX_QR_CODE_DATA {
+        return Ok(vec![generate_qr_code(data)?]);
+    }
+
+    qr_parts::split(data, set_id, MAX_QR_CODE_DATA)?
+        .iter()
+        .map(|part| generate_qr_code(part))
+        .collect()
+}
+
+/// Renders the data as a qr code made of unicode half blocks, two pixel rows per line of text.
+/// Every line is painted black on white, so the code scans on dark and light terminal themes alike
+pub fn render_terminal_qr_code(data: &str) -> Result<String, QrError> {
+    let code = QrCode::new(data.as_bytes())?;
+    let image = code.render::<Dense1x2>().quiet_zone(true).build();
+
+    let lines: Vec<String> = image
+        .lines()
+        .map(|line| format!("\x1b[30;47m{}\x1b[0m", line))
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// The first qr code of the image or the pdf
+pub fn read_qr_code(path: &Path) -> Result<String, QrError> {
+    let scan = scan(path)?;
+    match scan.codes.into_iter().next() {
+        Some(code) => Ok(code.content),
+        None => Err(QrError::NotFound {
+            locations: scan.unreadable,
+        }),
+    }
+}
+
+/// Reads every qr code of the image, or of every page of the pdf
+pub fn scan(path: &Path) -> Result<Scan, QrError> {
+    let pages = if is_pdf(path) {
+        let data = fs::read(path).map_err(|source| QrError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let pages = pdf_pages::pages(&data).map_err(|source| QrError::PdfError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let number_of_pages = pages.len();
+        pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let location = if number_of_pages > 1 {
+                    PathBuf::from(format!("{}#page={}", path.display(), i + 1))
+                } else {
+                    path.to_path_buf()
+                };
+                (location, page.images)
+            })
+            .collect()
+    } else {
+        vec![(path.to_path_buf(), vec![image::open(path)?.to_luma8()])]
+    };
+
+    let mut scan = Scan::default();
+    for (location, images) in pages {
+        let mut contents: Vec<String> = vec![];
+        for content in images.iter().flat_map(read_codes) {
+            if !contents.contains(&content) {
+                contents.push(content);
+            }
+        }
+
+        if contents.is_empty() {
+            scan.unreadable.push(location);
+            continue;
+        }
+
+        let number_of_codes = contents.len();
+        for (i, content) in contents.into_iter().enumerate() {
+            let location = if number_of_codes > 1 {
+                let separator = if location.to_string_lossy().contains('#') {
+                    '&'
+                } else {
+                    '#'
+                };
+                PathBuf::from(format!("{}{}code={}", location.display(), separator, i + 1))
+            } else {
+                location.clone()
+            };
+            scan.codes.push(ScannedCode { location, content });
+        }
+    }
+
+    Ok(scan)
+}
+
+/// Every distinct qr code of the image. The detector finds rotated and tilted codes by their finder patterns;
+/// the image is also looked at with its contrast stretched (faded prints, dim photos), binarized and,
+/// if it's big, scaled down. A code found in any of them counts
+fn read_codes(image: &GrayImage) -> Vec<String> {
+    let stretched = stretch_contrast(image);
+    let binarized = binarize(&stretched);
+    let scaled_down = scale_down(&stretched);
+
+    let mut codes = vec![];
+    let variants = [
+        Some(image),
+        Some(&stretched),
+        Some(&binarized),
+        scaled_down.as_ref(),
+    ];
+    for variant in variants.into_iter().flatten() {
+        for code in decode_grids(variant) {
+            if !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+    }
+    codes
+}
+
+fn decode_grids(image: &GrayImage) -> Vec<String> {
+    let mut prepared_image = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| image.get_pixel(x as u32, y as u32).0[0],
+    );
+
+    // a grid that can't be decoded is a false match or a damaged code, another look may read it
+    prepared_image
+        .detect_grids()
+        .iter()
+        .filter_map(|grid| grid.decode().ok())
+        .map(|(_, content)| content)
+        .collect()
+}
+
+/// Spreads the brightness of the image over the full range, ignoring a few of the darkest and brightest pixels
+fn stretch_contrast(image: &GrayImage) -> GrayImage {
+    let histogram = histogram(image);
+    let clipped = (image.len() as f32 * CONTRAST_CLIP) as usize;
+
+    let mut darkest = 0;
+    let mut count = 0;
+    while darkest < 255 && count + histogram[darkest] <= clipped {
+        count += histogram[darkest];
+        darkest += 1;
+    }
+    let mut brightest = 255;
+    count = 0;
+    while brightest > darkest && count + histogram[brightest] <= clipped {
+        count += histogram[brightest];
+        brightest -= 1;
+    }
+
+    let range = (brightest - darkest).max(1) as f32;
+    let mut stretched = image.clone();
+    for pixel in stretched.pixels_mut() {
+        let value = (f32::from(pixel.0[0]) - darkest as f32) / range;
+        pixel.0[0] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    stretched
+}
+
+/// Black and white image, split at the threshold that separates the dark and the bright pixels best (Otsu)
+fn binarize(image: &GrayImage) -> GrayImage {
+    let histogram = histogram(image);
+    let total = image.len() as f64;
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &count)| value as f64 * count as f64)
+        .sum();
+
+    let mut threshold = 128;
+    let mut best_variance = 0.0;
+    let mut dark_count = 0.0;
+    let mut dark_sum = 0.0;
+    for (value, &count) in histogram.iter().enumerate() {
+        dark_count += count as f64;
+        dark_sum += value as f64 * count as f64;
+        let bright_count = total - dark_count;
+        if dark_count == 0.0 || bright_count == 0.0 {
+            continue;
+        }
+        let mean_difference = dark_sum / dark_count - (sum - dark_sum) / bright_count;
+        let variance = dark_count * bright_count * mean_difference * mean_difference;
+        if variance > best_variance {
+            best_variance = variance;
+            threshold = value;
+        }
+    }
+
+    let mut binarized = image.clone();
+    for pixel in binarized.pixels_mut() {
+        pixel.0[0] = if usize::from(pixel.0[0]) <= threshold {
+            0
+        } else {
+            255
+        };
+    }
+    binarized
+}
+
+fn scale_down(image: &GrayImage) -> Option<GrayImage> {
+    let longest_side = image.width().max(image.height());
+    if longest_side <= SCALED_DOWN_SIZE {
+        return None;
+    }
+
+    let scale = SCALED_DOWN_SIZE as f32 / longest_side as f32;
+    let width = ((image.width() as f32 * scale).round() as u32).max(1);
+    let height = ((image.height() as f32 * scale).round() as u32).max(1);
+    Some(imageops::resize(image, width, height, FilterType::Triangle))
+}
+
+fn histogram(image: &GrayImage) -> [usize; 256] {
+    let mut histogram = [0; 256];
+    for pixel in image.pixels() {
+        histogram[usize::from(pixel.0[0])] += 1;
+    }
+    histogram
+}
+
+pub fn display_locations(locations: &[PathBuf]) -> String {
+    locations
+        .iter()
+        .map(|location| location.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,213 @@
+//! Shares encrypted to their recipients: every share is encrypted with the public key of its holder
+//! (an age X25519 key or an SSH key) and can be sent over untrusted channels, e.g. by email.
+//!
+//! The files are ascii armored [age](https://age-encryption.org) files, so the holders can also decrypt them
+//! with `age -d -i <identity>` (or `rage`) and keep the plain share file.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::SecretString;
+
+pub const AGE_EXTENSION: &str = "age";
+
+/// Holder of a share and the public key the share is encrypted to
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    pub name: String,
+    /// `age1...` or an SSH public key (`ssh-ed25519 AAAA...`, `ssh-rsa AAAA...`)
+    pub public_key: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecipientError {
+    #[error("Invalid public key of recipient {name}. Expected an age key (age1...) or an SSH key (ssh-ed25519, ssh-rsa)")]
+    InvalidPublicKey { name: String },
+    #[error("Error reading identity file: {path}")]
+    ReadIdentityError { path: PathBuf, source: io::Error },
+    #[error("Invalid identity file: {path}. Expected an age identity file or an SSH private key (ed25519, rsa)")]
+    InvalidIdentity { path: PathBuf },
+    #[error("The share is encrypted to a recipient, use --identity <FILE> to decrypt it: {path}")]
+    NoIdentities { path: PathBuf },
+    #[error("Error decrypting: {path}. None of the identities can decrypt it")]
+    DecryptionError {
+        path: PathBuf,
+        source: age::DecryptError,
+    },
+    #[error("Error decrypting: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+}
+
+/// Private keys of the recipients, used to decrypt their shares
+#[derive(Default)]
+pub struct Identities(Vec<Box<dyn age::Identity>>);
+
+/// Files encrypted to a recipient
+pub fn is_age_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext == AGE_EXTENSION)
+        .unwrap_or(false)
+}
+
+/// Name of the share file of the recipient: `<name>.age`, with the characters unsafe for file names replaced
+pub fn file_name(recipient: &Recipient) -> String {
+    let name: String = recipient
+        .name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '-',
+        })
+        .collect();
+
+    format!("{}.{}", name.trim_start_matches('.'), AGE_EXTENSION)
+}
+
+/// Checks the public key of the recipient without encrypting anything
+pub fn validate(recipient: &Recipient) -> Result<(), RecipientError> {
+    parse_public_key(recipient).map(|_| ())
+}
+
+/// Encrypts the data to the recipient, as an ascii armored age file
+pub fn encrypt(recipient: &Recipient, data: &[u8]) -> Result<Vec<u8>, RecipientError> {
+    let public_key = parse_public_key(recipient)?;
+    let encryptor = age::Encryptor::with_recipients(iter::once(public_key.as_ref()))
+        .expect("One recipient is always given");
+
+    let mut encrypted = vec![];
+    let armored = ArmoredWriter::wrap_output(&mut encrypted, Format::AsciiArmor)
+        .expect("Writing into memory can't fail");
+    let mut writer = encryptor
+        .wrap_output(armored)
+        .expect("Writing into memory can't fail");
+    writer
+        .write_all(data)
+        .and_then(|_| writer.finish())
+        .and_then(|armored| armored.finish())
+        .expect("Writing into memory can't fail");
+
+    Ok(encrypted)
+}
+
+/// Decrypts a share file encrypted to one of the identities
+pub fn decrypt(
+    path: &Path,
+    encrypted: &[u8],
+    identities: &Identities,
+) -> Result<Vec<u8>, RecipientError> {
+    if identities.0.is_empty() {
+        return Err(RecipientError::NoIdentities {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let decryption_error = |source| RecipientError::DecryptionError {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let decryptor = age::Decryptor::new(ArmoredReader::new(encrypted)).map_err(decryption_error)?;
+    let mut reader = decryptor
+        .decrypt(identities.0.iter().map(|identity| identity.as_ref()))
+        .map_err(decryption_error)?;
+
+    let mut decrypted = vec![];
+    reader
+        .read_to_end(&mut decrypted)
+        .map_err(|source| RecipientError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    Ok(decrypted)
+}
+
+/// Reads age identity files (`age-keygen` output) and SSH private keys.
+/// The passphrase of an encrypted SSH key is asked for when the key is used
+pub fn load_identities(files: &[PathBuf]) -> Result<Identities, RecipientError> {
+    let mut identities = Identities::default();
+    for path in files {
+        let content =
+            fs::read_to_string(path).map_err(|source| RecipientError::ReadIdentityError {
+                path: path.clone(),
+                source,
+            })?;
+        let invalid_identity = || RecipientError::InvalidIdentity { path: path.clone() };
+
+        if content.trim_start().starts_with("-----BEGIN") {
+            let identity = age::ssh::Identity::from_buffer(
+                content.as_bytes(),
+                Some(path.display().to_string()),
+            )
+            .map_err(|_| invalid_identity())?;
+            if let age::ssh::Identity::Unsupported(_) = identity {
+                return Err(invalid_identity());
+            }
+
+            identities
+                .0
+                .push(Box::new(identity.with_callbacks(TerminalCallbacks)));
+        } else {
+            let identity_file = age::IdentityFile::from_buffer(content.as_bytes())
+                .map_err(|_| invalid_identity())?;
+            let file_identities = identity_file
+                .into_identities()
+                .map_err(|_| invalid_identity())?;
+            if file_identities.is_empty() {
+                return Err(invalid_identity());
+            }
+
+            identities.0.extend(file_identities);
+        }
+    }
+
+    Ok(identities)
+}
+
+fn parse_public_key(recipient: &Recipient) -> Result<Box<dyn age::Recipient>, RecipientError> {
+    let invalid_public_key = || RecipientError::InvalidPublicKey {
+        name: recipient.name.clone(),
+    };
+
+    let public_key = recipient.public_key.trim();
+    if public_key.starts_with("age1") {
+        let public_key: age::x25519::Recipient =
+            public_key.parse().map_err(|_| invalid_public_key())?;
+        return Ok(Box::new(public_key));
+    }
+
+    if public_key.starts_with("ssh-") {
+        let public_key: age::ssh::Recipient =
+            public_key.parse().map_err(|_| invalid_public_key())?;
+        return Ok(Box::new(public_key));
+    }
+
+    Err(invalid_public_key())
+}
+
+/// Asks for the passphrase of an encrypted SSH key in a hidden terminal prompt
+#[derive(Clone)]
+struct TerminalCallbacks;
+
+impl age::Callbacks for TerminalCallbacks {
+    fn display_message(&self, message: &str) {
+        eprintln!("{}", message);
+    }
+
+    fn confirm(&self, _message: &str, _yes_string: &str, _no_string: Option<&str>) -> Option<bool> {
+        None
+    }
+
+    fn request_public_string(&self, _description: &str) -> Option<String> {
+        None
+    }
+
+    fn request_passphrase(&self, description: &str) -> Option<SecretString> {
+        rpassword::prompt_password(format!("{}: ", description))
+            .ok()
+            .map(SecretString::from)
+    }
+}
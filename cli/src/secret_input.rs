@@ -0,0 +1,228 @@
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+/// Where `split` takes the secret from.
+/// Without any flag the secret is read from a hidden terminal prompt, or from stdin when it is piped.
+#[derive(Debug, Args)]
+pub struct SecretArgs {
+    /// Pass the secret as an argument. It will be visible in shell history and `ps` output,
+    /// so it has to be unlocked with --insecure-secret-arg
+    #[clap(
+        short,
+        long,
+        requires = "insecure-secret-arg",
+        conflicts_with_all = &["secret-fd", "secret-env"]
+    )]
+    secret: Option<String>,
+
+    /// Allow --secret
+    #[clap(long)]
+    insecure_secret_arg: bool,
+
+    /// Read the secret from an already opened file descriptor, 3 or higher, e.g. `--secret-fd 3 3<secret.txt`
+    #[clap(long, conflicts_with = "secret-env")]
+    secret_fd: Option<i32>,
+
+    /// Read the secret from the environment variable with the given name
+    #[clap(long)]
+    secret_env: Option<String>,
+
+    /// Split the content of a file, e.g. a private key or a keystore. Binary files are supported
+    #[clap(long, conflicts_with_all = &["secret", "secret-fd", "secret-env"])]
+    file: Option<PathBuf>,
+}
+
+/// The secret to split: a text, the content of a file or a data key of an encrypted file (hybrid mode)
+#[derive(Debug)]
+pub enum Secret {
+    Text(String),
+    File {
+        file_name: Option<String>,
+        content_type: String,
+        content: Vec<u8>,
+    },
+    DataKey {
+        key: Vec<u8>,
+        encrypted_file: String,
+        file_name: Option<String>,
+        content_type: String,
+    },
+}
+
+impl Secret {
+    /// The secret data itself, as it is returned by restore
+    pub fn content(&self) -> &[u8] {
+        match self {
+            Secret::Text(text) => text.as_bytes(),
+            Secret::File { content, .. } => content,
+            Secret::DataKey { key, .. } => key,
+        }
+    }
+}
+
+impl SecretArgs {
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn read_secret(self) -> Result<Secret> {
+        if let Some(path) = self.file {
+            return read_secret_file(&path);
+        }
+
+        let secret = if let Some(secret) = self.secret {
+            secret
+        } else if let Some(fd) = self.secret_fd {
+            read_from_fd(fd)?
+        } else if let Some(var_name) = self.secret_env {
+            env::var(&var_name)
+                .with_context(|| format!("Environment variable {} is not set", var_name))?
+        } else if atty::is(atty::Stream::Stdin) {
+            read_from_prompt()?
+        } else {
+            read_from_stdin()?
+        };
+
+        if secret.is_empty() {
+            bail!("The secret is empty");
+        }
+
+        Ok(Secret::Text(secret))
+    }
+}
+
+/// The content of a file as the secret, binary files are supported
+pub fn read_secret_file(path: &Path) -> Result<Secret> {
+    let content =
+        fs::read(path).with_context(|| format!("Error reading secret file: {}", path.display()))?;
+
+    if content.is_empty() {
+        bail!("The secret file is empty: {}", path.display());
+    }
+
+    Ok(Secret::File {
+        file_name: path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string()),
+        content_type: mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string(),
+        content,
+    })
+}
+
+fn read_from_prompt() -> Result<String> {
+    let secret = rpassword::prompt_password("Enter secret: ")
+        .with_context(|| "Error reading secret from terminal")?;
+    let confirmation = rpassword::prompt_password("Confirm secret: ")
+        .with_context(|| "Error reading secret from terminal")?;
+
+    if secret != confirmation {
+        bail!("Secrets do not match");
+    }
+
+    Ok(secret)
+}
+
+/// Reads a passphrase from a hidden terminal prompt, an empty passphrase is allowed
+pub fn read_passphrase(name: &str, confirm: bool) -> Result<String> {
+    let passphrase = rpassword::prompt_password(format!("Enter {}: ", name))
+        .with_context(|| format!("Error reading {} from terminal", name))?;
+
+    if confirm {
+        let confirmation = rpassword::prompt_password(format!("Confirm {}: ", name))
+            .with_context(|| format!("Error reading {} from terminal", name))?;
+        if passphrase != confirmation {
+            bail!("Passphrases do not match");
+        }
+    }
+
+    Ok(passphrase)
+}
+
+fn read_from_stdin() -> Result<String> {
+    let mut secret = String::new();
+    io::stdin()
+        .read_to_string(&mut secret)
+        .with_context(|| "Error reading secret from stdin")?;
+
+    Ok(trim_line_ending(secret))
+}
+
+/// Reads the secret from a descriptor opened by the caller, e.g. `3<secret.txt`.
+/// The standard streams are rejected, the descriptor is left open
+#[cfg(unix)]
+fn read_from_fd(fd: i32) -> Result<String> {
+    use std::fs::File;
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::FromRawFd;
+
+    if fd < 3 {
+        bail!(
+            "Invalid file descriptor {}: 0, 1 and 2 are stdin, stdout and stderr. \
+             Pipe the secret into stdin without --secret-fd, or pass it on a descriptor from 3 up",
+            fd
+        );
+    }
+
+    // Safety: the descriptor is only borrowed. It is never closed, so a descriptor the process
+    // already uses stays valid, and the file doesn't outlive this function.
+    // A descriptor that isn't open makes the read fail with EBADF
+    let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    let mut secret = String::new();
+    file.read_to_string(&mut secret)
+        .with_context(|| format!("Error reading secret from file descriptor {}", fd))?;
+
+    Ok(trim_line_ending(secret))
+}
+
+#[cfg(not(unix))]
+fn read_from_fd(_fd: i32) -> Result<String> {
+    bail!("--secret-fd is only supported on unix systems")
+}
+
+/// `echo secret | meta-secret-cli split` appends a newline which is not a part of the secret
+fn trim_line_ending(mut secret: String) -> String {
+    if secret.ends_with('\n') {
+        secret.pop();
+        if secret.ends_with('\r') {
+            secret.pop();
+        }
+    }
+    secret
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::{Seek, SeekFrom, Write};
+    use std::os::unix::io::AsRawFd;
+
+    use super::*;
+
+    #[test]
+    fn standard_streams_are_rejected() {
+        for fd in [-1, 0, 1, 2] {
+            assert!(read_from_fd(fd).is_err());
+        }
+    }
+
+    #[test]
+    fn descriptor_is_left_open() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"correct horse\n").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+
+        assert_eq!(read_from_fd(file.as_raw_fd()).unwrap(), "correct horse");
+
+        // the descriptor is still ours: closing it would make this fail with EBADF
+        file.write_all(b"battery staple").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "correct horse\nbattery staple");
+    }
+}
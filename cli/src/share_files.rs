@@ -0,0 +1,1080 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use clap::Args;
+use meta_secret_core::errors::CoreError;
+use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+use meta_secret_core::shared_secret::shared_secret::{
+    PlainText, SharedSecretEncryption, UserShareDto,
+};
+use meta_secret_share_formats::qr_parts::{self, Part, PartsError};
+use meta_secret_share_formats::vss::{self, VssError};
+use meta_secret_share_formats::words::{self, WordsError};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::policy::{PolicyError, PolicyShare, SetPolicy};
+use crate::protection::{self, EncryptedShareFile};
+use crate::qr::{self, QrError};
+use crate::recipients::{self, Identities, Recipient, RecipientError};
+use crate::secret_input::Secret;
+use crate::text_code::{self, TextCodeError};
+use crate::verify::{self, Commitment};
+
+pub const DEFAULT_SHARES_DIR: &str = "secrets";
+pub const WORDS_EXTENSION: &str = "words";
+/// Version of the share file format, see [ShareFile]
+pub const SHARE_FILE_VERSION: u32 = 1;
+
+/// A share as it is stored in json files and qr codes: the core share plus non-secret metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareFile {
+    pub version: u32,
+    /// Missing in the shares made by the previous versions of the cli
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set: Option<ShareSetInfo>,
+    pub secret: SecretMetadata,
+    pub share: ShareData,
+    /// Used by `verify`, missing in the shares made by the previous versions of the cli
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<Commitment>,
+}
+
+/// The share itself: a share of the core, a share of the verifiable sharing (`split --verifiable`)
+/// or the share of a holder of a policy (`split --policy`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ShareData {
+    Core(UserShareDto),
+    Verifiable(VerifiableShare),
+    Policy(PolicyShare),
+}
+
+/// A share of the verifiable sharing and the commitments of the set, a holder can check the share alone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerifiableShare {
+    /// Base64, see [vss::Share]
+    pub vss_share: String,
+    /// Base64, the same for all the shares of the set, see [vss::Commitments]
+    pub commitments: String,
+}
+
+impl ShareData {
+    /// Fingerprint of the commitments of a verifiable share, the same for all the shares of the set
+    pub fn commitments_fingerprint(&self) -> Option<String> {
+        match self {
+            ShareData::Core(_) | ShareData::Policy(_) => None,
+            ShareData::Verifiable(share) => vss::Commitments::decode(&share.commitments)
+                .ok()
+                .map(|commitments| commitments.fingerprint()),
+        }
+    }
+}
+
+impl VerifiableShare {
+    pub fn decode(&self) -> Result<(vss::Share, vss::Commitments), VssError> {
+        Ok((
+            vss::Share::decode(&self.vss_share)?,
+            vss::Commitments::decode(&self.commitments)?,
+        ))
+    }
+}
+
+/// Share files written by the previous versions of the cli contain just the core share
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VersionedShareFile {
+    ShareFile(Box<ShareFile>),
+    Encrypted(Box<EncryptedShareFile>),
+    Legacy(UserShareDto),
+}
+
+/// Content of a share file: a share or a share encrypted with the passphrase of its holder
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum StoredShare {
+    Plain(ShareFile),
+    Encrypted(EncryptedShareFile),
+}
+
+impl From<VersionedShareFile> for StoredShare {
+    fn from(share_file: VersionedShareFile) -> Self {
+        match share_file {
+            VersionedShareFile::ShareFile(share_file) => StoredShare::Plain(*share_file),
+            VersionedShareFile::Encrypted(share_file) => StoredShare::Encrypted(*share_file),
+            VersionedShareFile::Legacy(share) => StoredShare::Plain(ShareFile {
+                version: 0,
+                set: None,
+                secret: SecretMetadata::default(),
+                share: ShareData::Core(share),
+                commitment: None,
+            }),
+        }
+    }
+}
+
+impl StoredShare {
+    pub fn version(&self) -> u32 {
+        match self {
+            StoredShare::Plain(share_file) => share_file.version,
+            StoredShare::Encrypted(share_file) => share_file.version,
+        }
+    }
+
+    pub fn set(&self) -> Option<&ShareSetInfo> {
+        match self {
+            StoredShare::Plain(share_file) => share_file.set.as_ref(),
+            StoredShare::Encrypted(share_file) => share_file.set.as_ref(),
+        }
+    }
+
+    /// Unknown until an encrypted share is decrypted
+    pub fn secret(&self) -> Option<&SecretMetadata> {
+        match self {
+            StoredShare::Plain(share_file) => Some(&share_file.secret),
+            StoredShare::Encrypted(_) => None,
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self, StoredShare::Encrypted(_))
+    }
+
+    /// The share itself. An encrypted share is decrypted with the given passphrase,
+    /// or with the one asked for in a terminal prompt
+    pub fn open(self, path: &Path, passphrase: Option<&str>) -> Result<ShareFile, ShareFileError> {
+        match (self, passphrase) {
+            (StoredShare::Plain(share_file), _) => Ok(share_file),
+            (StoredShare::Encrypted(share_file), Some(passphrase)) => {
+                protection::decrypt(path, &share_file, passphrase)
+            }
+            (StoredShare::Encrypted(share_file), None) => protection::unlock(path, &share_file),
+        }
+    }
+}
+
+/// Place of the share in the set of shares made by one split
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareSetInfo {
+    /// Random id, the same for all the shares of one split
+    pub set_id: String,
+    pub index: usize,
+    pub threshold: usize,
+    pub number_of_shares: usize,
+    /// Id of the set this one replaces (`reshare`): the shares of the old set should be destroyed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<String>,
+    /// The policy of `split --policy`, share `index` belongs to its `index`-th holder.
+    /// `threshold` is then the smallest number of holders that can meet the policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<SetPolicy>,
+}
+
+/// Describes what the shares are made of. Stored in every share, it doesn't reveal the secret itself
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretMetadata {
+    pub encoding: SecretEncoding,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Hybrid mode: name of the file encrypted with the data key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_file: Option<String>,
+    /// The password manager entry the secret was imported from, see `import-passwords`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<ImportedEntry>,
+}
+
+/// Title and url of a password manager entry: they name the secret, the password itself is split
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedEntry {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// How the secret is turned into the text that gets split
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretEncoding {
+    /// The secret is a utf8 text, split as is
+    #[default]
+    Text,
+    /// Arbitrary binary data, split as a base64 string
+    Base64,
+    /// Hybrid mode: the secret is a file encrypted with a data key, the shares contain the base64 encoded key
+    DataKey,
+}
+
+/// How the secret is split into shares
+#[derive(Debug, Clone)]
+pub enum Sharing {
+    Core(SharedSecretConfig),
+    /// `split --verifiable`
+    Verifiable(SharedSecretConfig),
+    /// `split --policy`: one share per holder of the policy
+    Policy(SetPolicy),
+}
+
+impl Sharing {
+    pub fn new(config: SharedSecretConfig, verifiable: bool) -> Self {
+        if verifiable {
+            Sharing::Verifiable(config)
+        } else {
+            Sharing::Core(config)
+        }
+    }
+
+    pub fn number_of_shares(&self) -> usize {
+        match self {
+            Sharing::Core(config) | Sharing::Verifiable(config) => config.number_of_shares,
+            Sharing::Policy(policy) => policy.root.holders().len(),
+        }
+    }
+
+    /// For a policy: the smallest number of holders that can meet it
+    pub fn threshold(&self) -> usize {
+        match self {
+            Sharing::Core(config) | Sharing::Verifiable(config) => config.threshold,
+            Sharing::Policy(policy) => policy.root.min_holders(),
+        }
+    }
+
+    pub fn policy(&self) -> Option<&SetPolicy> {
+        match self {
+            Sharing::Core(_) | Sharing::Verifiable(_) => None,
+            Sharing::Policy(policy) => Some(policy),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SplitOutputArgs {
+    /// Directory to write the shares into
+    #[clap(short, long, default_value = DEFAULT_SHARES_DIR)]
+    out_dir: PathBuf,
+
+    /// Write every share into its own directory, e.g. onto separate mounted USB drives.
+    /// Has to be repeated once per share: --share-dir /media/usb1 --share-dir /media/usb2 ...
+    #[clap(long = "share-dir", value_name = "DIR")]
+    share_dirs: Vec<PathBuf>,
+
+    /// Encrypt every share with its own passphrase, asked for every share.
+    /// The holder of the share needs the passphrase to restore the secret
+    #[clap(long)]
+    share_passphrases: bool,
+
+    /// Encrypt every share to the public key of its holder and write it into `<name>.age`.
+    /// A name of the `recipients` section of the config or `<NAME>=<PUBLIC KEY>` (age or SSH key).
+    /// Has to be repeated once per share: --recipient alice --recipient bob ...
+    #[clap(long = "recipient", value_name = "RECIPIENT")]
+    recipients: Vec<String>,
+
+    /// Verifiable sharing: every share carries the commitments of the set,
+    /// so a holder can check their share alone with the `check` command
+    #[clap(long)]
+    verifiable: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreInputArgs {
+    /// Directory to read the shares from
+    #[clap(long, default_value = DEFAULT_SHARES_DIR)]
+    shares_dir: PathBuf,
+
+    /// Share files (json, qr code images or pdfs, text codes or word lists) to restore from, instead of scanning --shares-dir
+    #[clap(value_name = "FILE")]
+    files: Vec<PathBuf>,
+
+    /// Identity to decrypt the shares encrypted to recipients (.age files) with:
+    /// an age identity file or an SSH private key. Can be repeated
+    #[clap(long = "identity", value_name = "FILE")]
+    identities: Vec<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShareFileError {
+    #[error("Expected {expected} --share-dir destinations (one per share), got {actual}")]
+    ShareDirsMismatch { expected: usize, actual: usize },
+    #[error("Expected {expected} --recipient holders (one per share), got {actual}")]
+    RecipientsMismatch { expected: usize, actual: usize },
+    #[error("Two recipients get the same share file: {path}")]
+    DuplicateRecipientFile { path: PathBuf },
+    #[error("The new shares would overwrite the old ones in: {dir}. Use --out-dir")]
+    SameDirReshare { dir: PathBuf },
+    #[error("Error writing share file: {path}")]
+    WriteError { path: PathBuf, source: io::Error },
+    #[error("Error reading share file: {path}")]
+    ReadError { path: PathBuf, source: io::Error },
+    #[error("Read-back verification failed for: {path}. The file on disk differs from the written share")]
+    VerificationError { path: PathBuf },
+    #[error("Invalid share file: {path}")]
+    InvalidShare {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("Invalid text code: {path}")]
+    InvalidTextCode {
+        path: PathBuf,
+        source: TextCodeError,
+    },
+    #[error("Invalid word list: {path}")]
+    InvalidWords { path: PathBuf, source: WordsError },
+    #[error("Only secrets split as text can be exported as word lists: the file name and the encoding of the secret don't fit into the words. {path}")]
+    NotATextSecret { path: PathBuf },
+    #[error("Verifiable shares are too long for word lists: {path}")]
+    VerifiableWords { path: PathBuf },
+    #[error("Shares of a policy can't be restored without the policy of their set, it doesn't fit into word lists: {path}")]
+    PolicyWords { path: PathBuf },
+    #[error("Wrong passphrase, or the share file is corrupted: {path}")]
+    WrongPassphrase { path: PathBuf },
+    #[error("Error reading the passphrase of: {path}")]
+    PassphraseError { path: PathBuf, source: io::Error },
+    #[error("Invalid encryption settings of the share file: {path}")]
+    InvalidEnvelope { path: PathBuf },
+    #[error("The key derivation settings of the share file ask for more memory or time than split ever uses: {path}")]
+    KdfTooExpensive { path: PathBuf },
+    #[error("Error reading shares directory: {dir}")]
+    ReadDirError { dir: PathBuf, source: io::Error },
+    #[error("No share files found in: {dir}")]
+    NoShares { dir: PathBuf },
+    #[error(transparent)]
+    QrError(#[from] QrError),
+    #[error("Invalid qr code part: {path}")]
+    InvalidQrPart { path: PathBuf, source: PartsError },
+    #[error(
+        "Error putting the qr code parts together: {}",
+        qr::display_locations(files)
+    )]
+    QrParts {
+        files: Vec<PathBuf>,
+        source: PartsError,
+    },
+    #[error("The qr code is one part of a share written as several qr codes, restore it together with the other parts: {path}")]
+    QrPartAlone { path: PathBuf },
+    #[error(transparent)]
+    RecipientError(#[from] RecipientError),
+    #[error("File already exists: {path}")]
+    AlreadyExists { path: PathBuf },
+    #[error("The directory already holds share files: {dir}. Shares are never overwritten, write the new ones into another directory")]
+    SharesExist { dir: PathBuf },
+    #[error(transparent)]
+    SplitError(#[from] CoreError),
+    #[error(transparent)]
+    VssError(#[from] VssError),
+    #[error(transparent)]
+    PolicyError(#[from] PolicyError),
+}
+
+/// Shares read from the files, and where no qr code could be read
+#[derive(Default)]
+pub struct ReadShares {
+    pub shares: Vec<(PathBuf, ShareFile)>,
+    /// Images and pdf pages without a readable qr code
+    pub unreadable: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordsFile {
+    pub file: PathBuf,
+    pub words: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WrittenShare {
+    /// Missing for the shares encrypted to recipients, they are written only as age files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_file: Option<PathBuf>,
+    /// Missing for the shares too big for one qr code, they are written as several parts
+    pub qr_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub qr_parts: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sheet_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_file: Option<PathBuf>,
+}
+
+/// Splits the secret with the core, with the verifiable sharing or among the holders of a policy
+pub fn split_secret(secret: &Secret, sharing: &Sharing) -> Result<Vec<ShareFile>, ShareFileError> {
+    let (text, metadata) = encode_secret(secret);
+
+    let shares: Vec<ShareData> = match sharing {
+        Sharing::Verifiable(config) => {
+            let (vss_shares, commitments) =
+                vss::split(text.as_bytes(), config.threshold, config.number_of_shares)?;
+            let commitments = commitments.encode();
+            vss_shares
+                .iter()
+                .map(|vss_share| {
+                    ShareData::Verifiable(VerifiableShare {
+                        vss_share: vss_share.encode(),
+                        commitments: commitments.clone(),
+                    })
+                })
+                .collect()
+        }
+        Sharing::Core(config) => {
+            let plain_text = PlainText::from(text.as_str());
+            let shared_secret = SharedSecretEncryption::new(*config, &plain_text)?;
+            (0..config.number_of_shares)
+                .map(|share_index| ShareData::Core(shared_secret.get_share(share_index)))
+                .collect()
+        }
+        Sharing::Policy(policy) => policy
+            .root
+            .split(&text)?
+            .into_iter()
+            .map(ShareData::Policy)
+            .collect(),
+    };
+    let commitments = verify::commit(secret, &shares, sharing)?;
+
+    let set_id = Uuid::new_v4().to_string();
+
+    let share_files = shares
+        .into_iter()
+        .zip(commitments)
+        .enumerate()
+        .map(|(share_index, (share, commitment))| ShareFile {
+            version: SHARE_FILE_VERSION,
+            set: Some(ShareSetInfo {
+                set_id: set_id.clone(),
+                index: share_index,
+                threshold: sharing.threshold(),
+                number_of_shares: sharing.number_of_shares(),
+                supersedes: None,
+                policy: sharing.policy().cloned(),
+            }),
+            secret: metadata.clone(),
+            share,
+            commitment: Some(commitment),
+        })
+        .collect();
+
+    Ok(share_files)
+}
+
+fn encode_secret(secret: &Secret) -> (String, SecretMetadata) {
+    match secret {
+        Secret::Text(text) => (text.clone(), SecretMetadata::default()),
+        Secret::File {
+            file_name,
+            content_type,
+            content,
+        } => {
+            let metadata = SecretMetadata {
+                encoding: SecretEncoding::Base64,
+                file_name: file_name.clone(),
+                content_type: Some(content_type.clone()),
+                encrypted_file: None,
+                entry: None,
+            };
+            (BASE64.encode(content), metadata)
+        }
+        Secret::DataKey {
+            key,
+            encrypted_file,
+            file_name,
+            content_type,
+        } => {
+            let metadata = SecretMetadata {
+                encoding: SecretEncoding::DataKey,
+                file_name: file_name.clone(),
+                content_type: Some(content_type.clone()),
+                encrypted_file: Some(encrypted_file.clone()),
+                entry: None,
+            };
+            (BASE64.encode(key), metadata)
+        }
+    }
+}
+
+/// Turns the recovered text back into the original secret
+pub fn decode_secret(
+    text: Vec<u8>,
+    metadata: &SecretMetadata,
+) -> Result<Vec<u8>, base64::DecodeError> {
+    match metadata.encoding {
+        SecretEncoding::Text => Ok(text),
+        SecretEncoding::Base64 | SecretEncoding::DataKey => BASE64.decode(text),
+    }
+}
+
+/// Writes the restored secret into a new file, readable only by the owner
+pub fn write_secret_file(path: &Path, content: &[u8]) -> Result<(), ShareFileError> {
+    write_new_file(path, content)
+}
+
+/// Writes a new file readable only by the owner, an existing file is never overwritten
+pub fn write_new_file(path: &Path, content: &[u8]) -> Result<(), ShareFileError> {
+    let mut file = create_private_file(path).map_err(|source| match source.kind() {
+        io::ErrorKind::AlreadyExists => ShareFileError::AlreadyExists {
+            path: path.to_path_buf(),
+        },
+        _ => ShareFileError::WriteError {
+            path: path.to_path_buf(),
+            source,
+        },
+    })?;
+
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .map_err(|source| ShareFileError::WriteError {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Creates a new file with 0600 permissions, fails if the file already exists
+pub fn create_private_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)
+}
+
+impl SplitOutputArgs {
+    pub fn out_dir(&self) -> &Path {
+        &self.out_dir
+    }
+
+    pub fn share_passphrases(&self) -> bool {
+        self.share_passphrases
+    }
+
+    pub fn verifiable(&self) -> bool {
+        self.verifiable
+    }
+
+    /// Names or `<name>=<public key>` of the --recipient holders, resolved with the config
+    pub fn recipients(&self) -> &[String] {
+        &self.recipients
+    }
+
+    /// The same output, with every share written into its own directory
+    pub fn with_share_dirs(&self, share_dirs: Vec<PathBuf>) -> SplitOutputArgs {
+        SplitOutputArgs {
+            out_dir: self.out_dir.clone(),
+            share_dirs,
+            share_passphrases: self.share_passphrases,
+            recipients: self.recipients.clone(),
+            verifiable: self.verifiable,
+        }
+    }
+
+    /// Directories the shares are written into
+    pub fn target_dirs(&self, number_of_shares: usize) -> Vec<&Path> {
+        (0..number_of_shares)
+            .map(|share_index| self.share_dir(share_index))
+            .collect()
+    }
+
+    fn share_dir(&self, share_index: usize) -> &Path {
+        self.share_dirs
+            .get(share_index)
+            .unwrap_or(&self.out_dir)
+            .as_path()
+    }
+
+    /// A new set never goes into a directory with share files: an old set would be overwritten,
+    /// or its shares with higher indices would be restored together with the new ones
+    pub fn check_target_dirs(&self, number_of_shares: usize) -> Result<(), ShareFileError> {
+        for dir in self.target_dirs(number_of_shares) {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(source) if source.kind() == io::ErrorKind::NotFound => continue,
+                Err(source) => {
+                    return Err(ShareFileError::ReadDirError {
+                        dir: dir.to_path_buf(),
+                        source,
+                    })
+                }
+            };
+
+            let has_shares = entries
+                .filter_map(|entry| entry.ok())
+                .any(|entry| entry.path().is_file() && is_share_file(&entry.path()));
+            if has_shares {
+                return Err(ShareFileError::SharesExist {
+                    dir: dir.to_path_buf(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes every share as json and as a qr code, then reads the files back to make sure they were stored correctly
+    pub fn write_shares(
+        &self,
+        shares: &[StoredShare],
+    ) -> Result<Vec<WrittenShare>, ShareFileError> {
+        if !self.share_dirs.is_empty() && self.share_dirs.len() != shares.len() {
+            return Err(ShareFileError::ShareDirsMismatch {
+                expected: shares.len(),
+                actual: self.share_dirs.len(),
+            });
+        }
+        self.check_target_dirs(shares.len())?;
+
+        let mut written_shares = vec![];
+        for (share_index, share) in shares.iter().enumerate() {
+            let share_dir = self.create_share_dir(share_index)?;
+
+            // a share is a plain struct of numbers and strings, serialization can't fail
+            let share_json = serde_json::to_string_pretty(share).expect("Error serializing share");
+
+            let json_file = share_dir.join(format!("shared-secret-{}.json", share_index));
+            write_and_verify(&json_file, share_json.as_bytes())?;
+
+            // the smaller the json, the smaller the qr code
+            let compact_json = serde_json::to_string(share).expect("Error serializing share");
+            let set_id = share
+                .set()
+                .map(|set| set.set_id.as_str())
+                .unwrap_or_default();
+            let qr_codes = qr::generate_qr_codes(&compact_json, set_id)?;
+
+            let mut qr_file = None;
+            let mut qr_parts = vec![];
+            if let [qr_code] = &qr_codes[..] {
+                let path = share_dir.join(format!("shared-secret-{}.png", share_index));
+                write_and_verify(&path, qr_code)?;
+                qr_file = Some(path);
+            } else {
+                for (part, qr_code) in qr_codes.iter().enumerate() {
+                    let path = share_dir.join(format!(
+                        "shared-secret-{}.part-{}-of-{}.png",
+                        share_index,
+                        part + 1,
+                        qr_codes.len()
+                    ));
+                    write_and_verify(&path, qr_code)?;
+                    qr_parts.push(path);
+                }
+            }
+
+            written_shares.push(WrittenShare {
+                json_file: Some(json_file),
+                qr_file,
+                qr_parts,
+                sheet_file: None,
+                recipient: None,
+                age_file: None,
+            });
+        }
+
+        Ok(written_shares)
+    }
+
+    /// Writes every share encrypted to its recipient into `<name>.age`, nothing is written unencrypted.
+    /// The files are read back to make sure they were stored correctly
+    pub fn write_recipient_shares(
+        &self,
+        shares: &[StoredShare],
+        recipients: &[Recipient],
+    ) -> Result<Vec<WrittenShare>, ShareFileError> {
+        if !self.share_dirs.is_empty() && self.share_dirs.len() != shares.len() {
+            return Err(ShareFileError::ShareDirsMismatch {
+                expected: shares.len(),
+                actual: self.share_dirs.len(),
+            });
+        }
+
+        let age_files: Vec<PathBuf> = recipients
+            .iter()
+            .enumerate()
+            .map(|(share_index, recipient)| {
+                self.share_dir(share_index)
+                    .join(recipients::file_name(recipient))
+            })
+            .collect();
+        let mut unique_files = HashSet::new();
+        if let Some(path) = age_files.iter().find(|path| !unique_files.insert(*path)) {
+            return Err(ShareFileError::DuplicateRecipientFile { path: path.clone() });
+        }
+        self.check_target_dirs(shares.len())?;
+
+        let mut written_shares = vec![];
+        for (share_index, ((share, recipient), age_file)) in
+            shares.iter().zip(recipients).zip(age_files).enumerate()
+        {
+            self.create_share_dir(share_index)?;
+
+            // a share is a plain struct of numbers and strings, serialization can't fail
+            let share_json = serde_json::to_string_pretty(share).expect("Error serializing share");
+            let encrypted_share = recipients::encrypt(recipient, share_json.as_bytes())?;
+            write_and_verify(&age_file, &encrypted_share)?;
+
+            written_shares.push(WrittenShare {
+                json_file: None,
+                qr_file: None,
+                qr_parts: vec![],
+                sheet_file: None,
+                recipient: Some(recipient.name.clone()),
+                age_file: Some(age_file),
+            });
+        }
+
+        Ok(written_shares)
+    }
+
+    fn create_share_dir(&self, share_index: usize) -> Result<&Path, ShareFileError> {
+        let share_dir = self.share_dir(share_index);
+        fs::create_dir_all(share_dir).map_err(|source| ShareFileError::WriteError {
+            path: share_dir.to_path_buf(),
+            source,
+        })?;
+
+        Ok(share_dir)
+    }
+}
+
+/// The file is new and readable only by the owner, like the restored secret
+fn write_and_verify(path: &Path, content: &[u8]) -> Result<(), ShareFileError> {
+    let write_error = |source| ShareFileError::WriteError {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let mut file = create_private_file(path).map_err(|source| match source.kind() {
+        io::ErrorKind::AlreadyExists => ShareFileError::AlreadyExists {
+            path: path.to_path_buf(),
+        },
+        _ => write_error(source),
+    })?;
+    file.write_all(content).map_err(write_error)?;
+    // make sure the data reached the device (removable drives) before reading it back
+    file.sync_all().map_err(write_error)?;
+
+    let written_content = fs::read(path).map_err(|source| ShareFileError::ReadError {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    if written_content != content {
+        return Err(ShareFileError::VerificationError {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(())
+}
+
+impl RestoreInputArgs {
+    /// Reads the shares paired with the files they come from, decrypting the shares encrypted to recipients
+    /// with the --identity files. Fails if there is an image or a pdf page without a readable qr code
+    pub fn read_share_files(
+        &self,
+        paths: &[PathBuf],
+    ) -> Result<Vec<(PathBuf, ShareFile)>, ShareFileError> {
+        let read_shares = self.read_shares(paths)?;
+        if !read_shares.unreadable.is_empty() {
+            return Err(QrError::NotFound {
+                locations: read_shares.unreadable,
+            }
+            .into());
+        }
+
+        Ok(read_shares.shares)
+    }
+
+    /// Reads the shares like `read_share_files`, but the images and pdf pages without a readable qr code
+    /// are returned instead of failing, for the other shares to be restored from
+    pub fn read_shares(&self, paths: &[PathBuf]) -> Result<ReadShares, ShareFileError> {
+        let identities = if paths.iter().any(|path| recipients::is_age_file(path)) {
+            recipients::load_identities(&self.identities)?
+        } else {
+            Identities::default()
+        };
+
+        let mut read_shares = ReadShares::default();
+        // the parts of the shares written as several qr codes, put together once all the files are read
+        let mut parts = vec![];
+        for path in paths {
+            // an image or a pdf can hold several shares, each one is named by where it was found
+            if qr::is_qr_source(path) {
+                let scan = qr::scan(path)?;
+                for code in scan.codes {
+                    if qr_parts::is_part(&code.content) {
+                        let part = qr_parts::parse(&code.content).map_err(|source| {
+                            ShareFileError::InvalidQrPart {
+                                path: code.location.clone(),
+                                source,
+                            }
+                        })?;
+                        parts.push((code.location, part));
+                        continue;
+                    }
+
+                    let share_file =
+                        parse_share(&code.location, &code.content)?.open(&code.location, None)?;
+                    read_shares.shares.push((code.location, share_file));
+                }
+                read_shares.unreadable.extend(scan.unreadable);
+                continue;
+            }
+
+            let share_json = read_share_json_with(path, &identities)?;
+            let share_file = parse_share(path, &share_json)?.open(path, None)?;
+            read_shares.shares.push((path.clone(), share_file));
+        }
+
+        for (path, share_json) in join_qr_parts(&parts)? {
+            let share_file = parse_share(&path, &share_json)?.open(&path, None)?;
+            read_shares.shares.push((path, share_file));
+        }
+
+        Ok(read_shares)
+    }
+
+    /// Share files to restore from: the explicitly listed ones or the files of --shares-dir with the given extension
+    pub fn share_files(&self, matches: fn(&Path) -> bool) -> Result<Vec<PathBuf>, ShareFileError> {
+        if !self.files.is_empty() {
+            return Ok(self.files.clone());
+        }
+
+        let files = list_files(&self.shares_dir, matches)?;
+        if files.is_empty() {
+            return Err(ShareFileError::NoShares {
+                dir: self.shares_dir.clone(),
+            });
+        }
+
+        Ok(files)
+    }
+}
+
+/// Puts the shares written as several qr codes back together, every share is named by the file of its first part
+fn join_qr_parts(parts: &[(PathBuf, Part)]) -> Result<Vec<(PathBuf, String)>, ShareFileError> {
+    if parts.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let joined_shares = qr_parts::join(
+        &parts
+            .iter()
+            .map(|(_, part)| part.clone())
+            .collect::<Vec<_>>(),
+    )
+    .map_err(|source| ShareFileError::QrParts {
+        files: parts.iter().map(|(path, _)| path.clone()).collect(),
+        source,
+    })?;
+
+    Ok(joined_shares
+        .into_iter()
+        .map(|joined_share| {
+            let first_part = parts.iter().find(|(_, part)| {
+                part.part == 1
+                    && part.set_id == joined_share.set_id
+                    && part.checksum == joined_share.checksum
+            });
+            // a joined share has all its parts, the first one included
+            let path = first_part.map(|(path, _)| path.clone()).unwrap_or_default();
+            (path, joined_share.data)
+        })
+        .collect())
+}
+
+/// Files of the directory (not recursive) accepted by `matches`, sorted by name
+pub fn list_files(dir: &Path, matches: fn(&Path) -> bool) -> Result<Vec<PathBuf>, ShareFileError> {
+    let read_dir_error = |source| ShareFileError::ReadDirError {
+        dir: dir.to_path_buf(),
+        source,
+    };
+
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).map_err(read_dir_error)? {
+        let path = entry.map_err(read_dir_error)?.path();
+        if path.is_file() && matches(&path) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+pub fn is_json_file(path: &Path) -> bool {
+    path.extension().map(|ext| ext == "json").unwrap_or(false)
+}
+
+/// Text codes of the shares typed in from the paper sheets
+pub fn is_text_code_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext == text_code::TEXT_CODE_EXTENSION)
+        .unwrap_or(false)
+}
+
+/// Word lists of the shares
+pub fn is_words_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext == WORDS_EXTENSION)
+        .unwrap_or(false)
+}
+
+/// Any file a share can be read from: json, qr code image, text code, word list or a share encrypted to a recipient
+pub fn is_share_file(path: &Path) -> bool {
+    is_json_file(path)
+        || qr::is_qr_image(path)
+        || is_text_code_file(path)
+        || is_words_file(path)
+        || recipients::is_age_file(path)
+}
+
+/// Writes the core share of the file as a word list into `<name>.words`, next to the file or into `out_dir`.
+/// The set information and the commitment are not included, to keep the list short
+pub fn export_words(path: &Path, out_dir: Option<&Path>) -> Result<WordsFile, ShareFileError> {
+    let share_file = read_share_file(path)?;
+    if share_file.secret.encoding != SecretEncoding::Text {
+        return Err(ShareFileError::NotATextSecret {
+            path: path.to_path_buf(),
+        });
+    }
+    match share_file.share {
+        ShareData::Core(_) => {}
+        ShareData::Verifiable(_) => {
+            return Err(ShareFileError::VerifiableWords {
+                path: path.to_path_buf(),
+            })
+        }
+        ShareData::Policy(_) => {
+            return Err(ShareFileError::PolicyWords {
+                path: path.to_path_buf(),
+            })
+        }
+    }
+
+    // a share is a plain struct of numbers and strings, serialization can't fail
+    let share_json = serde_json::to_vec(&share_file.share).expect("Error serializing share");
+    let words = words::encode(&share_json).map_err(|source| ShareFileError::InvalidWords {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let words_file = path.with_extension(WORDS_EXTENSION);
+    let words_file = match (out_dir, words_file.file_name()) {
+        (Some(out_dir), Some(file_name)) => out_dir.join(file_name),
+        _ => words_file,
+    };
+
+    create_private_file(&words_file)
+        .and_then(|mut file| file.write_all(format!("{}\n", words).as_bytes()))
+        .map_err(|source| match source.kind() {
+            io::ErrorKind::AlreadyExists => ShareFileError::AlreadyExists {
+                path: words_file.clone(),
+            },
+            _ => ShareFileError::WriteError {
+                path: words_file.clone(),
+                source,
+            },
+        })?;
+
+    Ok(WordsFile {
+        file: words_file,
+        words,
+    })
+}
+
+/// Reads a share from a json file or from a qr code image or pdf (the first qr code).
+/// The passphrase of an encrypted share is asked for in a terminal prompt
+pub fn read_share_file(path: &Path) -> Result<ShareFile, ShareFileError> {
+    read_stored_share(path)?.open(path, None)
+}
+
+/// Reads a share as it is stored, an encrypted share stays encrypted
+pub fn read_stored_share(path: &Path) -> Result<StoredShare, ShareFileError> {
+    let share_json = read_share_json(path)?;
+    parse_share(path, &share_json)
+}
+
+/// Raw json of the share, exactly as it is stored in the file, in the qr code or in the text code
+pub fn read_share_json(path: &Path) -> Result<String, ShareFileError> {
+    read_share_json_with(path, &Identities::default())
+}
+
+/// Raw json of the share, a share encrypted to a recipient is decrypted with the identities
+fn read_share_json_with(path: &Path, identities: &Identities) -> Result<String, ShareFileError> {
+    if qr::is_qr_source(path) {
+        let content = qr::read_qr_code(path)?;
+        if qr_parts::is_part(&content) {
+            return Err(ShareFileError::QrPartAlone {
+                path: path.to_path_buf(),
+            });
+        }
+        return Ok(content);
+    }
+
+    if recipients::is_age_file(path) {
+        let encrypted_share = fs::read(path).map_err(|source| ShareFileError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let share_json = recipients::decrypt(path, &encrypted_share, identities)?;
+        return Ok(String::from_utf8_lossy(&share_json).to_string());
+    }
+
+    let content = fs::read_to_string(path).map_err(|source| ShareFileError::ReadError {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    if is_text_code_file(path) {
+        let share_json =
+            text_code::decode(&content).map_err(|source| ShareFileError::InvalidTextCode {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        // a broken utf8 sequence fails the json parsing later on
+        return Ok(String::from_utf8_lossy(&share_json).to_string());
+    }
+
+    if is_words_file(path) {
+        let share_json =
+            words::decode(&content).map_err(|source| ShareFileError::InvalidWords {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        return Ok(String::from_utf8_lossy(&share_json).to_string());
+    }
+
+    Ok(content)
+}
+
+/// Reads the share to be shown as a qr code or printed, an encrypted share stays encrypted.
+/// Returns the share and its json without whitespaces: the smaller the json, the smaller the qr code
+pub fn read_compact_share(path: &Path) -> Result<(StoredShare, String), ShareFileError> {
+    let share_json = read_share_json(path)?;
+    let share_file = parse_share(path, &share_json)?;
+
+    let compact_json = serde_json::from_str::<serde_json::Value>(&share_json)
+        .map(|value| value.to_string())
+        .unwrap_or(share_json);
+
+    Ok((share_file, compact_json))
+}
+
+pub fn parse_share(path: &Path, share_json: &str) -> Result<StoredShare, ShareFileError> {
+    let share: VersionedShareFile =
+        serde_json::from_str(share_json).map_err(|source| ShareFileError::InvalidShare {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    Ok(StoredShare::from(share))
+}
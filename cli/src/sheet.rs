@@ -0,0 +1,506 @@
+//! Printable paper backup sheets, one per share: the qr code, the text code to type the share in by hand
+//! when the qr code can't be scanned, what the share belongs to and how to recover the secret.
+//!
+//! The page is laid out once as a list of elements (text lines and qr code modules)
+//! and then rendered into pdf or svg.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ArgEnum;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use qrcode::{Color, QrCode};
+
+use crate::share_files::{self, SecretEncoding, StoredShare};
+use crate::text_code;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const QR_CODE_SIZE: f32 = 200.0;
+const QR_QUIET_ZONE: usize = 4;
+/// Approximate width of a Helvetica character relative to the font size, used to wrap the text
+const CHARACTER_WIDTH: f32 = 0.5;
+
+const PROJECT_URL: &str = "https://github.com/meta-secret/meta-secret-node";
+const WEB_APP_URL: &str = "https://meta-secret.github.io";
+
+#[derive(Debug, Clone, Copy, ArgEnum, Eq, PartialEq)]
+#[clap(rename_all = "kebab_case")]
+pub enum SheetFormat {
+    Pdf,
+    Svg,
+}
+
+impl SheetFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            SheetFormat::Pdf => "pdf",
+            SheetFormat::Svg => "svg",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SheetError {
+    #[error("Expected {expected} holder labels (one per share), got {actual}")]
+    HoldersMismatch { expected: usize, actual: usize },
+    #[error("Error writing sheet: {path}")]
+    WriteError { path: PathBuf, source: io::Error },
+}
+
+/// A share to make a sheet for
+pub struct Sheet<'a> {
+    pub share_file: &'a StoredShare,
+    /// Exactly what the qr code and the text code have to contain
+    pub share_json: &'a str,
+    pub holder: Option<&'a str>,
+    /// Printed on the sheet as is
+    pub date: &'a str,
+}
+
+/// Holder labels by share, checking there is one label per share if any are given
+pub fn holder_labels(
+    holders: &[String],
+    number_of_shares: usize,
+) -> Result<Vec<Option<&str>>, SheetError> {
+    if holders.is_empty() {
+        return Ok(vec![None; number_of_shares]);
+    }
+
+    if holders.len() != number_of_shares {
+        return Err(SheetError::HoldersMismatch {
+            expected: number_of_shares,
+            actual: holders.len(),
+        });
+    }
+
+    Ok(holders.iter().map(|holder| Some(holder.as_str())).collect())
+}
+
+pub fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+pub fn write_sheet(path: &Path, sheet: &Sheet, format: SheetFormat) -> Result<(), SheetError> {
+    let pages = layout(sheet);
+    let content = match format {
+        SheetFormat::Pdf => render_pdf(&pages),
+        SheetFormat::Svg => render_svg(&pages).into_bytes(),
+    };
+
+    // the sheet carries the share, it is as private as the share file
+    share_files::create_private_file(path)
+        .and_then(|mut file| file.write_all(&content))
+        .map_err(|source| SheetError::WriteError {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+#[derive(Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+enum Element {
+    /// `y` is the baseline, measured from the top of the page
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        font: Font,
+        text: String,
+    },
+    /// `y` is the top of the rectangle
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+}
+
+/// Fills the pages top to bottom, starting a new page when the current one is full
+struct Layout {
+    pages: Vec<Vec<Element>>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Layout {
+            pages: vec![vec![]],
+            y: MARGIN,
+        }
+    }
+
+    fn text(&mut self, size: f32, font: Font, text: impl Into<String>) {
+        let line_height = size * 1.3;
+        if self.y + line_height > PAGE_HEIGHT - MARGIN {
+            self.pages.push(vec![]);
+            self.y = MARGIN;
+        }
+
+        self.y += line_height;
+        self.push(Element::Text {
+            x: MARGIN,
+            y: self.y,
+            size,
+            font,
+            text: text.into(),
+        });
+    }
+
+    /// Wraps the text at the page width, the continuation lines are indented
+    fn paragraph(&mut self, size: f32, text: &str) {
+        let max_chars = ((PAGE_WIDTH - 2.0 * MARGIN) / (size * CHARACTER_WIDTH)) as usize;
+
+        let mut line = String::new();
+        for word in text.split(' ') {
+            if !line.is_empty() && line.len() + word.len() + 1 > max_chars {
+                self.text(size, Font::Regular, line);
+                line = String::from("   ");
+            }
+            if !line.trim().is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        self.text(size, Font::Regular, line);
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.y += height;
+    }
+
+    /// Draws the qr code in the top right corner of the current position, next to the text that follows.
+    /// Returns the bottom of the qr code
+    fn qr_code(&mut self, code: &QrCode) -> f32 {
+        let width = code.width();
+        let module_size = QR_CODE_SIZE / (width + 2 * QR_QUIET_ZONE) as f32;
+        let left = PAGE_WIDTH - MARGIN - QR_CODE_SIZE + QR_QUIET_ZONE as f32 * module_size;
+        let top = self.y + QR_QUIET_ZONE as f32 * module_size;
+
+        // every run of dark modules in a row is drawn as one rectangle
+        let colors = code.to_colors();
+        for (row, modules) in colors.chunks(width).enumerate() {
+            let mut column = 0;
+            while column < width {
+                if modules[column] == Color::Light {
+                    column += 1;
+                    continue;
+                }
+
+                let run_start = column;
+                while column < width && modules[column] == Color::Dark {
+                    column += 1;
+                }
+
+                self.push(Element::Rect {
+                    x: left + run_start as f32 * module_size,
+                    y: top + row as f32 * module_size,
+                    width: (column - run_start) as f32 * module_size,
+                    height: module_size,
+                });
+            }
+        }
+
+        self.y + QR_CODE_SIZE
+    }
+
+    fn push(&mut self, element: Element) {
+        self.pages
+            .last_mut()
+            .expect("Layout has at least one page")
+            .push(element);
+    }
+}
+
+fn layout(sheet: &Sheet) -> Vec<Vec<Element>> {
+    let mut layout = Layout::new();
+    let set = sheet.share_file.set();
+
+    let title = match set {
+        Some(set) => format!(
+            "Meta Secret share {} of {}",
+            set.index + 1,
+            set.number_of_shares
+        ),
+        None => String::from("Meta Secret share"),
+    };
+    let qr_code = QrCode::new(sheet.share_json.as_bytes()).ok();
+    let qr_code_bottom = qr_code.as_ref().map(|code| layout.qr_code(code));
+
+    layout.text(20.0, Font::Bold, title);
+    layout.gap(6.0);
+
+    let holder = sheet.holder.unwrap_or("______________________________");
+    layout.text(11.0, Font::Regular, format!("Holder: {}", holder));
+    if let Some(set) = set {
+        layout.text(11.0, Font::Regular, format!("Set id: {}", set.set_id));
+        layout.text(
+            11.0,
+            Font::Regular,
+            format!(
+                "Threshold: any {} of the {} shares restore the secret",
+                set.threshold, set.number_of_shares
+            ),
+        );
+    }
+    layout.text(11.0, Font::Regular, format!("Date: {}", sheet.date));
+    layout.text(
+        11.0,
+        Font::Regular,
+        format!("Share format version: {}", sheet.share_file.version()),
+    );
+
+    match qr_code_bottom {
+        Some(qr_code_bottom) => layout.y = layout.y.max(qr_code_bottom),
+        None => layout.text(
+            11.0,
+            Font::Bold,
+            "The share is too big for a qr code, use the text code below",
+        ),
+    }
+    layout.gap(10.0);
+
+    layout.text(12.0, Font::Bold, "Text code");
+    layout.paragraph(
+        9.0,
+        "If the qr code can't be scanned, type the lines below into a .txt file. \
+        The last two characters of every line are a checksum, a typo is reported with its line.",
+    );
+    layout.gap(4.0);
+    for line in text_code::encode(sheet.share_json.as_bytes()).lines() {
+        layout.text(8.0, Font::Mono, line);
+    }
+    layout.gap(10.0);
+
+    layout.text(12.0, Font::Bold, "How to recover the secret");
+    for step in recovery_steps(sheet.share_file) {
+        layout.paragraph(8.5, &step);
+    }
+
+    layout.pages
+}
+
+fn recovery_steps(share_file: &StoredShare) -> Vec<String> {
+    let threshold = share_file
+        .set()
+        .map(|set| format!("at least {}", set.threshold))
+        .unwrap_or_else(|| String::from("enough"));
+
+    let mut steps = vec![
+        format!(
+            "1. Collect {} sheets with the same set id (or their share files) from the holders.",
+            threshold
+        ),
+        format!(
+            "2. Get the Meta Secret command line app ({}) or open the web app ({}).",
+            PROJECT_URL, WEB_APP_URL
+        ),
+        String::from(
+            "3. Scan or photograph the qr codes and save the images into one folder, then run: \
+            meta-secret-cli restore --from qr --shares-dir <folder>",
+        ),
+        String::from(
+            "4. If a qr code can't be read, type the text code of every share into its own .txt file \
+            in the folder instead, then run: meta-secret-cli restore --from text --shares-dir <folder>",
+        ),
+    ];
+
+    match share_file.secret().map(|secret| secret.encoding) {
+        None => steps.push(String::from(
+            "5. The share is protected with the passphrase of its holder: \
+            restore asks for it, keep it apart from this sheet.",
+        )),
+        Some(SecretEncoding::Text) => {}
+        Some(SecretEncoding::Base64) => steps.push(String::from(
+            "5. The secret is a file: add --output <file> to the restore command to save it.",
+        )),
+        Some(SecretEncoding::DataKey) => steps.push(format!(
+            "5. The shares hold the key of the encrypted file {}, it is needed too: \
+            put it into the folder and add --output <file> to the restore command.",
+            share_file
+                .secret()
+                .and_then(|secret| secret.encrypted_file.as_deref())
+                .unwrap_or("(unknown)")
+        )),
+    }
+
+    steps.push(String::from(
+        "Keep this sheet private: it is a part of the secret. \
+        One sheet alone reveals nothing, but enough sheets together restore the secret.",
+    ));
+
+    steps
+}
+
+fn render_pdf(pages: &[Vec<Element>]) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let fonts = [
+        (Name(b"F1"), Ref::new(3), Name(b"Helvetica")),
+        (Name(b"F2"), Ref::new(4), Name(b"Helvetica-Bold")),
+        (Name(b"F3"), Ref::new(5), Name(b"Courier")),
+    ];
+    let first_page_id = 6;
+
+    let page_ids: Vec<Ref> = (0..pages.len())
+        .map(|page_index| Ref::new(first_page_id + 2 * page_index as i32))
+        .collect();
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(pages.len() as i32);
+
+    for (_, font_id, base_font) in fonts {
+        pdf.type1_font(font_id)
+            .base_font(base_font)
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+
+    for (page_id, elements) in page_ids.into_iter().zip(pages) {
+        let content_id = Ref::new(page_id.get() + 1);
+
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        {
+            let mut resources = page.resources();
+            let mut page_fonts = resources.fonts();
+            for (font_name, font_id, _) in fonts {
+                page_fonts.pair(font_name, font_id);
+            }
+        }
+        page.finish();
+
+        let mut content = Content::new();
+        content.set_fill_gray(0.0);
+        for element in elements {
+            match element {
+                Element::Text {
+                    x,
+                    y,
+                    size,
+                    font,
+                    text,
+                } => {
+                    let (font_name, _, _) = fonts[*font as usize];
+                    content.begin_text();
+                    content.set_font(font_name, *size);
+                    content.next_line(*x, PAGE_HEIGHT - y);
+                    content.show(Str(&win_ansi(text)));
+                    content.end_text();
+                }
+                Element::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    content.rect(*x, PAGE_HEIGHT - y - height, *width, *height);
+                }
+            }
+        }
+        // all the rectangles are filled at once
+        if elements
+            .iter()
+            .any(|element| matches!(element, Element::Rect { .. }))
+        {
+            content.fill_nonzero();
+        }
+
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.finish()
+}
+
+/// Standard pdf fonts know only the WinAnsi characters (latin-1 mostly), the rest is replaced with '?'
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|character| match u32::from(character) {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => code as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Pages are stacked one under another in a single svg image
+fn render_svg(pages: &[Vec<Element>]) -> String {
+    let height = PAGE_HEIGHT * pages.len() as f32;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}pt\" height=\"{h}pt\" viewBox=\"0 0 {w} {h}\">\n",
+        w = PAGE_WIDTH,
+        h = height
+    );
+    svg.push_str(&format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+        PAGE_WIDTH, height
+    ));
+
+    for (page_index, elements) in pages.iter().enumerate() {
+        let offset = PAGE_HEIGHT * page_index as f32;
+
+        for element in elements {
+            match element {
+                Element::Text {
+                    x,
+                    y,
+                    size,
+                    font,
+                    text,
+                } => {
+                    let (family, weight) = match font {
+                        Font::Regular => ("Helvetica, Arial, sans-serif", "normal"),
+                        Font::Bold => ("Helvetica, Arial, sans-serif", "bold"),
+                        Font::Mono => ("Courier, monospace", "normal"),
+                    };
+                    svg.push_str(&format!(
+                        "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-weight=\"{}\" font-size=\"{}\" xml:space=\"preserve\">{}</text>\n",
+                        x,
+                        offset + y,
+                        family,
+                        weight,
+                        size,
+                        escape_xml(text)
+                    ));
+                }
+                Element::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    // a bit bigger than the modules, so there are no hairlines between the rows
+                    svg.push_str(&format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                        x,
+                        offset + y,
+                        width + 0.05,
+                        height + 0.05
+                    ));
+                }
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
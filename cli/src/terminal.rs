@@ -0,0 +1,110 @@
+//! Shows shares as qr codes right in the terminal, one share per screen,
+//! so headless servers don't need the images at all
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use atty::Stream;
+
+use crate::qr::{self, QrError};
+use crate::share_files::StoredShare;
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TerminalError {
+    #[error("Qr codes can be shown only in a terminal")]
+    NotATerminal,
+    #[error("Share is too big to be shown as a qr code: {path}")]
+    TooBig { path: PathBuf },
+    #[error("Error rendering qr code of: {path}")]
+    QrError { path: PathBuf, source: QrError },
+    #[error("Error writing to the terminal")]
+    Io(#[from] io::Error),
+}
+
+/// A share to show on its own screen
+pub struct TerminalShare {
+    pub path: PathBuf,
+    pub share_file: StoredShare,
+    /// Exactly what the qr code has to contain
+    pub share_json: String,
+}
+
+/// Shows every share on a clean screen and waits for enter before the next one.
+/// The screen is cleared after the last share too, so no share stays in the terminal
+pub fn show_shares(shares: &[TerminalShare]) -> Result<(), TerminalError> {
+    if !atty::is(Stream::Stdout) {
+        return Err(TerminalError::NotATerminal);
+    }
+    let mut input = terminal_input()?;
+
+    // render everything upfront to fail before the screen gets cleared
+    let qr_codes = shares
+        .iter()
+        .map(|share| {
+            qr::render_terminal_qr_code(&share.share_json).map_err(|source| match source {
+                QrError::EncodingError(qrcode::types::QrError::DataTooLong) => {
+                    TerminalError::TooBig {
+                        path: share.path.clone(),
+                    }
+                }
+                source => TerminalError::QrError {
+                    path: share.path.clone(),
+                    source,
+                },
+            })
+        })
+        .collect::<Result<Vec<String>, TerminalError>>()?;
+
+    let mut stdout = io::stdout();
+    for (screen, (share, qr_code)) in shares.iter().zip(qr_codes).enumerate() {
+        write!(stdout, "{}", CLEAR_SCREEN)?;
+        writeln!(stdout, "{}", title(share))?;
+        writeln!(stdout, "{}", qr_code)?;
+
+        if screen + 1 < shares.len() {
+            write!(
+                stdout,
+                "Press enter for the next share ({} of {})",
+                screen + 2,
+                shares.len()
+            )?;
+        } else {
+            write!(stdout, "Press enter to clear the screen")?;
+        }
+        stdout.flush()?;
+
+        input.read_line(&mut String::new())?;
+    }
+
+    write!(stdout, "{}", CLEAR_SCREEN)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn title(share: &TerminalShare) -> String {
+    match share.share_file.set() {
+        Some(set) => format!(
+            "Share {} of {} (threshold: {}, set: {}), file: {}",
+            set.index + 1,
+            set.number_of_shares,
+            set.threshold,
+            set.set_id,
+            share.path.display()
+        ),
+        None => format!("Share: {}", share.path.display()),
+    }
+}
+
+/// Where the enter key is read from: the terminal, even when stdin is a pipe
+/// (e.g. the secret has been piped into split)
+fn terminal_input() -> Result<Box<dyn BufRead>, TerminalError> {
+    if atty::is(Stream::Stdin) {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+
+    let tty = File::open("/dev/tty").map_err(|_| TerminalError::NotATerminal)?;
+    Ok(Box::new(BufReader::new(tty)))
+}
@@ -0,0 +1,193 @@
+//! Human-typeable text encoding of shares, printed on the paper sheets next to the qr code.
+//!
+//! The data is encoded in Crockford's base32 (no I, L, O, U, case-insensitive),
+//! in numbered lines of groups of 5 characters. Every line ends with a 2-character checksum of the line,
+//! so a typo is reported with the line it is in. The last line (`==`) is a checksum of the whole data,
+//! it catches missing lines.
+//!
+//! ```text
+//! 01  3NCQ0 R9PDH ... 5TGQ8  K7
+//! 02  ...
+//! ==  7HQ2MX
+//! ```
+
+use sha2::{Digest, Sha256};
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const GROUP_SIZE: usize = 5;
+const GROUPS_PER_LINE: usize = 8;
+const LINE_CHECKSUM_SIZE: usize = 2;
+const TOTAL_CHECKSUM_SIZE: usize = 6;
+const TOTAL_CHECKSUM_MARKER: &str = "==";
+
+pub const TEXT_CODE_EXTENSION: &str = "txt";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TextCodeError {
+    #[error("Invalid line: {line}. Expected: <line number> <groups of characters> <checksum>")]
+    InvalidLine { line: String },
+    #[error("Unexpected line number {actual}, expected {expected}. Is a line missing?")]
+    WrongLineNumber { expected: usize, actual: String },
+    #[error("Invalid character '{character}' in line {line_number}")]
+    InvalidCharacter { line_number: usize, character: char },
+    #[error("Checksum mismatch in line {line_number}, check the line for typos")]
+    LineChecksumMismatch { line_number: usize },
+    #[error("The last line (==) with the checksum of the whole text is missing")]
+    MissingTotalChecksum,
+    #[error("Checksum of the whole text doesn't match. Are lines missing or swapped?")]
+    TotalChecksumMismatch,
+}
+
+pub fn encode(data: &[u8]) -> String {
+    let encoded = to_base32(data);
+
+    let mut lines: Vec<String> = encoded
+        .as_bytes()
+        .chunks(GROUP_SIZE * GROUPS_PER_LINE)
+        .enumerate()
+        .map(|(line_index, chunk)| {
+            let line_number = line_index + 1;
+            let groups: Vec<&str> = chunk
+                .chunks(GROUP_SIZE)
+                .map(|group| std::str::from_utf8(group).expect("Base32 is ascii"))
+                .collect();
+            let line_data = groups.concat();
+
+            format!(
+                "{:02}  {}  {}",
+                line_number,
+                groups.join(" "),
+                checksum(line_number, &line_data, LINE_CHECKSUM_SIZE)
+            )
+        })
+        .collect();
+
+    lines.push(format!(
+        "{}  {}",
+        TOTAL_CHECKSUM_MARKER,
+        checksum(0, &encoded, TOTAL_CHECKSUM_SIZE)
+    ));
+    lines.join("\n")
+}
+
+pub fn decode(text: &str) -> Result<Vec<u8>, TextCodeError> {
+    let mut encoded = String::new();
+    let mut total_checksum = None;
+
+    let lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    for (line_index, line) in lines.enumerate() {
+        let invalid_line = || TextCodeError::InvalidLine {
+            line: line.to_string(),
+        };
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (first, rest) = tokens.split_first().ok_or_else(invalid_line)?;
+
+        if *first == TOTAL_CHECKSUM_MARKER {
+            let checksum = rest.concat();
+            total_checksum = Some(normalize(line_index + 1, &checksum)?);
+            break;
+        }
+
+        let line_number = line_index + 1;
+        let typed_line_number = normalize(line_number, first).ok();
+        if typed_line_number.and_then(|number| number.parse::<usize>().ok()) != Some(line_number) {
+            return Err(TextCodeError::WrongLineNumber {
+                expected: line_number,
+                actual: first.to_string(),
+            });
+        }
+
+        let (line_checksum, groups) = rest.split_last().ok_or_else(invalid_line)?;
+        let line_data = normalize(line_number, &groups.concat())?;
+        if normalize(line_number, line_checksum)?
+            != checksum(line_number, &line_data, LINE_CHECKSUM_SIZE)
+        {
+            return Err(TextCodeError::LineChecksumMismatch { line_number });
+        }
+
+        encoded.push_str(&line_data);
+    }
+
+    let total_checksum = total_checksum.ok_or(TextCodeError::MissingTotalChecksum)?;
+    if total_checksum != checksum(0, &encoded, TOTAL_CHECKSUM_SIZE) {
+        return Err(TextCodeError::TotalChecksumMismatch);
+    }
+
+    Ok(from_base32(&encoded))
+}
+
+/// Upper case, with the characters that are easy to confuse mapped the way Crockford's base32 does
+fn normalize(line_number: usize, text: &str) -> Result<String, TextCodeError> {
+    text.chars()
+        .map(|character| {
+            let normalized = match character.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                other => other,
+            };
+
+            if normalized.is_ascii() && ALPHABET.contains(&(normalized as u8)) {
+                Ok(normalized)
+            } else {
+                Err(TextCodeError::InvalidCharacter {
+                    line_number,
+                    character,
+                })
+            }
+        })
+        .collect()
+}
+
+fn checksum(line_number: usize, data: &str, size: usize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((line_number as u32).to_be_bytes());
+    hasher.update(data.as_bytes());
+    let hash = hasher.finalize();
+
+    to_base32(&hash)[..size].to_string()
+}
+
+fn to_base32(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in data {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+/// Expects normalized input, the trailing bits that don't make a whole byte are dropped
+fn from_base32(encoded: &str) -> Vec<u8> {
+    let mut data = vec![];
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for character in encoded.bytes() {
+        let value = ALPHABET
+            .iter()
+            .position(|symbol| *symbol == character)
+            .expect("Normalized base32") as u32;
+
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+        }
+    }
+
+    data
+}
@@ -0,0 +1,908 @@
+//! Verification of a share set without revealing the secret.
+//!
+//! At split time every share gets a [Commitment]: hashes of all the shares of the set
+//! and a mac of the secret. The mac key (check key) is random and is split along with the secret,
+//! so a single share holder can't use the mac to brute force a weak password.
+//!
+//! [restore] uses the commitments to restore the secret even when some of the given shares are corrupted
+//! or belong to another secret: such shares are left out and named. A share whose hash doesn't match
+//! is found right away, otherwise subsets of the shares are tried until one restores the secret the mac was made of.
+//!
+//! Verifiable shares (`split --verifiable`) can also be checked one by one with [check],
+//! against the commitments of the verifiable sharing, see [vss].
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use hmac::{Hmac, Mac};
+use meta_secret_core::shared_secret::shared_secret::{PlainText, SharedSecretEncryption};
+use meta_secret_share_formats::vss::{self, VssError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::MIN_THRESHOLD;
+use crate::policy::PolicyError;
+use crate::secret_input::Secret;
+use crate::share_files::{SecretMetadata, ShareData, ShareFile, ShareFileError, Sharing};
+use crate::{RestoreError, RestoredSecret};
+
+const CHECK_KEY_SIZE: usize = 32;
+/// Subsets of the shares [restore] tries before giving up, 184756 subsets of 20 shares would take too long
+const MAX_SUBSETS: usize = 10_000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Commitment {
+    /// sha256 of every share of the set, by share index
+    pub share_hashes: Vec<String>,
+    /// hmac-sha256 of the secret with the check key
+    pub secret_mac: String,
+    /// This share's part of the check key, split the same way as the secret
+    pub check_key_share: ShareData,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error("Corrupted shares: {}", display_files(files))]
+    CorruptedShares { files: Vec<PathBuf> },
+    #[error("No combination of the shares restores the secret committed at split time: some of them are corrupted or belong to another secret. More shares are needed to tell which: {}", display_files(files))]
+    NoMatchingShares { files: Vec<PathBuf> },
+    #[error(
+        "Corrupted or foreign shares left out: {}. The other shares don't restore the secret",
+        display_files(faulty_files)
+    )]
+    NotEnoughGoodShares {
+        faulty_files: Vec<PathBuf>,
+        source: Box<VerifyError>,
+    },
+    #[error("Too many combinations of the {shares} shares to find the corrupted ones, give fewer shares")]
+    TooManyCombinations { shares: usize },
+    #[error("The restored secret doesn't match the commitment made at split time")]
+    CommitmentMismatch,
+    #[error("Error restoring the check key")]
+    CheckKeyError(#[source] RestoreError),
+    #[error(transparent)]
+    RestoreError(#[from] RestoreError),
+    #[error("Not a verifiable share (split without --verifiable): {path}")]
+    NotVerifiable { path: PathBuf },
+    #[error("Invalid share: {path}")]
+    InvalidShare { path: PathBuf, source: VssError },
+    #[error(
+        "The commitments of the share have the fingerprint {actual}, expected {expected}: {path}"
+    )]
+    FingerprintMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_id: Option<String>,
+    /// Share files the secret has been restored from
+    pub files: Vec<PathBuf>,
+    /// false for the shares made by the previous versions of the cli: they have no commitment,
+    /// so only the recovery itself has been checked
+    pub commitment_checked: bool,
+}
+
+/// The secret restored by [restore]
+pub struct CheckedSecret {
+    pub secret: RestoredSecret,
+    pub set_id: Option<String>,
+    /// Share files the secret has been restored from
+    pub files: Vec<PathBuf>,
+    /// Corrupted shares and shares of other secrets, left out of the recovery
+    pub faulty_files: Vec<PathBuf>,
+    /// false for the shares made by the previous versions of the cli, they have no commitment
+    pub commitment_checked: bool,
+}
+
+/// Verifiable shares checked alone against their commitments
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    pub shares: Vec<CheckedShare>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckedShare {
+    pub file: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_id: Option<String>,
+    pub index: usize,
+    pub threshold: usize,
+    /// Fingerprint of the commitments, the same for all the shares of the set
+    pub commitments_fingerprint: String,
+}
+
+/// Makes the commitments for the shares of the secret, one per share
+pub fn commit(
+    secret: &Secret,
+    shares: &[ShareData],
+    sharing: &Sharing,
+) -> Result<Vec<Commitment>, ShareFileError> {
+    let mut check_key = [0u8; CHECK_KEY_SIZE];
+    OsRng.fill_bytes(&mut check_key);
+
+    let check_key_text = BASE64.encode(check_key);
+    // the shares of a policy restore the check key only if they meet the policy
+    let check_key_shares: Vec<ShareData> = match sharing {
+        Sharing::Core(config) | Sharing::Verifiable(config) => {
+            let check_key_shares =
+                SharedSecretEncryption::new(*config, &PlainText::from(check_key_text.as_str()))?;
+            (0..shares.len())
+                .map(|share_index| ShareData::Core(check_key_shares.get_share(share_index)))
+                .collect()
+        }
+        Sharing::Policy(policy) => policy
+            .root
+            .split(&check_key_text)?
+            .into_iter()
+            .map(ShareData::Policy)
+            .collect(),
+    };
+
+    let share_hashes: Vec<String> = shares.iter().map(share_hash).collect();
+    let secret_mac = hex::encode(mac(&check_key, secret.content()).finalize().into_bytes());
+
+    let commitments = check_key_shares
+        .into_iter()
+        .map(|check_key_share| Commitment {
+            share_hashes: share_hashes.clone(),
+            secret_mac: secret_mac.clone(),
+            check_key_share,
+        })
+        .collect();
+
+    Ok(commitments)
+}
+
+/// Restores the secret in memory and checks it against the commitment.
+/// The secret is never returned, printed or written anywhere
+pub fn verify(share_files: Vec<(PathBuf, ShareFile)>) -> Result<VerifyReport, VerifyError> {
+    let checked_secret = restore(share_files)?;
+    if !checked_secret.faulty_files.is_empty() {
+        return Err(VerifyError::CorruptedShares {
+            files: checked_secret.faulty_files,
+        });
+    }
+
+    Ok(VerifyReport {
+        set_id: checked_secret.set_id,
+        files: checked_secret.files,
+        commitment_checked: checked_secret.commitment_checked,
+    })
+}
+
+/// Restores the secret from the shares that can be trusted and names the others:
+///  - shares of another secret than most of the shares are
+///  - shares that don't match the hashes of the commitment most of the shares agree on
+///  - shares that restore a secret other than the one the mac of the commitment was made of,
+///    found by trying subsets of the shares
+pub fn restore(share_files: Vec<(PathBuf, ShareFile)>) -> Result<CheckedSecret, VerifyError> {
+    let (share_files, mut faulty_files) = leave_out_foreign_shares(share_files)?;
+    let set_id = share_files
+        .first()
+        .and_then(|(_, share_file)| share_file.set.as_ref())
+        .map(|set| set.set_id.clone());
+
+    let commitment = match expected_commitment(&share_files) {
+        Some(commitment) => commitment.clone(),
+        None => {
+            let files = share_files.iter().map(|(file, _)| file.clone()).collect();
+            let shares = share_files
+                .into_iter()
+                .map(|(_, share_file)| share_file)
+                .collect();
+
+            return Ok(CheckedSecret {
+                secret: crate::restore_from_json(shares)?,
+                set_id,
+                files,
+                faulty_files,
+                commitment_checked: false,
+            });
+        }
+    };
+
+    let (good_shares, corrupted_shares): (Vec<_>, Vec<_>) = share_files
+        .into_iter()
+        .partition(|(_, share_file)| matches_commitment(share_file, &commitment));
+    faulty_files.extend(corrupted_shares.into_iter().map(|(file, _)| file));
+
+    // the same share can be provided several times (e.g. as a json file and as a qr code)
+    let mut indices = HashSet::new();
+    let good_shares: Vec<(PathBuf, ShareFile)> = good_shares
+        .into_iter()
+        .filter(|(_, share_file)| {
+            share_file
+                .set
+                .as_ref()
+                .map(|set| indices.insert(set.index))
+                .unwrap_or(true)
+        })
+        .collect();
+
+    let (subset, secret) = match find_matching_subset(&good_shares, &commitment) {
+        Ok(matching_subset) => matching_subset,
+        Err(err) if !faulty_files.is_empty() => {
+            return Err(VerifyError::NotEnoughGoodShares {
+                faulty_files,
+                source: Box::new(err),
+            })
+        }
+        Err(err) => return Err(err),
+    };
+
+    // a share left out of the subset is good if it restores the secret along with the good ones.
+    // It goes first: the core may not use more shares than the threshold
+    let mut files = vec![];
+    for (share_index, (file, _)) in good_shares.iter().enumerate() {
+        let is_good = subset.contains(&share_index)
+            || restore_committed(
+                std::iter::once(share_index).chain(subset.iter().copied()),
+                &good_shares,
+                &commitment,
+            )
+            .is_ok();
+
+        if is_good {
+            files.push(file.clone());
+        } else {
+            faulty_files.push(file.clone());
+        }
+    }
+
+    Ok(CheckedSecret {
+        secret,
+        set_id,
+        files,
+        faulty_files,
+        commitment_checked: true,
+    })
+}
+
+type ForeignFiles = Vec<PathBuf>;
+
+/// Shares of the secret most of the files belong to (the same set and secret metadata)
+/// and the files of the other secrets. Shares of several secrets in equal numbers are an error
+fn leave_out_foreign_shares(
+    share_files: Vec<(PathBuf, ShareFile)>,
+) -> Result<(Vec<(PathBuf, ShareFile)>, ForeignFiles), VerifyError> {
+    let secret_of = |share_file: &ShareFile| {
+        (
+            share_file.set.as_ref().map(|set| set.set_id.clone()),
+            share_file.secret.clone(),
+        )
+    };
+
+    let mut secrets: Vec<((Option<String>, SecretMetadata), usize)> = vec![];
+    for (_, share_file) in &share_files {
+        let secret = secret_of(share_file);
+        match secrets.iter_mut().find(|(known, _)| *known == secret) {
+            Some((_, count)) => *count += 1,
+            None => secrets.push((secret, 1)),
+        }
+    }
+
+    secrets.sort_by(|(_, left), (_, right)| right.cmp(left));
+    let expected_secret = match secrets.as_slice() {
+        [] => return Ok((share_files, vec![])),
+        [(_, first), (_, second), ..] if first == second => {
+            return Err(RestoreError::MixedShares.into())
+        }
+        [(secret, _), ..] => secret.clone(),
+    };
+
+    let (share_files, foreign_shares): (Vec<_>, Vec<_>) = share_files
+        .into_iter()
+        .partition(|(_, share_file)| secret_of(share_file) == expected_secret);
+
+    Ok((
+        share_files,
+        foreign_shares.into_iter().map(|(file, _)| file).collect(),
+    ))
+}
+
+/// The share is one of the shares the commitment was made for
+fn matches_commitment(share_file: &ShareFile, commitment: &Commitment) -> bool {
+    let matches_set = share_file
+        .commitment
+        .as_ref()
+        .map(|share_commitment| {
+            share_commitment.share_hashes == commitment.share_hashes
+                && share_commitment.secret_mac == commitment.secret_mac
+        })
+        .unwrap_or(false);
+    let matches_hash = share_file
+        .set
+        .as_ref()
+        .and_then(|set| commitment.share_hashes.get(set.index))
+        .map(|hash| *hash == share_hash(&share_file.share))
+        .unwrap_or(false);
+
+    matches_set && matches_hash
+}
+
+/// Indices of the shares that restore the committed secret, all of them if they can,
+/// otherwise the first subset that does, from the smallest ones
+fn find_matching_subset(
+    share_files: &[(PathBuf, ShareFile)],
+    commitment: &Commitment,
+) -> Result<(Vec<usize>, RestoredSecret), VerifyError> {
+    let all_shares: Vec<usize> = (0..share_files.len()).collect();
+    match restore_committed(all_shares.iter().copied(), share_files, commitment) {
+        Ok(secret) => return Ok((all_shares, secret)),
+        // fewer shares can't do better
+        Err(VerifyError::RestoreError(
+            err @ (RestoreError::NotEnoughShares { .. }
+            | RestoreError::MixedShares
+            | RestoreError::PolicyError(PolicyError::Unmet { .. })),
+        )) => return Err(err.into()),
+        Err(_) => {}
+    }
+
+    let required = share_files
+        .first()
+        .and_then(|(_, share_file)| share_file.set.as_ref())
+        .map(|set| set.threshold)
+        .unwrap_or(MIN_THRESHOLD);
+
+    let mut tried_subsets = 0;
+    for subset_size in required..share_files.len() {
+        for subset in Combinations::new(share_files.len(), subset_size) {
+            tried_subsets += 1;
+            if tried_subsets > MAX_SUBSETS {
+                return Err(VerifyError::TooManyCombinations {
+                    shares: share_files.len(),
+                });
+            }
+
+            if let Ok(secret) = restore_committed(subset.iter().copied(), share_files, commitment) {
+                return Ok((subset, secret));
+            }
+        }
+    }
+
+    Err(VerifyError::NoMatchingShares {
+        files: share_files.iter().map(|(file, _)| file.clone()).collect(),
+    })
+}
+
+/// Restores the secret and the check key from the shares with the given indices
+/// and checks the secret against the mac of the commitment
+fn restore_committed(
+    indices: impl Iterator<Item = usize>,
+    share_files: &[(PathBuf, ShareFile)],
+    commitment: &Commitment,
+) -> Result<RestoredSecret, VerifyError> {
+    let shares: Vec<ShareFile> = indices
+        .map(|share_index| share_files[share_index].1.clone())
+        .collect();
+
+    let policy = shares
+        .first()
+        .and_then(|share_file| share_file.set.as_ref())
+        .and_then(|set| set.policy.clone());
+    let check_key_shares = shares
+        .iter()
+        .filter_map(|share_file| share_file.commitment.as_ref())
+        .map(|commitment| commitment.check_key_share.clone())
+        .collect();
+
+    let restored_secret = crate::restore_from_json(shares)?;
+
+    let check_key_text = crate::recover_text(check_key_shares, policy.as_ref())
+        .map_err(VerifyError::CheckKeyError)?;
+    let check_key = BASE64
+        .decode(check_key_text)
+        .map_err(|_| VerifyError::CommitmentMismatch)?;
+    let secret_mac =
+        hex::decode(&commitment.secret_mac).map_err(|_| VerifyError::CommitmentMismatch)?;
+
+    mac(&check_key, &restored_secret.content)
+        .verify_slice(&secret_mac)
+        .map_err(|_| VerifyError::CommitmentMismatch)?;
+
+    Ok(restored_secret)
+}
+
+/// All the subsets of `size` indices out of `0..count`, in lexicographic order
+struct Combinations {
+    count: usize,
+    next: Option<Vec<usize>>,
+}
+
+impl Combinations {
+    fn new(count: usize, size: usize) -> Self {
+        Self {
+            count,
+            next: (size <= count).then(|| (0..size).collect()),
+        }
+    }
+}
+
+impl Iterator for Combinations {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+
+        // move the last index that can still move forward, the following ones go right after it
+        let size = current.len();
+        let mut following = current.clone();
+        if let Some(position) = (0..size)
+            .rev()
+            .find(|&position| following[position] < self.count - size + position)
+        {
+            following[position] += 1;
+            for next_position in position + 1..size {
+                following[next_position] = following[next_position - 1] + 1;
+            }
+            self.next = Some(following);
+        }
+
+        Some(current)
+    }
+}
+
+/// Checks every verifiable share alone against the commitments it carries
+/// and, if given, against the fingerprint of the commitments published at split time
+pub fn check(
+    share_files: Vec<(PathBuf, ShareFile)>,
+    fingerprint: Option<&str>,
+) -> Result<CheckReport, VerifyError> {
+    let mut shares = vec![];
+    for (path, share_file) in share_files {
+        let verifiable_share = match &share_file.share {
+            ShareData::Verifiable(verifiable_share) => verifiable_share,
+            ShareData::Core(_) | ShareData::Policy(_) => {
+                return Err(VerifyError::NotVerifiable { path })
+            }
+        };
+        let invalid_share = |source| VerifyError::InvalidShare {
+            path: path.clone(),
+            source,
+        };
+
+        let (vss_share, commitments) = verifiable_share.decode().map_err(invalid_share)?;
+        // the verifiable share counts from 1, the set from 0
+        let index = vss_share.index() - 1;
+        if share_file.set.as_ref().map(|set| set.index) != Some(index) {
+            return Err(invalid_share(VssError::ShareMismatch {
+                index: vss_share.index(),
+            }));
+        }
+        vss::check(&vss_share, &commitments).map_err(invalid_share)?;
+
+        let commitments_fingerprint = commitments.fingerprint();
+        if let Some(expected) = fingerprint {
+            if normalize_fingerprint(expected) != normalize_fingerprint(&commitments_fingerprint) {
+                return Err(VerifyError::FingerprintMismatch {
+                    path,
+                    expected: expected.to_string(),
+                    actual: commitments_fingerprint,
+                });
+            }
+        }
+
+        shares.push(CheckedShare {
+            file: path,
+            set_id: share_file.set.map(|set| set.set_id),
+            index,
+            threshold: commitments.threshold(),
+            commitments_fingerprint,
+        });
+    }
+
+    Ok(CheckReport { shares })
+}
+
+/// Fingerprints are read out loud or typed in: the case and the separators don't matter
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The commitment most of the shares agree on.
+/// Shares of one set carry the same hashes and mac, a corrupted share is outvoted by the others
+fn expected_commitment(share_files: &[(PathBuf, ShareFile)]) -> Option<&Commitment> {
+    let commitments: Vec<&Commitment> = share_files
+        .iter()
+        .filter_map(|(_, share_file)| share_file.commitment.as_ref())
+        .collect();
+
+    commitments.iter().copied().max_by_key(|candidate| {
+        commitments
+            .iter()
+            .filter(|commitment| {
+                commitment.share_hashes == candidate.share_hashes
+                    && commitment.secret_mac == candidate.secret_mac
+            })
+            .count()
+    })
+}
+
+fn share_hash(share: &ShareData) -> String {
+    // a share is a plain struct of numbers and strings, serialization can't fail
+    let share_json = serde_json::to_vec(share).expect("Error serializing share");
+    hex::encode(Sha256::digest(share_json))
+}
+
+fn mac(key: &[u8], content: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("Hmac accepts keys of any size");
+    mac.update(content);
+    mac
+}
+
+fn display_files(files: &[PathBuf]) -> String {
+    files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl VerifyReport {
+    pub fn print_text(&self) {
+        if let Some(set_id) = &self.set_id {
+            println!("Set id: {}", set_id);
+        }
+        for file in &self.files {
+            println!("Share: {}", file.display());
+        }
+
+        if self.commitment_checked {
+            println!("Verified: the secret can be restored and matches the commitment");
+        } else {
+            println!(
+                "Verified: the secret can be restored \
+                (the shares have no commitment, made by a previous version of the cli)"
+            );
+        }
+    }
+}
+
+impl CheckReport {
+    pub fn print_text(&self) {
+        for share in &self.shares {
+            match &share.set_id {
+                Some(set_id) => println!(
+                    "Share {} of set {}: {}",
+                    share.index,
+                    set_id,
+                    share.file.display()
+                ),
+                None => println!("Share {}: {}", share.index, share.file.display()),
+            }
+            println!(
+                "  matches the commitments, {} shares restore the secret",
+                share.threshold
+            );
+            println!(
+                "  commitments fingerprint: {}",
+                share.commitments_fingerprint
+            );
+        }
+
+        println!(
+            "Checked: the shares match their commitments. \
+            The other holders have to see the same fingerprint"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::Parser;
+    use meta_secret_core::shared_secret::data_block::common::SharedSecretConfig;
+
+    use super::*;
+    use crate::output::{self, CommandOutput, ExitCode, OutputFormat};
+    use crate::policy::SetPolicy;
+    use crate::share_files::{self, SplitOutputArgs, StoredShare};
+
+    const SECRET: &str = "correct horse battery staple";
+
+    #[derive(Parser)]
+    struct TestArgs {
+        #[clap(flatten)]
+        output: SplitOutputArgs,
+    }
+
+    /// Splits the secret the way `split` does and reads the written share files back
+    fn write_shares(
+        dir: &Path,
+        threshold: usize,
+        number_of_shares: usize,
+    ) -> Vec<(PathBuf, ShareFile)> {
+        let sharing = Sharing::Core(SharedSecretConfig {
+            number_of_shares,
+            threshold,
+        });
+        let shares =
+            share_files::split_secret(&Secret::Text(SECRET.to_string()), &sharing).unwrap();
+        let stored_shares: Vec<StoredShare> = shares.into_iter().map(StoredShare::Plain).collect();
+
+        let output = TestArgs::parse_from(["split", "--out-dir", dir.to_str().unwrap()]).output;
+        crate::json_files(&output.write_shares(&stored_shares).unwrap())
+            .into_iter()
+            .map(|json_file| {
+                let share_file = share_files::read_stored_share(&json_file)
+                    .unwrap()
+                    .open(&json_file, None)
+                    .unwrap();
+                (json_file, share_file)
+            })
+            .collect()
+    }
+
+    fn exit_code(err: VerifyError) -> i32 {
+        output::report(
+            OutputFormat::Json,
+            Err(anyhow::Error::new(err).context("Verification failed")),
+        )
+    }
+
+    #[test]
+    fn recoverable_set_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let shares = write_shares(dir.path(), 2, 3);
+        let files: Vec<PathBuf> = shares.iter().map(|(file, _)| file.clone()).collect();
+
+        let report = verify(shares.clone()).unwrap();
+        assert!(report.commitment_checked);
+        assert_eq!(report.files, files);
+
+        let report = verify(shares[1..].to_vec()).unwrap();
+        assert_eq!(report.files, files[1..]);
+    }
+
+    #[test]
+    fn below_threshold_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut shares = write_shares(dir.path(), 3, 5);
+        shares.truncate(2);
+
+        let err = verify(shares).unwrap_err();
+        assert!(matches!(
+            err,
+            VerifyError::RestoreError(RestoreError::NotEnoughShares {
+                found: 2,
+                required: 3
+            })
+        ));
+        assert_eq!(exit_code(err), ExitCode::NotEnoughShares as i32);
+    }
+
+    #[test]
+    fn secret_is_never_printed() {
+        let dir = tempfile::tempdir().unwrap();
+        let shares = write_shares(dir.path(), 2, 3);
+
+        let report = verify(shares.clone()).unwrap();
+        let json = serde_json::to_string(&CommandOutput::Verify(report)).unwrap();
+        assert!(!json.contains(SECRET));
+
+        // a share of another secret outvoted by the others
+        let mut foreign_shares = write_shares(&dir.path().join("foreign"), 2, 3);
+        let mut shares = shares;
+        shares.push(foreign_shares.remove(0));
+        let err = verify(shares).unwrap_err();
+        assert!(matches!(err, VerifyError::CorruptedShares { .. }));
+        let message = format!("{:?}", anyhow::Error::new(err));
+        assert!(!message.contains(SECRET));
+    }
+
+    #[test]
+    fn round_trip_needs_a_threshold_subset() {
+        let dir = tempfile::tempdir().unwrap();
+        // a set that only all of its shares restore, checked as 2 of 3
+        let shares = write_shares(dir.path(), 3, 3);
+        let sharing = |threshold| {
+            Sharing::Core(SharedSecretConfig {
+                number_of_shares: 3,
+                threshold,
+            })
+        };
+
+        let err = crate::check_round_trip(shares.clone(), &sharing(2)).unwrap_err();
+        assert!(format!("{:#}", err).contains("the shares [0, 1] alone don't restore the secret"));
+
+        crate::check_round_trip(shares, &sharing(3)).unwrap();
+    }
+
+    #[test]
+    fn threshold_subsets() {
+        let sharing = Sharing::Core(SharedSecretConfig {
+            number_of_shares: 5,
+            threshold: 3,
+        });
+        assert_eq!(crate::threshold_subset(&sharing), [0, 1, 2]);
+
+        let root = serde_yaml::from_str(
+            "
+            threshold: 2
+            of:
+              - name: board
+                threshold: 2
+                of: [alice, bob]
+              - carol
+              - dave
+            ",
+        )
+        .unwrap();
+        let sharing = Sharing::Policy(SetPolicy {
+            name: String::from("estate"),
+            root,
+        });
+        assert_eq!(crate::threshold_subset(&sharing), [2, 3]);
+    }
+
+    /// Changes the last number or letter of the json value, returns false if there is none
+    fn flip_last_value(value: &mut serde_json::Value) -> bool {
+        use serde_json::Value;
+
+        match value {
+            Value::Number(number) => {
+                *value = Value::from(number.as_u64().unwrap() ^ 1);
+                true
+            }
+            Value::String(text) => match text.pop() {
+                Some(last) => {
+                    text.push(if last == 'A' { 'B' } else { 'A' });
+                    true
+                }
+                None => false,
+            },
+            Value::Array(values) => values.iter_mut().rev().any(flip_last_value),
+            Value::Object(fields) => fields.values_mut().rev().any(flip_last_value),
+            Value::Bool(_) | Value::Null => false,
+        }
+    }
+
+    /// Flips a value of the share file on disk and reads it again
+    fn corrupt(share: &mut (PathBuf, ShareFile), field: &str) {
+        let (path, share_file) = share;
+        let mut share_json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&*path).unwrap()).unwrap();
+        assert!(flip_last_value(share_json.pointer_mut(field).unwrap()));
+        std::fs::write(&*path, share_json.to_string()).unwrap();
+
+        *share_file = share_files::read_stored_share(path)
+            .unwrap()
+            .open(path, None)
+            .unwrap();
+    }
+
+    fn files(shares: &[(PathBuf, ShareFile)]) -> Vec<PathBuf> {
+        shares.iter().map(|(file, _)| file.clone()).collect()
+    }
+
+    #[test]
+    fn corrupted_share_is_named() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut shares = write_shares(dir.path(), 2, 4);
+        corrupt(&mut shares[1], "/share");
+        let bad_file = shares[1].0.clone();
+
+        match verify(shares.clone()).unwrap_err() {
+            VerifyError::CorruptedShares { files } => assert_eq!(files, vec![bad_file.clone()]),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        let checked_secret = restore(shares.clone()).unwrap();
+        assert_eq!(checked_secret.secret.content, SECRET.as_bytes());
+        assert_eq!(checked_secret.faulty_files, vec![bad_file]);
+        assert_eq!(
+            checked_secret.files,
+            files(&[shares[0].clone(), shares[2].clone(), shares[3].clone()])
+        );
+    }
+
+    #[test]
+    fn corrupted_commitment_is_named() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut shares = write_shares(dir.path(), 2, 3);
+        corrupt(&mut shares[2], "/commitment/secret_mac");
+        let bad_file = shares[2].0.clone();
+
+        match verify(shares.clone()).unwrap_err() {
+            VerifyError::CorruptedShares { files } => assert_eq!(files, vec![bad_file.clone()]),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        let checked_secret = restore(shares.clone()).unwrap();
+        assert_eq!(checked_secret.secret.content, SECRET.as_bytes());
+        assert_eq!(checked_secret.faulty_files, vec![bad_file]);
+        assert_eq!(checked_secret.files, files(&shares[..2]));
+    }
+
+    #[test]
+    fn too_few_good_shares_left() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut shares = write_shares(dir.path(), 2, 3);
+        shares.truncate(2);
+        corrupt(&mut shares[0], "/share");
+        let bad_file = shares[0].0.clone();
+
+        let err = restore(shares).map(|_| ()).unwrap_err();
+        match &err {
+            VerifyError::NotEnoughGoodShares {
+                faulty_files,
+                source,
+            } => {
+                assert_eq!(*faulty_files, vec![bad_file]);
+                assert!(matches!(
+                    source.as_ref(),
+                    VerifyError::RestoreError(RestoreError::NotEnoughShares {
+                        found: 1,
+                        required: 2
+                    })
+                ));
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert_eq!(exit_code(err), ExitCode::NotEnoughShares as i32);
+    }
+
+    #[test]
+    fn too_many_combinations() {
+        let sharing = Sharing::Core(SharedSecretConfig {
+            number_of_shares: 20,
+            threshold: 2,
+        });
+        let secret = Secret::Text(SECRET.to_string());
+        // all the shares agree on a commitment of another secret, no subset can match it
+        let shares: Vec<(PathBuf, ShareFile)> = share_files::split_secret(&secret, &sharing)
+            .unwrap()
+            .into_iter()
+            .enumerate()
+            .map(|(share_index, mut share_file)| {
+                if let Some(commitment) = &mut share_file.commitment {
+                    commitment.secret_mac = "00".repeat(32);
+                }
+                (
+                    PathBuf::from(format!("share-{}.json", share_index)),
+                    share_file,
+                )
+            })
+            .collect();
+
+        let err = restore(shares).map(|_| ()).unwrap_err();
+        assert!(matches!(
+            err,
+            VerifyError::TooManyCombinations { shares: 20 }
+        ));
+    }
+
+    #[test]
+    fn combinations() {
+        let subsets: Vec<Vec<usize>> = Combinations::new(4, 2).collect();
+        assert_eq!(
+            subsets,
+            vec![
+                vec![0, 1],
+                vec![0, 2],
+                vec![0, 3],
+                vec![1, 2],
+                vec![1, 3],
+                vec![2, 3]
+            ]
+        );
+        assert_eq!(Combinations::new(20, 5).count(), 15504);
+        assert_eq!(Combinations::new(2, 3).count(), 0);
+    }
+}
@@ -0,0 +1,9 @@
+//! Text encodings of meta secret shares, used by the cli and by the web cli (wasm),
+//! the share formats of other tools (SLIP-39, ssss), the verifiable sharing
+//! and the multi-part qr codes of big shares
+
+pub mod qr_parts;
+pub mod slip39;
+pub mod ssss;
+pub mod vss;
+pub mod words;
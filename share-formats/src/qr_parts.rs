@@ -0,0 +1,250 @@
+//! Multi-part qr codes for shares too big for one readable qr code.
+//!
+//! Every part is a text of the form `msqr1:<set id>:<part>/<parts>:<checksum>:<data>`, the part number
+//! starts from 1. The checksum is the first 4 bytes of the sha256 of the whole share, in hex:
+//! it tells the shares of one set apart and proves the parts were put back together right.
+//! The parts can be scanned in any order, the parts that are missing are named.
+
+use sha2::{Digest, Sha256};
+
+/// Marks the text of a qr code as a part, never the start of a json share
+pub const PREFIX: &str = "msqr1:";
+const CHECKSUM_SIZE: usize = 4;
+/// Nobody scans more codes for one share. The number of parts is read from untrusted qr codes,
+/// a bigger one is never taken as the size of the share
+pub const MAX_PARTS: usize = 1000;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PartsError {
+    #[error("Invalid qr code part: {reason}")]
+    InvalidPart { reason: &'static str },
+    #[error("The set id can't contain ':'")]
+    InvalidSetId,
+    #[error("A part of {max_size} bytes can't hold the header of the part")]
+    PartTooSmall { max_size: usize },
+    #[error("A share can have at most {MAX_PARTS} qr code parts, not {parts}")]
+    TooManyParts { parts: usize },
+    #[error(
+        "Share {checksum} of set {set_id} is missing {} of {parts}",
+        missing_text(missing)
+    )]
+    MissingParts {
+        set_id: String,
+        checksum: String,
+        parts: usize,
+        /// Part numbers, starting from 1
+        missing: Vec<usize>,
+    },
+    #[error("Share {checksum} of set {set_id} has two different parts {part}")]
+    ConflictingParts {
+        set_id: String,
+        checksum: String,
+        part: usize,
+    },
+    #[error("The parts of share {checksum} of set {set_id} don't match its checksum")]
+    ChecksumMismatch { set_id: String, checksum: String },
+}
+
+/// A part of a share, as read from a qr code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    pub set_id: String,
+    /// Starts from 1
+    pub part: usize,
+    pub parts: usize,
+    pub checksum: String,
+    pub data: String,
+}
+
+/// A share put back together from its parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinedShare {
+    pub set_id: String,
+    pub checksum: String,
+    pub data: String,
+}
+
+pub fn is_part(text: &str) -> bool {
+    text.starts_with(PREFIX)
+}
+
+/// Splits the data into parts of at most `max_size` bytes, headers included.
+/// Data that fits into one part is still written with a header, as part 1 of 1
+pub fn split(data: &str, set_id: &str, max_size: usize) -> Result<Vec<String>, PartsError> {
+    if set_id.contains(':') {
+        return Err(PartsError::InvalidSetId);
+    }
+
+    let checksum = checksum(data);
+    // there are never more parts than bytes, the header is sized for that many
+    let number_size = (data.len().max(1)).to_string().len();
+    let header_size = PREFIX.len() + set_id.len() + 2 * number_size + checksum.len() + 4;
+    if max_size <= header_size {
+        return Err(PartsError::PartTooSmall { max_size });
+    }
+
+    let chunks = chunks(data, max_size - header_size);
+    let parts = chunks.len();
+    if parts > MAX_PARTS {
+        return Err(PartsError::TooManyParts { parts });
+    }
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            format!(
+                "{}{}:{}/{}:{}:{}",
+                PREFIX,
+                set_id,
+                i + 1,
+                parts,
+                checksum,
+                chunk
+            )
+        })
+        .collect())
+}
+
+pub fn parse(text: &str) -> Result<Part, PartsError> {
+    let invalid = |reason| PartsError::InvalidPart { reason };
+
+    let header = text
+        .strip_prefix(PREFIX)
+        .ok_or_else(|| invalid("not a part"))?;
+    let mut fields = header.splitn(4, ':');
+    let (set_id, numbers, checksum, data) =
+        match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(set_id), Some(numbers), Some(checksum), Some(data)) => {
+                (set_id, numbers, checksum, data)
+            }
+            _ => return Err(invalid("missing header fields")),
+        };
+
+    let (part, parts) = numbers
+        .split_once('/')
+        .and_then(|(part, parts)| Some((part.parse().ok()?, parts.parse().ok()?)))
+        .ok_or_else(|| invalid("the part number is not <part>/<parts>"))?;
+    if part == 0 || part > parts {
+        return Err(invalid("the part number is out of range"));
+    }
+    if parts > MAX_PARTS {
+        return Err(PartsError::TooManyParts { parts });
+    }
+    if checksum.len() != CHECKSUM_SIZE * 2 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("the checksum is not 8 hex digits"));
+    }
+
+    Ok(Part {
+        set_id: set_id.to_string(),
+        part,
+        parts,
+        checksum: checksum.to_lowercase(),
+        data: data.to_string(),
+    })
+}
+
+/// Puts the shares back together from their parts, given in any order and possibly more than once.
+/// The shares are returned in the order their first part was given
+pub fn join(parts: &[Part]) -> Result<Vec<JoinedShare>, PartsError> {
+    let mut shares: Vec<(&Part, Vec<Option<&str>>)> = vec![];
+    for part in parts {
+        let index = shares.iter().position(|(first, _)| {
+            first.set_id == part.set_id
+                && first.checksum == part.checksum
+                && first.parts == part.parts
+        });
+        let index = index.unwrap_or_else(|| {
+            shares.push((part, vec![None; part.parts]));
+            shares.len() - 1
+        });
+
+        let slot = &mut shares[index].1[part.part - 1];
+        match slot {
+            Some(data) if *data != part.data => {
+                return Err(PartsError::ConflictingParts {
+                    set_id: part.set_id.clone(),
+                    checksum: part.checksum.clone(),
+                    part: part.part,
+                })
+            }
+            _ => *slot = Some(&part.data),
+        }
+    }
+
+    shares
+        .into_iter()
+        .map(|(first, chunks)| {
+            let missing: Vec<usize> = chunks
+                .iter()
+                .enumerate()
+                .filter(|(_, chunk)| chunk.is_none())
+                .map(|(i, _)| i + 1)
+                .collect();
+            if !missing.is_empty() {
+                return Err(PartsError::MissingParts {
+                    set_id: first.set_id.clone(),
+                    checksum: first.checksum.clone(),
+                    parts: first.parts,
+                    missing,
+                });
+            }
+
+            let data: String = chunks.into_iter().flatten().collect();
+            if checksum(&data) != first.checksum {
+                return Err(PartsError::ChecksumMismatch {
+                    set_id: first.set_id.clone(),
+                    checksum: first.checksum.clone(),
+                });
+            }
+            Ok(JoinedShare {
+                set_id: first.set_id.clone(),
+                checksum: first.checksum.clone(),
+                data,
+            })
+        })
+        .collect()
+}
+
+fn checksum(data: &str) -> String {
+    let hash = Sha256::digest(data.as_bytes());
+    hash[..CHECKSUM_SIZE]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Pieces of at most `size` bytes, never splitting a character
+fn chunks(data: &str, size: usize) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut rest = data;
+    while !rest.is_empty() {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        // a character longer than the part, can't happen with the part sizes of qr codes
+        if end == 0 {
+            end = rest
+                .chars()
+                .next()
+                .map(char::len_utf8)
+                .unwrap_or(rest.len());
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    if chunks.is_empty() {
+        chunks.push("");
+    }
+    chunks
+}
+
+fn missing_text(missing: &[usize]) -> String {
+    let numbers: Vec<String> = missing.iter().map(usize::to_string).collect();
+    if missing.len() == 1 {
+        format!("part {}", numbers[0])
+    } else {
+        format!("parts {}", numbers.join(", "))
+    }
+}
@@ -0,0 +1,617 @@
+//! SLIP-39 mnemonic shares (https://github.com/satoshilabs/slips/blob/master/slip-0039.md),
+//! used by Trezor and other hardware wallets.
+//!
+//! SLIP-39 splits an encrypted master secret in GF(256), with the secret at x = 255 and a digest share at x = 254,
+//! and supports two levels of sharing (groups of member shares). The master secret is encrypted with a passphrase,
+//! every passphrase gives another valid secret. Shares of meta secret use another scheme,
+//! so the shares can't be converted one by one: the secret has to be restored and split again.
+//!
+//! Shares made here have a single group, `threshold` of `number_of_shares` members.
+//! Shares of any valid group configuration are restored.
+
+use std::collections::BTreeMap;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const WORD_LIST: &str = include_str!("slip39_wordlist.txt");
+
+const RADIX_BITS: usize = 10;
+const ID_BITS: usize = 15;
+const ITERATION_EXPONENT_BITS: usize = 4;
+const INDEX_BITS: usize = 4;
+/// identifier, extendable flag and iteration exponent, then group and member parameters
+const METADATA_WORDS: usize = 4;
+const CHECKSUM_WORDS: usize = 3;
+const MIN_SECRET_SIZE: usize = 16;
+const MIN_WORDS: usize =
+    METADATA_WORDS + CHECKSUM_WORDS + (MIN_SECRET_SIZE * 8).div_ceil(RADIX_BITS);
+const MAX_SHARES: usize = 16;
+
+const CUSTOMIZATION: &[u8] = b"shamir";
+const EXTENDABLE_CUSTOMIZATION: &[u8] = b"shamir_extendable";
+
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+const ITERATION_EXPONENT: u8 = 1;
+
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+const DIGEST_SIZE: usize = 4;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum Slip39Error {
+    #[error("Share #{share}: unknown word #{position}: '{word}'")]
+    UnknownWord {
+        share: usize,
+        position: usize,
+        word: String,
+    },
+    #[error("Share #{share}: invalid checksum, check the words for typos")]
+    InvalidChecksum { share: usize },
+    #[error("Share #{share}: invalid number of words ({words}), a SLIP-39 share has at least {MIN_WORDS} words")]
+    InvalidLength { share: usize, words: usize },
+    #[error("Share #{share}: invalid padding of the share value")]
+    InvalidPadding { share: usize },
+    #[error("The shares belong to different secrets or to different group settings")]
+    MixedShares,
+    #[error("Not enough shares: found {found}, {required} required")]
+    NotEnoughShares { found: usize, required: usize },
+    #[error("Not enough groups: {found} groups have enough shares, {required} required")]
+    NotEnoughGroups { found: usize, required: usize },
+    #[error(
+        "The shares don't fit together: one of them is corrupted or belongs to another secret"
+    )]
+    InvalidDigest,
+    #[error("SLIP-39 master secrets are at least {MIN_SECRET_SIZE} bytes long and have an even number of bytes, the secret has {size} bytes. \
+             Padding is not an option: a wallet would restore another seed")]
+    SecretLength { size: usize },
+    #[error("SLIP-39 needs a threshold of at least 2 and at most the number of shares (up to {MAX_SHARES}), got {threshold} of {number_of_shares}")]
+    InvalidThreshold {
+        threshold: usize,
+        number_of_shares: usize,
+    },
+    #[error("SLIP-39 passphrases can only contain printable ascii characters")]
+    InvalidPassphrase,
+}
+
+/// Splits the master secret into `number_of_shares` mnemonics of a single group, `threshold` of them restore the secret
+pub fn split(
+    master_secret: &[u8],
+    passphrase: &str,
+    threshold: usize,
+    number_of_shares: usize,
+) -> Result<Vec<String>, Slip39Error> {
+    if master_secret.len() < MIN_SECRET_SIZE || !master_secret.len().is_multiple_of(2) {
+        return Err(Slip39Error::SecretLength {
+            size: master_secret.len(),
+        });
+    }
+    if threshold < 2 || threshold > number_of_shares || number_of_shares > MAX_SHARES {
+        return Err(Slip39Error::InvalidThreshold {
+            threshold,
+            number_of_shares,
+        });
+    }
+    check_passphrase(passphrase)?;
+
+    let mut random_id = [0u8; 2];
+    getrandom::getrandom(&mut random_id).expect("Random number generator failed");
+    let identifier = u16::from_be_bytes(random_id) & ((1 << ID_BITS) - 1);
+
+    let parameters = Parameters {
+        identifier,
+        extendable: false,
+        iteration_exponent: ITERATION_EXPONENT,
+        group_threshold: 1,
+        group_count: 1,
+    };
+    let encrypted_secret = encrypt(master_secret, passphrase, &parameters);
+
+    let shares = split_secret(threshold, number_of_shares, &encrypted_secret)
+        .into_iter()
+        .map(|(member_index, value)| {
+            Share {
+                parameters,
+                group_index: 0,
+                member_index,
+                member_threshold: threshold as u8,
+                value,
+            }
+            .to_mnemonic()
+        })
+        .collect();
+
+    Ok(shares)
+}
+
+/// Restores the master secret from the mnemonics, one share per string
+pub fn combine(mnemonics: &[String], passphrase: &str) -> Result<Vec<u8>, Slip39Error> {
+    check_passphrase(passphrase)?;
+
+    let shares = mnemonics
+        .iter()
+        .enumerate()
+        .map(|(position, mnemonic)| Share::from_mnemonic(position + 1, mnemonic))
+        .collect::<Result<Vec<Share>, Slip39Error>>()?;
+
+    let parameters = match shares.first() {
+        Some(share) => share.parameters,
+        None => {
+            return Err(Slip39Error::NotEnoughShares {
+                found: 0,
+                required: 1,
+            })
+        }
+    };
+    if shares.iter().any(|share| share.parameters != parameters) {
+        return Err(Slip39Error::MixedShares);
+    }
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in &shares {
+        let members = groups.entry(share.group_index).or_default();
+        match members
+            .iter()
+            .find(|member| member.member_index == share.member_index)
+        {
+            Some(member) if member.value != share.value => return Err(Slip39Error::MixedShares),
+            Some(_) => continue,
+            None => members.push(share),
+        }
+    }
+
+    let mut group_shares = vec![];
+    for (group_index, members) in &groups {
+        let member_threshold = members[0].member_threshold;
+        if members
+            .iter()
+            .any(|member| member.member_threshold != member_threshold)
+        {
+            return Err(Slip39Error::MixedShares);
+        }
+
+        if members.len() >= usize::from(member_threshold) {
+            let member_shares: Vec<(u8, Vec<u8>)> = members
+                .iter()
+                .take(usize::from(member_threshold))
+                .map(|member| (member.member_index, member.value.clone()))
+                .collect();
+            group_shares.push((*group_index, recover_secret(&member_shares)?));
+        }
+    }
+
+    let group_threshold = usize::from(parameters.group_threshold);
+    if group_shares.len() < group_threshold {
+        return Err(if group_threshold == 1 {
+            let (found, required) = groups
+                .values()
+                .map(|members| (members.len(), usize::from(members[0].member_threshold)))
+                .max()
+                .unwrap_or((0, 1));
+            Slip39Error::NotEnoughShares { found, required }
+        } else {
+            Slip39Error::NotEnoughGroups {
+                found: group_shares.len(),
+                required: group_threshold,
+            }
+        });
+    }
+
+    group_shares.truncate(group_threshold);
+    let encrypted_secret = recover_secret(&group_shares)?;
+    Ok(decrypt(&encrypted_secret, passphrase, &parameters))
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), Slip39Error> {
+    if passphrase.bytes().all(|byte| (32..=126).contains(&byte)) {
+        Ok(())
+    } else {
+        Err(Slip39Error::InvalidPassphrase)
+    }
+}
+
+/// Common to all shares of a secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Parameters {
+    identifier: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    group_threshold: u8,
+    group_count: u8,
+}
+
+struct Share {
+    parameters: Parameters,
+    group_index: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Vec<u8>,
+}
+
+impl Share {
+    fn to_mnemonic(&self) -> String {
+        let mut bits = Bits::default();
+        bits.push(self.parameters.identifier.into(), ID_BITS);
+        bits.push(self.parameters.extendable.into(), 1);
+        bits.push(
+            self.parameters.iteration_exponent.into(),
+            ITERATION_EXPONENT_BITS,
+        );
+        bits.push(self.group_index.into(), INDEX_BITS);
+        bits.push((self.parameters.group_threshold - 1).into(), INDEX_BITS);
+        bits.push((self.parameters.group_count - 1).into(), INDEX_BITS);
+        bits.push(self.member_index.into(), INDEX_BITS);
+        bits.push((self.member_threshold - 1).into(), INDEX_BITS);
+
+        // the value is padded with zero bits on the left up to whole words
+        let value_bits = self.value.len() * 8;
+        bits.push(0, value_bits.div_ceil(RADIX_BITS) * RADIX_BITS - value_bits);
+        for byte in &self.value {
+            bits.push((*byte).into(), 8);
+        }
+
+        let mut indices = bits.words();
+        indices.extend(checksum(
+            customization(self.parameters.extendable),
+            &indices,
+        ));
+
+        let word_list = word_list();
+        let words: Vec<&str> = indices
+            .iter()
+            .map(|index| word_list[*index as usize])
+            .collect();
+        words.join(" ")
+    }
+
+    /// `share` is the 1-based position of the mnemonic, for the errors
+    fn from_mnemonic(share: usize, mnemonic: &str) -> Result<Share, Slip39Error> {
+        let word_list = word_list();
+        let indices = mnemonic
+            .split_whitespace()
+            .enumerate()
+            .map(|(position, word)| {
+                let word = word.to_lowercase();
+                word_list
+                    .binary_search(&word.as_str())
+                    .map(|index| index as u16)
+                    .map_err(|_| Slip39Error::UnknownWord {
+                        share,
+                        position: position + 1,
+                        word,
+                    })
+            })
+            .collect::<Result<Vec<u16>, Slip39Error>>()?;
+
+        let value_words = indices
+            .len()
+            .saturating_sub(METADATA_WORDS + CHECKSUM_WORDS);
+        let padding = (value_words * RADIX_BITS) % 16;
+        if indices.len() < MIN_WORDS || padding > 8 {
+            return Err(Slip39Error::InvalidLength {
+                share,
+                words: indices.len(),
+            });
+        }
+
+        let mut bits = Bits::from_words(&indices[..indices.len() - CHECKSUM_WORDS]);
+        let identifier = bits.take(ID_BITS) as u16;
+        let extendable = bits.take(1) == 1;
+
+        if checksum_value(customization(extendable), &indices) != 1 {
+            return Err(Slip39Error::InvalidChecksum { share });
+        }
+
+        let iteration_exponent = bits.take(ITERATION_EXPONENT_BITS) as u8;
+        let group_index = bits.take(INDEX_BITS) as u8;
+        let group_threshold = bits.take(INDEX_BITS) as u8 + 1;
+        let group_count = bits.take(INDEX_BITS) as u8 + 1;
+        let member_index = bits.take(INDEX_BITS) as u8;
+        let member_threshold = bits.take(INDEX_BITS) as u8 + 1;
+
+        if bits.take(padding) != 0 {
+            return Err(Slip39Error::InvalidPadding { share });
+        }
+        let value_size = (value_words * RADIX_BITS - padding) / 8;
+        let value = (0..value_size).map(|_| bits.take(8) as u8).collect();
+
+        if group_threshold > group_count {
+            return Err(Slip39Error::MixedShares);
+        }
+
+        Ok(Share {
+            parameters: Parameters {
+                identifier,
+                extendable,
+                iteration_exponent,
+                group_threshold,
+                group_count,
+            },
+            group_index,
+            member_index,
+            member_threshold,
+            value,
+        })
+    }
+}
+
+fn word_list() -> Vec<&'static str> {
+    WORD_LIST.lines().collect()
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable {
+        EXTENDABLE_CUSTOMIZATION
+    } else {
+        CUSTOMIZATION
+    }
+}
+
+/// Bit string, most significant bit first
+#[derive(Default)]
+struct Bits {
+    bits: Vec<bool>,
+    position: usize,
+}
+
+impl Bits {
+    fn from_words(indices: &[u16]) -> Self {
+        let mut bits = Bits::default();
+        for index in indices {
+            bits.push((*index).into(), RADIX_BITS);
+        }
+        bits
+    }
+
+    fn push(&mut self, value: u32, size: usize) {
+        for bit in (0..size).rev() {
+            self.bits.push((value >> bit) & 1 == 1);
+        }
+    }
+
+    fn take(&mut self, size: usize) -> u32 {
+        let value = self.bits[self.position..self.position + size]
+            .iter()
+            .fold(0, |value, bit| (value << 1) | u32::from(*bit));
+        self.position += size;
+        value
+    }
+
+    fn words(&mut self) -> Vec<u16> {
+        (0..self.bits.len() / RADIX_BITS)
+            .map(|_| self.take(RADIX_BITS) as u16)
+            .collect()
+    }
+}
+
+/// Reed-Solomon code over GF(1024) of SLIP-39, it's 1 for valid mnemonics
+fn checksum_value(customization: &[u8], indices: &[u16]) -> u32 {
+    const GENERATOR: [u32; 10] = [
+        0xE0_E040,
+        0x1C1_C080,
+        0x383_8100,
+        0x707_0200,
+        0xE0E_0009,
+        0x1C0C_2412,
+        0x3808_6C24,
+        0x3090_FC48,
+        0x21B1_F890,
+        0x3F3_F120,
+    ];
+
+    let values = customization
+        .iter()
+        .map(|byte| u32::from(*byte))
+        .chain(indices.iter().map(|index| u32::from(*index)));
+
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 20;
+        checksum = ((checksum & 0xF_FFFF) << 10) ^ value;
+        for (bit, generator) in GENERATOR.iter().enumerate() {
+            if (top >> bit) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn checksum(customization: &[u8], indices: &[u16]) -> Vec<u16> {
+    let mut padded = indices.to_vec();
+    padded.extend([0; CHECKSUM_WORDS]);
+    let checksum = checksum_value(customization, &padded) ^ 1;
+
+    (0..CHECKSUM_WORDS)
+        .rev()
+        .map(|word| ((checksum >> (RADIX_BITS * word)) & 0x3FF) as u16)
+        .collect()
+}
+
+fn split_secret(threshold: usize, number_of_shares: usize, secret: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    if threshold == 1 {
+        return (0..number_of_shares)
+            .map(|index| (index as u8, secret.to_vec()))
+            .collect();
+    }
+
+    let random_bytes = |size: usize| {
+        let mut bytes = vec![0u8; size];
+        getrandom::getrandom(&mut bytes).expect("Random number generator failed");
+        bytes
+    };
+
+    let random_share_count = threshold - 2;
+    let mut shares: Vec<(u8, Vec<u8>)> = (0..random_share_count)
+        .map(|index| (index as u8, random_bytes(secret.len())))
+        .collect();
+
+    let random_part = random_bytes(secret.len() - DIGEST_SIZE);
+    let mut digest_share = digest(&random_part, secret);
+    digest_share.extend(&random_part);
+
+    let mut base_shares = shares.clone();
+    base_shares.push((DIGEST_INDEX, digest_share));
+    base_shares.push((SECRET_INDEX, secret.to_vec()));
+
+    for index in random_share_count..number_of_shares {
+        shares.push((index as u8, interpolate(&base_shares, index as u8)));
+    }
+    shares
+}
+
+fn recover_secret(shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>, Slip39Error> {
+    if shares.len() == 1 {
+        return Ok(shares[0].1.clone());
+    }
+
+    let secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    let (expected_digest, random_part) = digest_share.split_at(DIGEST_SIZE);
+    if digest(random_part, &secret) != expected_digest {
+        return Err(Slip39Error::InvalidDigest);
+    }
+
+    Ok(secret)
+}
+
+fn digest(random_part: &[u8], secret: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random_part).expect("Hmac takes keys of any size");
+    mac.update(secret);
+    mac.finalize().into_bytes()[..DIGEST_SIZE].to_vec()
+}
+
+/// Lagrange interpolation in GF(256) (the field of AES), byte by byte
+fn interpolate(shares: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = shares.iter().find(|(share_x, _)| *share_x == x) {
+        return value.clone();
+    }
+
+    let (exp, log) = gf256_tables();
+    let log_product: usize = shares
+        .iter()
+        .map(|(share_x, _)| usize::from(log[usize::from(share_x ^ x)]))
+        .sum();
+
+    let mut result = vec![0u8; shares[0].1.len()];
+    for (share_x, value) in shares {
+        let log_denominator: usize = shares
+            .iter()
+            .map(|(other_x, _)| usize::from(log[usize::from(share_x ^ other_x)]))
+            .sum();
+        let log_basis = (log_product + 255 * (shares.len() + 1)
+            - usize::from(log[usize::from(share_x ^ x)])
+            - log_denominator)
+            % 255;
+
+        for (result, byte) in result.iter_mut().zip(value) {
+            if *byte != 0 {
+                *result ^= exp[(usize::from(log[usize::from(*byte)]) + log_basis) % 255];
+            }
+        }
+    }
+    result
+}
+
+/// Powers and logarithms of the generator 3, modulo x^8 + x^4 + x^3 + x + 1
+fn gf256_tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0u8; 255];
+    let mut log = [0u8; 256];
+
+    let mut power: u16 = 1;
+    for (exponent, value) in exp.iter_mut().enumerate() {
+        *value = power as u8;
+        log[usize::from(power)] = exponent as u8;
+
+        power ^= power << 1;
+        if power & 0x100 != 0 {
+            power ^= 0x11B;
+        }
+    }
+    (exp, log)
+}
+
+/// Four round Feistel network, the round function is pbkdf2 of the passphrase
+fn encrypt(master_secret: &[u8], passphrase: &str, parameters: &Parameters) -> Vec<u8> {
+    let (left, right) = master_secret.split_at(master_secret.len() / 2);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+
+    for round in 0..ROUND_COUNT {
+        let next_right = xor(
+            &left,
+            &round_function(round, passphrase, parameters, &right),
+        );
+        left = right;
+        right = next_right;
+    }
+
+    [right, left].concat()
+}
+
+fn decrypt(encrypted_secret: &[u8], passphrase: &str, parameters: &Parameters) -> Vec<u8> {
+    let (left, right) = encrypted_secret.split_at(encrypted_secret.len() / 2);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+
+    for round in (0..ROUND_COUNT).rev() {
+        let next_right = xor(
+            &left,
+            &round_function(round, passphrase, parameters, &right),
+        );
+        left = right;
+        right = next_right;
+    }
+
+    [right, left].concat()
+}
+
+fn round_function(round: u8, passphrase: &str, parameters: &Parameters, data: &[u8]) -> Vec<u8> {
+    let mut password = vec![round];
+    password.extend(passphrase.as_bytes());
+
+    // extendable shares don't depend on the identifier, so new shares can be added to an existing set
+    let mut salt = vec![];
+    if !parameters.extendable {
+        salt.extend(CUSTOMIZATION);
+        salt.extend(parameters.identifier.to_be_bytes());
+    }
+    salt.extend(data);
+
+    let iterations =
+        (BASE_ITERATION_COUNT << parameters.iteration_exponent) / u32::from(ROUND_COUNT);
+    pbkdf2_sha256(&password, &salt, iterations, data.len())
+}
+
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, size: usize) -> Vec<u8> {
+    let hmac = |data: &[&[u8]]| {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(password).expect("Hmac takes keys of any size");
+        for part in data {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes()
+    };
+
+    let mut output = vec![];
+    for block in 1..=(size.div_ceil(32) as u32) {
+        let mut u = hmac(&[salt, &block.to_be_bytes()]);
+        let mut result = u;
+        for _ in 1..iterations {
+            u = hmac(&[&u]);
+            for (result, byte) in result.iter_mut().zip(&u) {
+                *result ^= byte;
+            }
+        }
+        output.extend(&result);
+    }
+
+    output.truncate(size);
+    output
+}
+
+fn xor(left: &[u8], right: &[u8]) -> Vec<u8> {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| left ^ right)
+        .collect()
+}
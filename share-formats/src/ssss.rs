@@ -0,0 +1,438 @@
+//! Shares of `ssss-split` / `ssss-combine` (http://point-at-infinity.org/ssss/).
+//!
+//! A share is a text line `[token-]index-hex`. ssss works in GF(2^n), n being 8 times the length of the secret
+//! (up to 1024 bits), the field is defined by the irreducible pentanomials of the table below.
+//! The polynomial of the shares is monic: `y = x^t + c(t-1) x^(t-1) + ... + c(1) x + secret`.
+//! Secrets of 64 bits and more are scrambled with a diffusion layer (XTEA with a zero key) before splitting,
+//! unless ssss has been run with `-D`.
+//!
+//! The field has nothing in common with the shares of meta secret, so the shares can't be converted one by one:
+//! the secret has to be restored from the threshold number of shares and split again.
+
+use std::fmt::Write;
+
+const MIN_DEGREE: usize = 8;
+const MAX_DEGREE: usize = 1024;
+const MIN_DIFFUSION_DEGREE: usize = 64;
+const DIFFUSION_ROUNDS: usize = 40;
+const MAX_SHARES: usize = 255;
+
+/// Coefficients `a, b, c` of the irreducible polynomials `x^n + x^a + x^b + x^c + 1`, for n = 8, 16, ..., 1024
+#[rustfmt::skip]
+const IRREDUCIBLE_COEFFICIENTS: [[usize; 3]; MAX_DEGREE / 8] = [
+    [4, 3, 1], [5, 3, 1], [4, 3, 1], [7, 3, 2], [5, 4, 3], [5, 3, 2], [7, 4, 2], [4, 3, 1],
+    [10, 9, 3], [9, 4, 2], [7, 6, 2], [10, 9, 6], [4, 3, 1], [5, 4, 3], [4, 3, 1], [7, 2, 1],
+    [5, 3, 2], [7, 4, 2], [6, 3, 2], [5, 3, 2], [15, 3, 2], [11, 3, 2], [9, 8, 7], [7, 2, 1],
+    [5, 3, 2], [9, 3, 1], [7, 3, 1], [9, 8, 3], [9, 4, 2], [8, 5, 3], [15, 14, 10], [10, 5, 2],
+    [9, 6, 2], [9, 3, 2], [9, 5, 2], [11, 10, 1], [7, 3, 2], [11, 2, 1], [9, 7, 4], [4, 3, 1],
+    [8, 3, 1], [7, 4, 1], [7, 2, 1], [13, 11, 6], [5, 3, 2], [7, 3, 2], [8, 7, 5], [12, 3, 2],
+    [13, 10, 6], [5, 3, 2], [5, 3, 2], [9, 5, 2], [9, 7, 2], [13, 4, 3], [4, 3, 1], [11, 6, 4],
+    [18, 9, 6], [19, 18, 13], [11, 3, 2], [15, 9, 6], [4, 3, 1], [16, 5, 2], [15, 14, 6], [8, 5, 2],
+    [15, 11, 2], [11, 6, 2], [7, 5, 3], [8, 3, 1], [19, 16, 9], [11, 9, 6], [15, 7, 6], [13, 4, 3],
+    [14, 13, 3], [13, 6, 3], [9, 5, 2], [19, 13, 6], [19, 10, 3], [11, 6, 5], [9, 2, 1], [14, 3, 2],
+    [13, 3, 1], [7, 5, 4], [11, 9, 8], [11, 6, 5], [23, 16, 9], [19, 14, 6], [23, 10, 2], [8, 3, 2],
+    [5, 4, 3], [9, 6, 4], [4, 3, 2], [13, 8, 6], [13, 11, 1], [13, 10, 3], [11, 6, 5], [19, 17, 4],
+    [15, 14, 7], [13, 9, 6], [9, 7, 3], [9, 7, 1], [14, 3, 2], [11, 8, 2], [11, 6, 4], [13, 5, 2],
+    [11, 5, 1], [11, 4, 1], [19, 10, 3], [21, 10, 6], [13, 3, 1], [15, 7, 5], [19, 18, 10], [7, 5, 3],
+    [12, 7, 2], [7, 5, 1], [14, 9, 6], [10, 3, 2], [15, 13, 12], [12, 11, 9], [16, 9, 7], [12, 9, 3],
+    [9, 5, 2], [17, 10, 6], [24, 9, 3], [17, 15, 13], [5, 4, 3], [19, 17, 8], [15, 6, 3], [19, 6, 1],
+];
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SsssError {
+    #[error("ssss secrets are 1 to 128 bytes long (8 to 1024 bits), the secret has {size} bytes")]
+    SecretLength { size: usize },
+    #[error("ssss needs a threshold of at least 2 and at most the number of shares (up to 255), got {threshold} of {number_of_shares}")]
+    InvalidThreshold {
+        threshold: usize,
+        number_of_shares: usize,
+    },
+    #[error("Invalid ssss share: '{share}'. Expected: [token-]index-hex, the hex part is 2 to 256 characters")]
+    InvalidShare { share: String },
+    #[error("The shares have different lengths or tokens, they belong to different secrets")]
+    MixedShares,
+    #[error("Share {index} is given twice")]
+    DuplicateShare { index: usize },
+    #[error("Not enough shares: found {found}, the threshold is {required}. ssss shares don't store the threshold, it has to be given")]
+    NotEnoughShares { found: usize, required: usize },
+}
+
+/// Splits the secret into `number_of_shares` lines of `ssss-split -t threshold -n number_of_shares`
+pub fn split(
+    secret: &[u8],
+    threshold: usize,
+    number_of_shares: usize,
+    diffusion: bool,
+) -> Result<Vec<String>, SsssError> {
+    let degree = secret.len() * 8;
+    if !(MIN_DEGREE..=MAX_DEGREE).contains(&degree) {
+        return Err(SsssError::SecretLength { size: secret.len() });
+    }
+    if threshold < 2 || threshold > number_of_shares || number_of_shares > MAX_SHARES {
+        return Err(SsssError::InvalidThreshold {
+            threshold,
+            number_of_shares,
+        });
+    }
+
+    let field = Field::new(degree);
+    let mut secret = secret.to_vec();
+    if diffusion && degree >= MIN_DIFFUSION_DEGREE {
+        diffuse(&mut secret, Direction::Encode);
+    }
+
+    let mut coefficients = vec![field.element_of_bytes(&secret)];
+    for _ in 1..threshold {
+        let mut random = vec![0u8; secret.len()];
+        getrandom::getrandom(&mut random).expect("Random number generator failed");
+        coefficients.push(field.element_of_bytes(&random));
+    }
+
+    let index_width = number_of_shares.to_string().len();
+    let shares = (1..=number_of_shares)
+        .map(|index| {
+            let x = field.element_of_index(index);
+
+            // horner scheme of the monic polynomial
+            let mut y = x.clone();
+            for coefficient in coefficients[1..].iter().rev() {
+                y = field.mul(&field.add(&y, coefficient), &x);
+            }
+            let y = field.add(&y, &coefficients[0]);
+
+            let mut share = format!("{:0width$}-", index, width = index_width);
+            for byte in field.to_bytes(&y) {
+                write!(share, "{:02x}", byte).expect("Writing into a string");
+            }
+            share
+        })
+        .collect();
+
+    Ok(shares)
+}
+
+/// Restores the secret of `ssss-combine -t threshold` from the first `threshold` shares.
+/// The secret has the full length of the shares: a secret split with a higher security level (`-s`)
+/// than its length is prefixed with zero bytes
+pub fn combine(shares: &[String], threshold: usize, diffusion: bool) -> Result<Vec<u8>, SsssError> {
+    let shares = shares
+        .iter()
+        .map(|share| parse_share(share))
+        .collect::<Result<Vec<ParsedShare>, SsssError>>()?;
+
+    if threshold < 2 {
+        return Err(SsssError::InvalidThreshold {
+            threshold,
+            number_of_shares: shares.len(),
+        });
+    }
+    if shares.len() < threshold {
+        return Err(SsssError::NotEnoughShares {
+            found: shares.len(),
+            required: threshold,
+        });
+    }
+
+    let shares = &shares[..threshold];
+    let first = &shares[0];
+    let mixed_shares = shares
+        .iter()
+        .any(|share| share.token != first.token || share.value.len() != first.value.len());
+    if mixed_shares {
+        return Err(SsssError::MixedShares);
+    }
+    for (position, share) in shares.iter().enumerate() {
+        if shares[..position]
+            .iter()
+            .any(|other| other.index == share.index)
+        {
+            return Err(SsssError::DuplicateShare { index: share.index });
+        }
+    }
+
+    let degree = first.value.len() * 8;
+    let field = Field::new(degree);
+
+    // remove the x^t term, the rest of the polynomial has the degree t-1 and gets interpolated at 0
+    let points: Vec<(Element, Element)> = shares
+        .iter()
+        .map(|share| {
+            let x = field.element_of_index(share.index);
+            let mut x_power = field.one();
+            for _ in 0..threshold {
+                x_power = field.mul(&x_power, &x);
+            }
+            (
+                x,
+                field.add(&field.element_of_bytes(&share.value), &x_power),
+            )
+        })
+        .collect();
+
+    let mut secret = field.zero();
+    for (i, (x_i, y_i)) in points.iter().enumerate() {
+        let mut numerator = field.one();
+        let mut denominator = field.one();
+        for (j, (x_j, _)) in points.iter().enumerate() {
+            if i != j {
+                numerator = field.mul(&numerator, x_j);
+                denominator = field.mul(&denominator, &field.add(x_i, x_j));
+            }
+        }
+
+        let basis = field.mul(&numerator, &field.invert(&denominator));
+        secret = field.add(&secret, &field.mul(y_i, &basis));
+    }
+
+    let mut secret = field.to_bytes(&secret);
+    if diffusion && degree >= MIN_DIFFUSION_DEGREE {
+        diffuse(&mut secret, Direction::Decode);
+    }
+
+    Ok(secret)
+}
+
+/// The secret as ssss-combine prints it in the text mode: without the leading zero bytes
+/// of a security level (`-s`) higher than the length of the secret
+pub fn text_secret(secret: &[u8]) -> &[u8] {
+    let start = secret
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(secret.len());
+    &secret[start..]
+}
+
+struct ParsedShare {
+    token: Option<String>,
+    index: usize,
+    value: Vec<u8>,
+}
+
+fn parse_share(share: &str) -> Result<ParsedShare, SsssError> {
+    let invalid_share = || SsssError::InvalidShare {
+        share: share.to_string(),
+    };
+
+    let trimmed = share.trim();
+    let (rest, hex) = trimmed.rsplit_once('-').ok_or_else(invalid_share)?;
+    let (token, index) = match rest.rsplit_once('-') {
+        Some((token, index)) => (Some(token.to_string()), index),
+        None => (None, rest),
+    };
+
+    let index: usize = index.parse().map_err(|_| invalid_share())?;
+    let degree = hex.len() * 4;
+    if index == 0 || degree % 8 != 0 || !(MIN_DEGREE..=MAX_DEGREE).contains(&degree) {
+        return Err(invalid_share());
+    }
+
+    let value = (0..hex.len())
+        .step_by(2)
+        .map(|position| u8::from_str_radix(&hex[position..position + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| invalid_share())?;
+
+    Ok(ParsedShare {
+        token,
+        index,
+        value,
+    })
+}
+
+/// Little endian 64-bit limbs
+type Element = Vec<u64>;
+
+/// GF(2^degree)
+struct Field {
+    degree: usize,
+    limbs: usize,
+    /// Low terms of the irreducible polynomial, including the constant one
+    reduction: Element,
+}
+
+impl Field {
+    fn new(degree: usize) -> Self {
+        let limbs = degree / 64 + 1;
+        let mut reduction = vec![0u64; limbs];
+        for exponent in IRREDUCIBLE_COEFFICIENTS[degree / 8 - 1].iter().chain(&[0]) {
+            reduction[exponent / 64] |= 1 << (exponent % 64);
+        }
+
+        Field {
+            degree,
+            limbs,
+            reduction,
+        }
+    }
+
+    fn zero(&self) -> Element {
+        vec![0; self.limbs]
+    }
+
+    fn one(&self) -> Element {
+        self.element_of_index(1)
+    }
+
+    fn element_of_index(&self, index: usize) -> Element {
+        let mut element = self.zero();
+        element[0] = index as u64;
+        element
+    }
+
+    /// Big endian bytes, as the secret and the hex of the shares are written
+    fn element_of_bytes(&self, bytes: &[u8]) -> Element {
+        let mut element = self.zero();
+        for (position, byte) in bytes.iter().rev().enumerate() {
+            element[position / 8] |= u64::from(*byte) << (8 * (position % 8));
+        }
+        element
+    }
+
+    fn to_bytes(&self, element: &Element) -> Vec<u8> {
+        (0..self.degree / 8)
+            .rev()
+            .map(|position| (element[position / 8] >> (8 * (position % 8))) as u8)
+            .collect()
+    }
+
+    fn add(&self, left: &Element, right: &Element) -> Element {
+        left.iter()
+            .zip(right)
+            .map(|(left, right)| left ^ right)
+            .collect()
+    }
+
+    fn mul(&self, left: &Element, right: &Element) -> Element {
+        let mut result = self.zero();
+        for bit in (0..self.degree).rev() {
+            self.shift_left(&mut result);
+            if (right[bit / 64] >> (bit % 64)) & 1 == 1 {
+                result = self.add(&result, left);
+            }
+        }
+        result
+    }
+
+    /// a^(2^n - 2) = a^-1, 2^n - 2 = 2 + 4 + ... + 2^(n-1)
+    fn invert(&self, element: &Element) -> Element {
+        let mut square = element.clone();
+        let mut result = self.one();
+        for _ in 1..self.degree {
+            square = self.mul(&square, &square);
+            result = self.mul(&result, &square);
+        }
+        result
+    }
+
+    /// Multiplies by x and reduces by the irreducible polynomial
+    fn shift_left(&self, element: &mut Element) {
+        let mut carry = 0;
+        for limb in element.iter_mut() {
+            let next_carry = *limb >> 63;
+            *limb = (*limb << 1) | carry;
+            carry = next_carry;
+        }
+
+        let top = (element[self.degree / 64] >> (self.degree % 64)) & 1;
+        if top == 1 {
+            element[self.degree / 64] ^= 1 << (self.degree % 64);
+            for (limb, reduction) in element.iter_mut().zip(&self.reduction) {
+                *limb ^= reduction;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Encode,
+    Decode,
+}
+
+/// The diffusion layer of ssss, `data` is the big endian secret.
+/// ssss processes the number exported as 16-bit words, least significant word first,
+/// every word big endian. A secret of an odd length gets its top byte moved next to the rest
+fn diffuse(data: &mut [u8], direction: Direction) {
+    let length = data.len();
+    let padded_length = length + length % 2;
+
+    let mut padded = vec![0u8; padded_length - length];
+    padded.extend_from_slice(data);
+
+    let mut words: Vec<u8> = padded
+        .chunks(2)
+        .rev()
+        .flat_map(|word| word.iter().copied())
+        .collect();
+    if length % 2 == 1 {
+        words[length - 1] = words[length];
+    }
+
+    match direction {
+        Direction::Encode => {
+            for index in (0..DIFFUSION_ROUNDS * length).step_by(2) {
+                process_slice(&mut words[..length], index, encipher_block);
+            }
+        }
+        Direction::Decode => {
+            for index in (0..DIFFUSION_ROUNDS * length).step_by(2).rev() {
+                process_slice(&mut words[..length], index, decipher_block);
+            }
+        }
+    }
+
+    if length % 2 == 1 {
+        words[length] = words[length - 1];
+        words[length - 1] = 0;
+    }
+
+    let padded: Vec<u8> = words
+        .chunks(2)
+        .rev()
+        .flat_map(|word| word.iter().copied())
+        .collect();
+    data.copy_from_slice(&padded[padded_length - length..]);
+}
+
+fn process_slice(data: &mut [u8], index: usize, process_block: fn(&mut [u32; 2])) {
+    let length = data.len();
+    let mut block = [0u32; 2];
+    for (half, value) in block.iter_mut().enumerate() {
+        for byte in 0..4 {
+            *value = (*value << 8) | u32::from(data[(index + 4 * half + byte) % length]);
+        }
+    }
+
+    process_block(&mut block);
+
+    for (half, value) in block.iter().enumerate() {
+        for byte in 0..4 {
+            data[(index + 4 * half + byte) % length] = (value >> (24 - 8 * byte)) as u8;
+        }
+    }
+}
+
+const XTEA_DELTA: u32 = 0x9E37_79B9;
+const XTEA_ROUNDS: u32 = 32;
+
+fn xtea_mix(value: u32) -> u32 {
+    ((value << 4) ^ (value >> 5)).wrapping_add(value)
+}
+
+fn encipher_block(block: &mut [u32; 2]) {
+    let mut sum: u32 = 0;
+    for _ in 0..XTEA_ROUNDS {
+        block[0] = block[0].wrapping_add(xtea_mix(block[1]) ^ sum);
+        sum = sum.wrapping_add(XTEA_DELTA);
+        block[1] = block[1].wrapping_add(xtea_mix(block[0]) ^ sum);
+    }
+}
+
+fn decipher_block(block: &mut [u32; 2]) {
+    let mut sum = XTEA_DELTA.wrapping_mul(XTEA_ROUNDS);
+    for _ in 0..XTEA_ROUNDS {
+        block[1] = block[1].wrapping_sub(xtea_mix(block[0]) ^ sum);
+        sum = sum.wrapping_sub(XTEA_DELTA);
+        block[0] = block[0].wrapping_sub(xtea_mix(block[1]) ^ sum);
+    }
+}
@@ -0,0 +1,432 @@
+//! Verifiable secret sharing (Pedersen): every share can be checked alone against the commitments
+//! published with the shares, without any other share and without learning anything about the secret.
+//!
+//! The scheme works in the subgroup of prime order q of the 2048-bit MODP group of RFC 3526 (p = 2q + 1).
+//! The secret is cut into chunks of 254 bytes, every chunk is shared with two random polynomials of degree t - 1:
+//! `f(x) = chunk + a1 x + ...` and the blinding `r(x) = b0 + b1 x + ...`.
+//! The share of index i is `(f(i), r(i))`, the commitments are `C_j = g^a_j h^b_j`
+//! and a share is valid if `g^f(i) h^r(i) = C_0 C_1^i C_2^(i^2) ...`.
+//!
+//! The second generator h is hashed into the group, so nobody knows its discrete logarithm to the base g.
+//! Unlike Feldman commitments (`g^secret`), the commitments don't allow to brute force a weak password.
+//!
+//! Shares and commitments are written as base64 of a versioned binary format:
+//! `version | index | number of chunks (u16) | f(i), r(i) per chunk`
+//! and `version | threshold | number of chunks (u16) | C_0 .. C_(t-1) per chunk`, every number takes 256 bytes.
+
+use std::collections::HashSet;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use sha2::{Digest, Sha256};
+
+const VERSION: u8 = 1;
+const ELEMENT_SIZE: usize = 256;
+/// A chunk and its marker byte stay below q
+const CHUNK_SIZE: usize = 254;
+/// Keeps the leading zero bytes of a chunk
+const CHUNK_MARKER: u8 = 1;
+/// Random bytes of a scalar, more than the size of q to make the bias negligible
+const RANDOM_SIZE: usize = ELEMENT_SIZE + 32;
+const MAX_SHARES: usize = 255;
+/// The number of chunks is written as u16
+const MAX_SECRET_SIZE: usize = u16::MAX as usize * CHUNK_SIZE;
+const HEADER_SIZE: usize = 4;
+const FINGERPRINT_SIZE: usize = 8;
+
+/// 2048-bit MODP group of RFC 3526, section 3
+const MODP_2048: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A08798E3404DD\
+    EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F\
+    83655D23DCA3AD961C62F356208552BB9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF6955817183995497CEA956AE515D2261898FA0510\
+    15728E5A8AACAA68FFFFFFFFFFFFFFFF";
+/// 2^2 is a square, so it generates the subgroup of order q
+const GENERATOR: u32 = 4;
+/// Seed of the second generator h
+const SECOND_GENERATOR_SEED: &[u8] = b"meta-secret vss pedersen generator h";
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum VssError {
+    #[error("Verifiable sharing needs a threshold of at least 1 and at most the number of shares (up to 255), got {threshold} of {number_of_shares}")]
+    InvalidThreshold {
+        threshold: usize,
+        number_of_shares: usize,
+    },
+    #[error(
+        "The secret is too big for verifiable sharing: {size} bytes, at most {max_size} bytes"
+    )]
+    TooBig { size: usize, max_size: usize },
+    #[error("Invalid verifiable share: not a share of the verifiable sharing, or a truncated one")]
+    InvalidShare,
+    #[error("Invalid commitments of a verifiable share")]
+    InvalidCommitments,
+    #[error("Share {index} doesn't match the commitments: it is corrupted or was tampered with")]
+    ShareMismatch { index: usize },
+    #[error("The shares have different commitments, they belong to different secrets")]
+    DifferentCommitments,
+    #[error("Share {index} is given twice")]
+    DuplicateShare { index: usize },
+    #[error("Not enough shares: found {found}, at least {required} required")]
+    NotEnoughShares { found: usize, required: usize },
+    #[error("The shares don't restore a valid secret, they belong to different secrets")]
+    MixedShares,
+}
+
+/// Share of index `index` (1 to 255) of every chunk of the secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    index: usize,
+    /// `(f(i), r(i))` of every chunk
+    chunks: Vec<(BigUint, BigUint)>,
+}
+
+/// Commitments to the coefficients of the polynomials of every chunk, the same for all the shares of a secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commitments {
+    threshold: usize,
+    chunks: Vec<Vec<BigUint>>,
+}
+
+/// The group and its two generators
+struct Group {
+    p: BigUint,
+    q: BigUint,
+    g: BigUint,
+    h: BigUint,
+}
+
+/// Splits the secret into `number_of_shares` shares, any `threshold` of them restore it.
+/// Returns the shares by index (1, 2, ...) and the commitments to publish with them
+pub fn split(
+    secret: &[u8],
+    threshold: usize,
+    number_of_shares: usize,
+) -> Result<(Vec<Share>, Commitments), VssError> {
+    if threshold < 1 || threshold > number_of_shares || number_of_shares > MAX_SHARES {
+        return Err(VssError::InvalidThreshold {
+            threshold,
+            number_of_shares,
+        });
+    }
+    if secret.len() > MAX_SECRET_SIZE {
+        return Err(VssError::TooBig {
+            size: secret.len(),
+            max_size: MAX_SECRET_SIZE,
+        });
+    }
+
+    let group = Group::new();
+    let mut chunks: Vec<&[u8]> = secret.chunks(CHUNK_SIZE).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+
+    let mut shares: Vec<Share> = (1..=number_of_shares)
+        .map(|index| Share {
+            index,
+            chunks: vec![],
+        })
+        .collect();
+    let mut commitments = Commitments {
+        threshold,
+        chunks: vec![],
+    };
+
+    for chunk in chunks {
+        let mut marked_chunk = vec![CHUNK_MARKER];
+        marked_chunk.extend_from_slice(chunk);
+
+        let mut coefficients = vec![BigUint::from_bytes_be(&marked_chunk)];
+        coefficients.extend((1..threshold).map(|_| group.random_scalar()));
+        let blinding: Vec<BigUint> = (0..threshold).map(|_| group.random_scalar()).collect();
+
+        commitments.chunks.push(
+            coefficients
+                .iter()
+                .zip(&blinding)
+                .map(|(coefficient, blinding)| group.commit(coefficient, blinding))
+                .collect(),
+        );
+
+        for share in &mut shares {
+            let x = BigUint::from(share.index);
+            share.chunks.push((
+                group.evaluate(&coefficients, &x),
+                group.evaluate(&blinding, &x),
+            ));
+        }
+    }
+
+    Ok((shares, commitments))
+}
+
+/// Checks the share against the commitments, no other share is needed
+pub fn check(share: &Share, commitments: &Commitments) -> Result<(), VssError> {
+    let mismatch = VssError::ShareMismatch { index: share.index };
+    if share.chunks.len() != commitments.chunks.len() {
+        return Err(mismatch);
+    }
+
+    let group = Group::new();
+    let x = BigUint::from(share.index);
+    for ((value, blinding), chunk_commitments) in share.chunks.iter().zip(&commitments.chunks) {
+        // C_0 * C_1^i * C_2^(i^2) * ...
+        let mut expected = BigUint::one();
+        let mut power = BigUint::one();
+        for commitment in chunk_commitments {
+            expected = expected * commitment.modpow(&power, &group.p) % &group.p;
+            power = power * &x % &group.q;
+        }
+
+        if group.commit(value, blinding) != expected {
+            return Err(mismatch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Restores the secret from `threshold` shares or more, the shares are checked against the commitments first
+pub fn combine(shares: &[Share], commitments: &Commitments) -> Result<Vec<u8>, VssError> {
+    let mut indices = HashSet::new();
+    for share in shares {
+        if !indices.insert(share.index) {
+            return Err(VssError::DuplicateShare { index: share.index });
+        }
+        check(share, commitments)?;
+    }
+
+    if shares.len() < commitments.threshold {
+        return Err(VssError::NotEnoughShares {
+            found: shares.len(),
+            required: commitments.threshold,
+        });
+    }
+
+    let group = Group::new();
+    let shares = &shares[..commitments.threshold];
+    let xs: Vec<BigUint> = shares
+        .iter()
+        .map(|share| BigUint::from(share.index))
+        .collect();
+
+    // lagrange coefficients of the polynomial at x = 0
+    let lagrange: Vec<BigUint> = xs
+        .iter()
+        .enumerate()
+        .map(|(i, x_i)| {
+            let mut numerator = BigUint::one();
+            let mut denominator = BigUint::one();
+            for (j, x_j) in xs.iter().enumerate() {
+                if i != j {
+                    numerator = numerator * x_j % &group.q;
+                    denominator = denominator * ((x_j + &group.q - x_i) % &group.q) % &group.q;
+                }
+            }
+            numerator * group.invert(&denominator) % &group.q
+        })
+        .collect();
+
+    let mut secret = vec![];
+    for chunk_index in 0..commitments.chunks.len() {
+        let chunk =
+            shares
+                .iter()
+                .zip(&lagrange)
+                .fold(BigUint::zero(), |chunk, (share, coefficient)| {
+                    (chunk + &share.chunks[chunk_index].0 * coefficient) % &group.q
+                });
+
+        let chunk = chunk.to_bytes_be();
+        match chunk.split_first() {
+            Some((&CHUNK_MARKER, chunk)) if chunk.len() <= CHUNK_SIZE => {
+                secret.extend_from_slice(chunk)
+            }
+            _ => return Err(VssError::MixedShares),
+        }
+    }
+
+    Ok(secret)
+}
+
+impl Share {
+    /// Index of the share, starting from 1
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn encode(&self) -> String {
+        let mut bytes = header(self.index, self.chunks.len());
+        for (value, blinding) in &self.chunks {
+            bytes.extend(element_bytes(value));
+            bytes.extend(element_bytes(blinding));
+        }
+        BASE64.encode(bytes)
+    }
+
+    pub fn decode(text: &str) -> Result<Self, VssError> {
+        let bytes = BASE64
+            .decode(text.trim())
+            .map_err(|_| VssError::InvalidShare)?;
+        let (index, elements) = parse(&bytes, 2).ok_or(VssError::InvalidShare)?;
+        if index == 0 {
+            return Err(VssError::InvalidShare);
+        }
+
+        let q = Group::new().q;
+        if elements.iter().any(|element| *element >= q) {
+            return Err(VssError::InvalidShare);
+        }
+
+        let mut elements = elements.into_iter();
+        let mut chunks = vec![];
+        while let (Some(value), Some(blinding)) = (elements.next(), elements.next()) {
+            chunks.push((value, blinding));
+        }
+
+        Ok(Share { index, chunks })
+    }
+}
+
+impl Commitments {
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn encode(&self) -> String {
+        BASE64.encode(self.to_bytes())
+    }
+
+    pub fn decode(text: &str) -> Result<Self, VssError> {
+        let bytes = BASE64
+            .decode(text.trim())
+            .map_err(|_| VssError::InvalidCommitments)?;
+        let threshold = bytes.get(1).copied().unwrap_or(0) as usize;
+        if threshold == 0 {
+            return Err(VssError::InvalidCommitments);
+        }
+        let (_, elements) = parse(&bytes, threshold).ok_or(VssError::InvalidCommitments)?;
+
+        let p = Group::new().p;
+        if elements
+            .iter()
+            .any(|element| element.is_zero() || *element >= p)
+        {
+            return Err(VssError::InvalidCommitments);
+        }
+
+        let chunks = elements
+            .chunks(threshold)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        Ok(Commitments { threshold, chunks })
+    }
+
+    /// Short hash of the commitments, for the holders to compare over the phone or on paper:
+    /// the shares of one secret have the same fingerprint
+    pub fn fingerprint(&self) -> String {
+        let hash = Sha256::digest(self.to_bytes());
+        hash[..FINGERPRINT_SIZE]
+            .chunks(2)
+            .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(self.threshold, self.chunks.len());
+        for commitment in self.chunks.iter().flatten() {
+            bytes.extend(element_bytes(commitment));
+        }
+        bytes
+    }
+}
+
+impl Group {
+    fn new() -> Self {
+        let p = BigUint::parse_bytes(MODP_2048.as_bytes(), 16).expect("Valid group prime");
+        let q = (&p - 1u32) >> 1;
+
+        // sha256 stretched to the size of p, squared to land in the subgroup of order q
+        let mut seed = vec![];
+        for counter in 0..(ELEMENT_SIZE / 32) as u8 {
+            let mut hasher = Sha256::new();
+            hasher.update(SECOND_GENERATOR_SEED);
+            hasher.update([counter]);
+            seed.extend(hasher.finalize());
+        }
+        let h = BigUint::from_bytes_be(&seed).modpow(&BigUint::from(2u32), &p);
+
+        Group {
+            g: BigUint::from(GENERATOR),
+            h,
+            p,
+            q,
+        }
+    }
+
+    /// `g^value h^blinding`
+    fn commit(&self, value: &BigUint, blinding: &BigUint) -> BigUint {
+        self.g.modpow(value, &self.p) * self.h.modpow(blinding, &self.p) % &self.p
+    }
+
+    /// Horner scheme, modulo q
+    fn evaluate(&self, coefficients: &[BigUint], x: &BigUint) -> BigUint {
+        coefficients
+            .iter()
+            .rev()
+            .fold(BigUint::zero(), |y, coefficient| {
+                (y * x + coefficient) % &self.q
+            })
+    }
+
+    /// q is a prime: a^(q-2) = a^-1
+    fn invert(&self, value: &BigUint) -> BigUint {
+        value.modpow(&(&self.q - 2u32), &self.q)
+    }
+
+    fn random_scalar(&self) -> BigUint {
+        let mut random = [0u8; RANDOM_SIZE];
+        getrandom::getrandom(&mut random).expect("Random number generator failed");
+        BigUint::from_bytes_be(&random) % &self.q
+    }
+}
+
+/// `split` and the decoding keep the number of shares and chunks in range
+fn header(number: usize, chunks: usize) -> Vec<u8> {
+    let number = u8::try_from(number).expect("At most 255 shares");
+    let chunks = u16::try_from(chunks).expect("At most u16::MAX chunks");
+    let [high, low] = chunks.to_be_bytes();
+    vec![VERSION, number, high, low]
+}
+
+fn element_bytes(element: &BigUint) -> Vec<u8> {
+    let bytes = element.to_bytes_be();
+    let mut padded = vec![0u8; ELEMENT_SIZE - bytes.len()];
+    padded.extend(bytes);
+    padded
+}
+
+/// Header number and the elements, `per_chunk` elements per chunk
+fn parse(bytes: &[u8], per_chunk: usize) -> Option<(usize, Vec<BigUint>)> {
+    if bytes.len() < HEADER_SIZE || bytes[0] != VERSION {
+        return None;
+    }
+
+    let number = bytes[1] as usize;
+    let chunks = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+    let body = &bytes[HEADER_SIZE..];
+    if chunks == 0 || body.len() != chunks * per_chunk * ELEMENT_SIZE {
+        return None;
+    }
+
+    let elements = body
+        .chunks(ELEMENT_SIZE)
+        .map(BigUint::from_bytes_be)
+        .collect();
+    Some((number, elements))
+}
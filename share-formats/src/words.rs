@@ -0,0 +1,261 @@
+//! Mnemonic word lists for shares, in the style of BIP39 and SLIP-39.
+//!
+//! The data is prefixed with the format version and its length, suffixed with a checksum
+//! (first 4 bytes of sha256) and written as words of the BIP39 english word list, 11 bits per word.
+//! Every word is identified by its first 4 letters, so the words can be shortened when typed.
+//!
+//! A word that is not in the list is reported with its position. A word that is in the list
+//! but is wrong fails the checksum; then every single-word replacement is tried,
+//! and if exactly one of them makes the checksum valid, it is reported with its position.
+
+use bip39::Language;
+use sha2::{Digest, Sha256};
+
+const FORMAT_VERSION: u8 = 1;
+const CHECKSUM_SIZE: usize = 4;
+const BITS_PER_WORD: usize = 11;
+/// BIP39 words are unique by their first 4 letters
+const UNIQUE_PREFIX_SIZE: usize = 4;
+const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum WordsError {
+    #[error("Unknown word #{position}: '{word}'{}", suggestions_text(suggestions))]
+    UnknownWord {
+        /// 1-based position of the word
+        position: usize,
+        word: String,
+        suggestions: Vec<String>,
+    },
+    #[error("Word #{position} '{word}' is wrong, it's probably '{suggestion}'")]
+    WrongWord {
+        position: usize,
+        word: String,
+        suggestion: String,
+    },
+    #[error("Checksum mismatch: more than one word is wrong, or words are missing or swapped")]
+    ChecksumMismatch,
+    #[error("Too few words")]
+    TooShort,
+    #[error("Unsupported word list version: {version}")]
+    UnsupportedVersion { version: u8 },
+    #[error("The data is too big for a word list: {size} bytes")]
+    TooBig { size: usize },
+}
+
+/// Encodes the data as a list of words separated by spaces
+pub fn encode(data: &[u8]) -> Result<String, WordsError> {
+    let length = u16::try_from(data.len()).map_err(|_| WordsError::TooBig { size: data.len() })?;
+
+    let mut payload = vec![FORMAT_VERSION];
+    payload.extend_from_slice(&length.to_be_bytes());
+    payload.extend_from_slice(data);
+    let checksum = checksum(&payload);
+    payload.extend_from_slice(&checksum);
+
+    let word_list = Language::English.word_list();
+    let words: Vec<&str> = to_indices(&payload)
+        .into_iter()
+        .map(|index| word_list[index as usize])
+        .collect();
+
+    Ok(words.join(" "))
+}
+
+/// Decodes the words, separated by any whitespace. Letter case and numbering of the words (`1.`, `2)`) are ignored
+pub fn decode(text: &str) -> Result<Vec<u8>, WordsError> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .filter(|token| !is_numbering(token))
+        .map(|word| word.to_lowercase())
+        .collect();
+
+    let mut indices = vec![];
+    for (position, word) in words.iter().enumerate() {
+        let index = find_word(word).ok_or_else(|| WordsError::UnknownWord {
+            position: position + 1,
+            word: word.clone(),
+            suggestions: suggestions(word),
+        })?;
+        indices.push(index);
+    }
+
+    match verify(&indices) {
+        Ok(data) => Ok(data),
+        Err(WordsError::ChecksumMismatch) => Err(find_wrong_word(&words, &indices)),
+        Err(err) => Err(err),
+    }
+}
+
+fn verify(indices: &[u16]) -> Result<Vec<u8>, WordsError> {
+    let payload = from_indices(indices);
+    if payload.len() < 3 + CHECKSUM_SIZE {
+        return Err(WordsError::TooShort);
+    }
+
+    let length = u16::from_be_bytes([payload[1], payload[2]]) as usize;
+    let payload_size = 3 + length + CHECKSUM_SIZE;
+    if payload.len() < payload_size {
+        return Err(WordsError::ChecksumMismatch);
+    }
+
+    let (content, rest) = payload.split_at(3 + length);
+    if rest[..CHECKSUM_SIZE] != checksum(content) {
+        return Err(WordsError::ChecksumMismatch);
+    }
+
+    // no extra words and zero padding bits, so every data has exactly one word list
+    if indices != to_indices(&payload[..payload_size]) {
+        return Err(WordsError::ChecksumMismatch);
+    }
+
+    if payload[0] != FORMAT_VERSION {
+        return Err(WordsError::UnsupportedVersion {
+            version: payload[0],
+        });
+    }
+
+    Ok(content[3..].to_vec())
+}
+
+/// Tries every replacement of every word, a single wrong word is found if just one replacement fits the checksum
+fn find_wrong_word(words: &[String], indices: &[u16]) -> WordsError {
+    let word_list = Language::English.word_list();
+    let mut candidates = vec![];
+
+    let mut attempt = indices.to_vec();
+    for position in 0..indices.len() {
+        for replacement in 0..word_list.len() as u16 {
+            if replacement == indices[position] {
+                continue;
+            }
+
+            attempt[position] = replacement;
+            if verify(&attempt).is_ok() {
+                candidates.push((position, replacement));
+            }
+        }
+        attempt[position] = indices[position];
+    }
+
+    match candidates.as_slice() {
+        [(position, replacement)] => WordsError::WrongWord {
+            position: position + 1,
+            word: words[*position].clone(),
+            suggestion: word_list[*replacement as usize].to_string(),
+        },
+        _ => WordsError::ChecksumMismatch,
+    }
+}
+
+fn find_word(word: &str) -> Option<u16> {
+    if let Some(index) = Language::English.find_word(word) {
+        return Some(index);
+    }
+
+    if word.chars().count() < UNIQUE_PREFIX_SIZE {
+        return None;
+    }
+
+    // shortened word: the first 4 letters are enough
+    let prefix: String = word.chars().take(UNIQUE_PREFIX_SIZE).collect();
+    let matches = Language::English.words_by_prefix(&prefix);
+    match matches {
+        [single] if single.starts_with(word) => Language::English.find_word(single),
+        _ => None,
+    }
+}
+
+/// Words of the list that are one typo away, then the words with the same first letters
+fn suggestions(word: &str) -> Vec<String> {
+    let word_list = Language::English.word_list();
+    let prefix: String = word.chars().take(3).collect();
+
+    let one_typo_away = word_list
+        .iter()
+        .filter(|candidate| edit_distance(word, candidate) <= 1);
+    let same_prefix = word_list
+        .iter()
+        .filter(|candidate| candidate.starts_with(&prefix) && edit_distance(word, candidate) > 1);
+
+    one_typo_away
+        .chain(same_prefix)
+        .take(MAX_SUGGESTIONS)
+        .map(|candidate| candidate.to_string())
+        .collect()
+}
+
+fn suggestions_text(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+
+    format!(", did you mean: {}?", suggestions.join(", "))
+}
+
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().collect();
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+
+    for (i, left_char) in left.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left_char != *right_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[right.len()]
+}
+
+fn is_numbering(token: &str) -> bool {
+    let digits = token.trim_end_matches(['.', ')', ':']);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let hash = Sha256::digest(payload);
+    let mut checksum = [0u8; CHECKSUM_SIZE];
+    checksum.copy_from_slice(&hash[..CHECKSUM_SIZE]);
+    checksum
+}
+
+/// Splits the bytes into 11-bit word indices, the last word is padded with zero bits
+fn to_indices(payload: &[u8]) -> Vec<u16> {
+    let mut indices = vec![];
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in payload {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= BITS_PER_WORD {
+            bits -= BITS_PER_WORD;
+            indices.push(((buffer >> bits) & 0x7ff) as u16);
+        }
+    }
+
+    if bits > 0 {
+        indices.push(((buffer << (BITS_PER_WORD - bits)) & 0x7ff) as u16);
+    }
+
+    indices
+}
+
+fn from_indices(indices: &[u16]) -> Vec<u8> {
+    let mut payload = vec![];
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for index in indices {
+        buffer = (buffer << BITS_PER_WORD) | u32::from(*index);
+        bits += BITS_PER_WORD;
+        while bits >= 8 {
+            bits -= 8;
+            payload.push((buffer >> bits) as u8);
+        }
+    }
+
+    payload
+}
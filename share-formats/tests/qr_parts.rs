@@ -0,0 +1,140 @@
+use meta_secret_share_formats::qr_parts::{self, Part, PartsError};
+
+fn join_data(parts: &[Part]) -> Vec<String> {
+    qr_parts::join(parts)
+        .unwrap()
+        .into_iter()
+        .map(|share| share.data)
+        .collect()
+}
+
+const SET_ID: &str = "971c3563-76d5-443a-9074-9bc9edcbf9e6";
+
+fn parse_all(texts: &[String]) -> Vec<Part> {
+    texts
+        .iter()
+        .map(|text| qr_parts::parse(text).unwrap())
+        .collect()
+}
+
+#[test]
+fn round_trip_in_any_order() {
+    let share = format!("{{\"share\":\"{}\",\"name\":\"ключ\"}}", "x".repeat(500));
+    let texts = qr_parts::split(&share, SET_ID, 120).unwrap();
+    assert!(texts.len() > 5);
+    assert!(texts.iter().all(|text| text.len() <= 120));
+    assert!(texts.iter().all(|text| qr_parts::is_part(text)));
+
+    let mut parts = parse_all(&texts);
+    parts.reverse();
+    // a part scanned twice is fine
+    parts.push(parts[0].clone());
+    assert_eq!(join_data(&parts), vec![share]);
+}
+
+#[test]
+fn shares_of_one_set_are_told_apart() {
+    let first = qr_parts::split(&"a".repeat(300), SET_ID, 100).unwrap();
+    let second = qr_parts::split(&"b".repeat(300), SET_ID, 100).unwrap();
+
+    let mut parts = parse_all(&second);
+    parts.extend(parse_all(&first));
+    let last = parts.len() - 1;
+    parts.swap(1, last);
+    assert_eq!(join_data(&parts), vec!["b".repeat(300), "a".repeat(300)]);
+}
+
+#[test]
+fn missing_parts_are_named() {
+    let texts = qr_parts::split(&"a".repeat(300), SET_ID, 100).unwrap();
+    let parts = parse_all(&texts);
+    let number_of_parts = parts.len();
+
+    let err = qr_parts::join(&[parts[0].clone(), parts[2].clone()]).unwrap_err();
+    let expected_missing: Vec<usize> = (1..=number_of_parts)
+        .filter(|&i| i != 1 && i != 3)
+        .collect();
+    assert_eq!(
+        err,
+        PartsError::MissingParts {
+            set_id: SET_ID.to_string(),
+            checksum: parts[0].checksum.clone(),
+            parts: number_of_parts,
+            missing: expected_missing,
+        }
+    );
+    assert!(err.to_string().contains("is missing parts 2, 4"));
+}
+
+#[test]
+fn damaged_parts() {
+    let texts = qr_parts::split(&"a".repeat(300), SET_ID, 100).unwrap();
+    let mut parts = parse_all(&texts);
+
+    let mut conflicting = parts[1].clone();
+    conflicting.data.push('!');
+    let mut with_conflict = parts.clone();
+    with_conflict.push(conflicting);
+    assert!(matches!(
+        qr_parts::join(&with_conflict),
+        Err(PartsError::ConflictingParts { part: 2, .. })
+    ));
+
+    parts[1].data.replace_range(0..1, "b");
+    assert!(matches!(
+        qr_parts::join(&parts),
+        Err(PartsError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn invalid_parts() {
+    for text in [
+        "{\"version\":1}",
+        "msqr1:set:1/2:abcd",
+        "msqr1:set:3/2:0123abcd:data",
+        "msqr1:set:0/2:0123abcd:data",
+        "msqr1:set:1-2:0123abcd:data",
+        "msqr1:set:1/2:xyz:data",
+    ] {
+        assert!(
+            matches!(qr_parts::parse(text), Err(PartsError::InvalidPart { .. })),
+            "{}",
+            text
+        );
+    }
+
+    let part = qr_parts::parse("msqr1:set:2/2:0123ABCD:da:ta").unwrap();
+    assert_eq!(part.data, "da:ta");
+    assert_eq!(part.checksum, "0123abcd");
+
+    assert_eq!(
+        qr_parts::split("data", "a:b", 100),
+        Err(PartsError::InvalidSetId)
+    );
+    assert_eq!(
+        qr_parts::split("data", SET_ID, 40),
+        Err(PartsError::PartTooSmall { max_size: 40 })
+    );
+}
+
+#[test]
+fn too_many_parts() {
+    let part = format!("msqr1:set:1/{}:0123abcd:data", qr_parts::MAX_PARTS);
+    assert_eq!(qr_parts::parse(&part).unwrap().parts, qr_parts::MAX_PARTS);
+
+    for parts in [qr_parts::MAX_PARTS + 1, 99_999_999_999, usize::MAX] {
+        let part = format!("msqr1:set:1/{}:0123abcd:data", parts);
+        assert_eq!(
+            qr_parts::parse(&part),
+            Err(PartsError::TooManyParts { parts })
+        );
+    }
+
+    // what is written can always be read
+    let data = "x".repeat(qr_parts::MAX_PARTS * 100);
+    assert!(matches!(
+        qr_parts::split(&data, SET_ID, 100),
+        Err(PartsError::TooManyParts { .. })
+    ));
+}
@@ -0,0 +1,105 @@
+use meta_secret_share_formats::slip39::{self, Slip39Error};
+
+/// Test vectors of SLIP-39, the passphrase is "TREZOR"
+const PASSPHRASE: &str = "TREZOR";
+
+fn mnemonics(mnemonics: &[&str]) -> Vec<String> {
+    mnemonics
+        .iter()
+        .map(|mnemonic| mnemonic.to_string())
+        .collect()
+}
+
+#[test]
+fn combine_single_share_vector() {
+    let shares = mnemonics(&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"]);
+
+    let secret = slip39::combine(&shares, PASSPHRASE).unwrap();
+    assert_eq!(
+        secret,
+        hex::decode("bb54aac4b89dc868ba37d9cc21b2cece").unwrap()
+    );
+}
+
+#[test]
+fn combine_two_of_three_vector() {
+    let shares = mnemonics(&[
+        "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+        "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+    ]);
+
+    let secret = slip39::combine(&shares, PASSPHRASE).unwrap();
+    assert_eq!(
+        secret,
+        hex::decode("b43ceb7e57a0ea8766221624d01b0864").unwrap()
+    );
+}
+
+#[test]
+fn invalid_checksum() {
+    let shares = mnemonics(&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney"]);
+
+    assert_eq!(
+        slip39::combine(&shares, PASSPHRASE),
+        Err(Slip39Error::InvalidChecksum { share: 1 })
+    );
+}
+
+#[test]
+fn unknown_word() {
+    let shares = mnemonics(&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keybord"]);
+
+    assert_eq!(
+        slip39::combine(&shares, PASSPHRASE),
+        Err(Slip39Error::UnknownWord {
+            share: 1,
+            position: 20,
+            word: "keybord".to_string()
+        })
+    );
+}
+
+#[test]
+fn round_trip() {
+    let master_secrets = [
+        hex::decode("bb54aac4b89dc868ba37d9cc21b2cece").unwrap(),
+        b"a 32 bytes long secret to split!".to_vec(),
+    ];
+
+    for master_secret in master_secrets {
+        for passphrase in ["", PASSPHRASE] {
+            let shares = slip39::split(&master_secret, passphrase, 3, 5).unwrap();
+            assert_eq!(shares.len(), 5);
+
+            let restored = slip39::combine(&shares[1..4], passphrase).unwrap();
+            assert_eq!(restored, master_secret);
+
+            assert_eq!(
+                slip39::combine(&shares[..2], passphrase),
+                Err(Slip39Error::NotEnoughShares {
+                    found: 2,
+                    required: 3
+                })
+            );
+        }
+    }
+}
+
+#[test]
+fn secrets_that_dont_fit() {
+    assert_eq!(
+        slip39::split(b"top$ecret", "", 2, 3),
+        Err(Slip39Error::SecretLength { size: 9 })
+    );
+    assert_eq!(
+        slip39::split(b"seventeen bytes!!", "", 2, 3),
+        Err(Slip39Error::SecretLength { size: 17 })
+    );
+    assert_eq!(
+        slip39::split(&[7; 16], "", 2, 17),
+        Err(Slip39Error::InvalidThreshold {
+            threshold: 2,
+            number_of_shares: 17
+        })
+    );
+}
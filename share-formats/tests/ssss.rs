@@ -0,0 +1,97 @@
+use meta_secret_share_formats::ssss::{self, SsssError};
+
+/// Example of the ssss man page: `ssss-split -t 3 -n 5`, 184 bit security level, with diffusion
+const MAN_PAGE_SHARES: [&str; 5] = [
+    "1-1c41ef496eccfbeba439714085df8437236298da8dd824",
+    "2-fbc74a03a50e14ab406c225afb5f45c40ae11976d2b665",
+    "3-fa1c3a9c6df8af0779c36de6c33f6e36e989d0e0b91309",
+    "4-468de7d6eb36674c9cf008c8e8fc8c566537ad6301eb9e",
+    "5-4756974923c0dce0a55f4774d09ca7a4865f64f56a4ee0",
+];
+
+fn shares(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+#[test]
+fn combine_man_page_example() {
+    let secret = ssss::combine(&shares(&MAN_PAGE_SHARES[..3]), 3, true).unwrap();
+    assert_eq!(secret, b"my secret root password");
+
+    let secret = ssss::combine(&shares(&MAN_PAGE_SHARES[2..]), 3, true).unwrap();
+    assert_eq!(secret, b"my secret root password");
+}
+
+#[test]
+fn combine_with_token() {
+    let with_token: Vec<String> = MAN_PAGE_SHARES
+        .iter()
+        .map(|share| format!("backup-{}", share))
+        .collect();
+
+    let secret = ssss::combine(&with_token[1..4], 3, true).unwrap();
+    assert_eq!(secret, b"my secret root password");
+}
+
+#[test]
+fn round_trip() {
+    let secrets: [&[u8]; 5] = [
+        b"x",
+        b"top$ecret",
+        b"0123456789abcdef",
+        b"an odd length secret, long enough for the diffusion layer",
+        &[0xA5; 128],
+    ];
+
+    for secret in secrets {
+        for diffusion in [true, false] {
+            let shares = ssss::split(secret, 3, 5, diffusion).unwrap();
+            assert_eq!(shares.len(), 5);
+            assert!(shares[0].starts_with("1-"));
+
+            let restored = ssss::combine(&shares[2..], 3, diffusion).unwrap();
+            assert_eq!(restored, secret);
+        }
+    }
+}
+
+#[test]
+fn share_index_width() {
+    let shares = ssss::split(b"top$ecret", 2, 12, true).unwrap();
+    assert!(shares[0].starts_with("01-"));
+    assert!(shares[11].starts_with("12-"));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        ssss::split(&[], 2, 3, true),
+        Err(SsssError::SecretLength { size: 0 })
+    );
+    assert_eq!(
+        ssss::split(&[1; 129], 2, 3, true),
+        Err(SsssError::SecretLength { size: 129 })
+    );
+    assert_eq!(
+        ssss::split(b"top$ecret", 4, 3, true),
+        Err(SsssError::InvalidThreshold {
+            threshold: 4,
+            number_of_shares: 3
+        })
+    );
+    assert_eq!(
+        ssss::combine(&shares(&MAN_PAGE_SHARES[..2]), 3, true),
+        Err(SsssError::NotEnoughShares {
+            found: 2,
+            required: 3
+        })
+    );
+    assert_eq!(
+        ssss::combine(&shares(&[MAN_PAGE_SHARES[0], MAN_PAGE_SHARES[0]]), 2, true),
+        Err(SsssError::DuplicateShare { index: 1 })
+    );
+    assert!(matches!(
+        ssss::combine(&shares(&["1-abc", "2-abc"]), 2, true),
+        Err(SsssError::InvalidShare { .. })
+    ));
+}
@@ -0,0 +1,118 @@
+use meta_secret_share_formats::vss::{self, Commitments, Share, VssError};
+
+#[test]
+fn round_trip() {
+    let long_secret = vec![0x5A; 600];
+    let secrets: [&[u8]; 5] = [b"", b"top$ecret", &[0, 0, 1], &[0xFF; 254], &long_secret];
+
+    for secret in secrets {
+        let (shares, commitments) = vss::split(secret, 2, 3).unwrap();
+        assert_eq!(shares.len(), 3);
+        assert_eq!(shares[0].index(), 1);
+        assert_eq!(commitments.threshold(), 2);
+
+        let restored = vss::combine(&shares[1..], &commitments).unwrap();
+        assert_eq!(restored, secret);
+    }
+}
+
+#[test]
+fn every_share_checks_alone() {
+    let (shares, commitments) = vss::split(b"top$ecret", 3, 5).unwrap();
+    for share in &shares {
+        vss::check(share, &commitments).unwrap();
+    }
+
+    let (_, other_commitments) = vss::split(b"top$ecret", 3, 5).unwrap();
+    assert_eq!(
+        vss::check(&shares[0], &other_commitments),
+        Err(VssError::ShareMismatch { index: 1 })
+    );
+}
+
+#[test]
+fn encoding() {
+    let (shares, commitments) = vss::split(b"top$ecret", 2, 3).unwrap();
+
+    let share = Share::decode(&shares[1].encode()).unwrap();
+    assert_eq!(share, shares[1]);
+    let decoded_commitments = Commitments::decode(&commitments.encode()).unwrap();
+    assert_eq!(decoded_commitments, commitments);
+    assert_eq!(decoded_commitments.fingerprint(), commitments.fingerprint());
+    assert_eq!(commitments.fingerprint().len(), 19);
+
+    assert_eq!(Share::decode("not a share"), Err(VssError::InvalidShare));
+    assert_eq!(
+        Commitments::decode(&shares[0].encode()),
+        Err(VssError::InvalidCommitments)
+    );
+}
+
+#[test]
+fn tampered_share() {
+    let (shares, commitments) = vss::split(b"top$ecret", 2, 3).unwrap();
+
+    let mut encoded = base64_bytes(&shares[2].encode());
+    let last = encoded.len() - 1;
+    encoded[last] ^= 1;
+    let tampered = Share::decode(&base64_text(&encoded)).unwrap();
+
+    assert_eq!(
+        vss::check(&tampered, &commitments),
+        Err(VssError::ShareMismatch { index: 3 })
+    );
+    assert_eq!(
+        vss::combine(&[shares[0].clone(), tampered], &commitments),
+        Err(VssError::ShareMismatch { index: 3 })
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        vss::split(b"top$ecret", 4, 3).unwrap_err(),
+        VssError::InvalidThreshold {
+            threshold: 4,
+            number_of_shares: 3
+        }
+    );
+
+    let (shares, commitments) = vss::split(b"top$ecret", 2, 3).unwrap();
+    assert_eq!(
+        vss::combine(&shares[..1], &commitments),
+        Err(VssError::NotEnoughShares {
+            found: 1,
+            required: 2
+        })
+    );
+    assert_eq!(
+        vss::combine(&[shares[0].clone(), shares[0].clone()], &commitments),
+        Err(VssError::DuplicateShare { index: 1 })
+    );
+}
+
+#[test]
+fn too_big_secret() {
+    // the number of chunks of 254 bytes is written as u16
+    let max_size = usize::from(u16::MAX) * 254;
+    let secret = vec![0x5A; max_size + 1];
+    assert_eq!(
+        vss::split(&secret, 2, 3).unwrap_err(),
+        VssError::TooBig {
+            size: max_size + 1,
+            max_size
+        }
+    );
+}
+
+fn base64_bytes(text: &str) -> Vec<u8> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD
+        .decode(text)
+        .unwrap()
+}
+
+fn base64_text(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
@@ -0,0 +1,138 @@
+use bip39::Language;
+use meta_secret_share_formats::words::{self, WordsError};
+
+const DATA: &[u8] = b"{\"share_id\":1,\"data\":\"c2hhcmUgZGF0YQ==\"}";
+
+fn encoded_words() -> Vec<String> {
+    words::encode(DATA)
+        .unwrap()
+        .split(' ')
+        .map(String::from)
+        .collect()
+}
+
+/// A word of the list other than the given one
+fn other_word(word: &str) -> String {
+    let word_list = Language::English.word_list();
+    let index = Language::English.find_word(word).unwrap() as usize;
+    word_list[(index + 1) % word_list.len()].to_string()
+}
+
+#[test]
+fn round_trip() {
+    for data in [&b""[..], b"x", DATA, &[0xffu8; 300][..]] {
+        let text = words::encode(data).unwrap();
+        assert_eq!(words::decode(&text).unwrap(), data);
+    }
+}
+
+#[test]
+fn words_shortened_to_four_letters() {
+    let shortened: Vec<String> = encoded_words()
+        .iter()
+        .map(|word| word.chars().take(4).collect())
+        .collect();
+    assert_eq!(words::decode(&shortened.join(" ")).unwrap(), DATA);
+}
+
+#[test]
+fn numbering_case_and_line_breaks_are_ignored() {
+    let numbered: Vec<String> = encoded_words()
+        .iter()
+        .enumerate()
+        .map(|(position, word)| format!("{}. {}", position + 1, word.to_uppercase()))
+        .collect();
+    assert_eq!(words::decode(&numbered.join("\n")).unwrap(), DATA);
+}
+
+#[test]
+fn unknown_word_position() {
+    let mut words = encoded_words();
+    words[4] = "xqzjv".to_string();
+
+    let err = words::decode(&words.join(" ")).unwrap_err();
+    assert_eq!(
+        err,
+        WordsError::UnknownWord {
+            position: 5,
+            word: "xqzjv".to_string(),
+            suggestions: vec![],
+        }
+    );
+}
+
+#[test]
+fn unknown_word_suggestions() {
+    let mut words = encoded_words();
+    words[0] = "abandom".to_string();
+
+    match words::decode(&words.join(" ")).unwrap_err() {
+        WordsError::UnknownWord {
+            position,
+            word,
+            suggestions,
+        } => {
+            assert_eq!(position, 1);
+            assert_eq!(word, "abandom");
+            assert_eq!(suggestions.first().map(String::as_str), Some("abandon"));
+        }
+        err => panic!("Unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn single_wrong_word_is_found() {
+    let mut words = encoded_words();
+    let original = words[7].clone();
+    words[7] = other_word(&original);
+
+    let err = words::decode(&words.join(" ")).unwrap_err();
+    assert_eq!(
+        err,
+        WordsError::WrongWord {
+            position: 8,
+            word: words[7].clone(),
+            suggestion: original,
+        }
+    );
+}
+
+#[test]
+fn several_wrong_words_fail_the_checksum() {
+    let mut words = encoded_words();
+    words[2] = other_word(&words[2]);
+    words[9] = other_word(&words[9]);
+
+    let err = words::decode(&words.join(" ")).unwrap_err();
+    assert_eq!(err, WordsError::ChecksumMismatch);
+}
+
+#[test]
+fn swapped_or_missing_words_fail_the_checksum() {
+    let mut words = encoded_words();
+    words.remove(3);
+    let err = words::decode(&words.join(" ")).unwrap_err();
+    assert_eq!(err, WordsError::ChecksumMismatch);
+
+    let mut words = encoded_words();
+    words.swap(3, 4);
+    let err = words::decode(&words.join(" ")).unwrap_err();
+    assert_eq!(err, WordsError::ChecksumMismatch);
+}
+
+#[test]
+fn too_few_words() {
+    assert_eq!(
+        words::decode("abandon ability").unwrap_err(),
+        WordsError::TooShort
+    );
+}
+
+#[test]
+fn too_big_data() {
+    let data = vec![0u8; usize::from(u16::MAX) + 1];
+    assert_eq!(
+        words::encode(&data).unwrap_err(),
+        WordsError::TooBig { size: data.len() }
+    );
+}
@@ -1,3 +1,4 @@
+use anyhow::Context;
 use meta_secret_core::models::{
     FindSharesRequest, JoinRequest, MembershipRequestType, MetaVault, SecretDistributionType,
     UserCredentials, UserSecurityBox, UserSignature, VaultDoc, VaultInfoData,
@@ -11,6 +12,8 @@ use meta_secret_core::shared_secret::shared_secret::{
     PlainText, SharedSecretEncryption, UserShareDto,
 };
 use meta_secret_core::shared_secret::MetaDistributor;
+use meta_secret_share_formats::{qr_parts, slip39, ssss, vss, words};
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 use crate::db::meta_pass;
@@ -265,14 +268,16 @@ pub async fn register() -> Result<JsValue, JsValue> {
     }
 }
 
+const SHARED_SECRET_CONFIG: SharedSecretConfig = SharedSecretConfig {
+    number_of_shares: 3,
+    threshold: 2,
+};
+
 /// https://rustwasm.github.io/docs/wasm-bindgen/reference/arbitrary-data-with-serde.html
 #[wasm_bindgen]
 pub fn split(pass: &str) -> Result<JsValue, JsValue> {
     let plain_text = PlainText::from(pass);
-    let config = SharedSecretConfig {
-        number_of_shares: 3,
-        threshold: 2,
-    };
+    let config = SHARED_SECRET_CONFIG;
     let shared_secret = SharedSecretEncryption::new(config, &plain_text).map_err(JsError::from)?;
 
     let mut res: Vec<UserShareDto> = vec![];
@@ -285,12 +290,211 @@ pub fn split(pass: &str) -> Result<JsValue, JsValue> {
     Ok(shares_js)
 }
 
+/// A share passed to restore_password: the share object or its word list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ShareInput {
+    Share(UserShareDto),
+    Words(String),
+}
+
+impl ShareInput {
+    fn into_share(self) -> anyhow::Result<UserShareDto> {
+        match self {
+            ShareInput::Share(share) => Ok(share),
+            ShareInput::Words(text) => {
+                let share_json = words::decode(&text)?;
+                Ok(serde_json::from_slice(&share_json)?)
+            }
+        }
+    }
+}
+
+/// Restores the password from the shares: share objects or word lists (a string per share).
+/// A mistyped word is reported with the number of the share and the position of the word.
+/// The texts of the qr codes of a share written as several parts (`msqr1:...`, see `split` of the cli)
+/// can be given in any order among the other shares, the missing parts are named
 #[wasm_bindgen]
 pub fn restore_password(shares_json: JsValue) -> Result<JsValue, JsValue> {
     log("wasm: restore password, core functionality");
 
-    let user_shares: Vec<UserShareDto> = serde_wasm_bindgen::from_value(shares_json)?;
-
+    let user_shares = user_shares(shares_json)?;
     let plain_text = recover_from_shares(user_shares).map_err(JsError::from)?;
     Ok(JsValue::from_str(plain_text.text.as_str()))
 }
+
+fn user_shares(shares_json: JsValue) -> Result<Vec<UserShareDto>, JsValue> {
+    let share_inputs: Vec<ShareInput> = serde_wasm_bindgen::from_value(shares_json)?;
+    let mut parts = vec![];
+    let mut other_inputs = vec![];
+    for (index, share_input) in share_inputs.into_iter().enumerate() {
+        match share_input {
+            ShareInput::Words(text) if qr_parts::is_part(&text) => parts.push((index, text)),
+            share_input => other_inputs.push((index, share_input)),
+        }
+    }
+
+    let mut user_shares = other_inputs
+        .into_iter()
+        .map(|(index, share_input)| {
+            share_input
+                .into_share()
+                .with_context(|| format!("Invalid share #{}", index + 1))
+        })
+        .collect::<anyhow::Result<Vec<UserShareDto>>>()
+        .map_err(|err| JsError::new(&format!("{:#}", err)))?;
+
+    let joined_shares = join_qr_parts(parts).map_err(|err| JsError::new(&format!("{:#}", err)))?;
+    user_shares.extend(joined_shares);
+
+    Ok(user_shares)
+}
+
+/// Puts the shares written as several qr codes back together. A part holds a piece of the json
+/// of the share file written by the cli, the core share is its `share` field.
+/// Only text secrets are passwords: files and keys of encrypted files are split as base64
+fn join_qr_parts(parts: Vec<(usize, String)>) -> anyhow::Result<Vec<UserShareDto>> {
+    let parts = parts
+        .into_iter()
+        .map(|(index, text)| {
+            qr_parts::parse(&text).with_context(|| format!("Invalid share #{}", index + 1))
+        })
+        .collect::<anyhow::Result<Vec<qr_parts::Part>>>()?;
+
+    qr_parts::join(&parts)?
+        .into_iter()
+        .map(|joined_share| {
+            let mut share_file: serde_json::Value = serde_json::from_str(&joined_share.data)?;
+            match share_file.pointer("/secret/encoding") {
+                None => {}
+                Some(encoding) if encoding == "text" => {}
+                Some(encoding) => anyhow::bail!(
+                    "The share {} put together from qr code parts holds a {} secret, not a password \
+                     (files and hybrid mode shares are restored with the cli)",
+                    joined_share.checksum,
+                    encoding
+                ),
+            }
+            let share = match share_file.get_mut("share") {
+                Some(share) => share.take(),
+                None => share_file,
+            };
+            serde_json::from_value(share).with_context(|| {
+                format!(
+                    "The share {} put together from qr code parts is not a plain share \
+                     (encrypted, verifiable and policy shares are restored with the cli)",
+                    joined_share.checksum
+                )
+            })
+        })
+        .collect()
+}
+
+/// Options of import_shares and export_shares, all of them are optional:
+/// `{ threshold: 3, noDiffusion: true, passphrase: "..." }`
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ConvertOptions {
+    /// ssss: the threshold of the shares to import, ssss shares don't store it
+    threshold: Option<usize>,
+    /// ssss: the shares are made without the diffusion layer (`ssss-split -D`)
+    no_diffusion: bool,
+    /// SLIP-39 passphrase, empty by default
+    passphrase: String,
+}
+
+impl ConvertOptions {
+    fn from_js(options: JsValue) -> Result<Self, JsValue> {
+        if options.is_undefined() || options.is_null() {
+            return Ok(ConvertOptions::default());
+        }
+        Ok(serde_wasm_bindgen::from_value(options)?)
+    }
+}
+
+/// Makes meta secret shares (like `split`) of the password restored from the shares of another tool:
+/// `format` is "slip39" or "ssss", `shares` is an array of strings, one per share
+#[wasm_bindgen]
+pub fn import_shares(format: &str, shares: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    let shares: Vec<String> = serde_wasm_bindgen::from_value(shares)?;
+    let options = ConvertOptions::from_js(options)?;
+
+    let secret = match format {
+        "slip39" => slip39::combine(&shares, &options.passphrase).map_err(JsError::from)?,
+        "ssss" => {
+            let threshold = options.threshold.ok_or_else(|| {
+                JsError::new("ssss shares don't store the threshold, it has to be given")
+            })?;
+            let secret =
+                ssss::combine(&shares, threshold, !options.no_diffusion).map_err(JsError::from)?;
+            ssss::text_secret(&secret).to_vec()
+        }
+        _ => return Err(JsError::new(&format!("Unknown share format: {}", format)).into()),
+    };
+
+    let pass = String::from_utf8(secret).map_err(|_| {
+        JsError::new("The imported secret is binary data (e.g. a wallet seed), only text passwords can be split here")
+    })?;
+    split(&pass)
+}
+
+/// Restores the password from meta secret shares (objects or word lists) and splits it
+/// into 3 shares of another tool ("slip39" or "ssss"), 2 of them restore the password
+#[wasm_bindgen]
+pub fn export_shares(
+    format: &str,
+    shares_json: JsValue,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = ConvertOptions::from_js(options)?;
+    let plain_text = recover_from_shares(user_shares(shares_json)?).map_err(JsError::from)?;
+    let secret = plain_text.text.as_bytes();
+
+    let config = SHARED_SECRET_CONFIG;
+    let shares = match format {
+        "slip39" => slip39::split(
+            secret,
+            &options.passphrase,
+            config.threshold,
+            config.number_of_shares,
+        )
+        .map_err(JsError::from)?,
+        "ssss" => ssss::split(
+            secret,
+            config.threshold,
+            config.number_of_shares,
+            !options.no_diffusion,
+        )
+        .map_err(JsError::from)?,
+        _ => return Err(JsError::new(&format!("Unknown share format: {}", format)).into()),
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&shares)?)
+}
+
+/// Result of check_share
+#[derive(Serialize)]
+struct CheckedShare {
+    /// Index of the share, starting from 1
+    index: usize,
+    threshold: usize,
+    /// Fingerprint of the commitments, the same for all the shares of one secret
+    fingerprint: String,
+}
+
+/// Checks a verifiable share (made by `split --verifiable` of the cli) alone, without the other shares:
+/// `share` and `commitments` are the `vss_share` and `commitments` fields of the share file.
+/// Returns `{ index, threshold, fingerprint }`, the holders compare the fingerprints with each other
+#[wasm_bindgen]
+pub fn check_share(share: &str, commitments: &str) -> Result<JsValue, JsValue> {
+    let vss_share = vss::Share::decode(share).map_err(JsError::from)?;
+    let commitments = vss::Commitments::decode(commitments).map_err(JsError::from)?;
+    vss::check(&vss_share, &commitments).map_err(JsError::from)?;
+
+    let checked_share = CheckedShare {
+        index: vss_share.index(),
+        threshold: commitments.threshold(),
+        fingerprint: commitments.fingerprint(),
+    };
+    Ok(serde_wasm_bindgen::to_value(&checked_share)?)
+}
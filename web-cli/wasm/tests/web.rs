@@ -3,6 +3,10 @@
 #![cfg(target_arch = "wasm32")]
 
 extern crate wasm_bindgen_test;
+use meta_secret_share_formats::qr_parts;
+use meta_secret_web_cli::{restore_password, split};
+use serde_json::json;
+use wasm_bindgen::JsValue;
 use wasm_bindgen_test::*;
 
 wasm_bindgen_test_configure!(run_in_browser);
@@ -11,3 +15,36 @@ wasm_bindgen_test_configure!(run_in_browser);
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+/// Texts of the qr code parts of two shares of the password, written by the cli with the `encoding`
+fn qr_part_texts(password: &str, encoding: &str) -> JsValue {
+    let shares: Vec<serde_json::Value> =
+        serde_wasm_bindgen::from_value(split(password).unwrap()).unwrap();
+
+    let mut texts = vec![];
+    for share in shares.iter().take(2) {
+        let share_file = json!({
+            "version": 1,
+            "secret": { "encoding": encoding },
+            "share": share,
+        });
+        texts.extend(qr_parts::split(&share_file.to_string(), "set", 200).unwrap());
+    }
+    serde_wasm_bindgen::to_value(&texts).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn qr_parts_of_a_password() {
+    let password = restore_password(qr_part_texts("correct horse", "text")).unwrap();
+    assert_eq!(password.as_string().unwrap(), "correct horse");
+}
+
+#[wasm_bindgen_test]
+fn qr_parts_of_a_file_are_refused() {
+    // `split --file` and `split --hybrid` split base64, it is never shown as the password
+    for encoding in ["base64", "data_key"] {
+        let err = restore_password(qr_part_texts("Y29ycmVjdCBob3JzZQ==", encoding)).unwrap_err();
+        let message = String::from(js_sys::Error::from(err).message());
+        assert!(message.contains("not a password"), "{}", message);
+    }
+}